@@ -1,24 +1,197 @@
 use address_scanner::AddressProvider;
-use once_cell::sync::Lazy;
+use once_cell::{sync::Lazy, unsync::OnceCell};
 use std::{
     any::{Any, TypeId},
     collections::HashMap,
+    ffi::{c_char, CStr},
+    fmt,
+    path::Path,
     sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use super::address_cache::{image_hash, AddressCache, AddressCacheError, BuildId};
+use crate::utils::{
+    BatchScanner, Mismatch, Pattern, PatternMatch, PatternScan, PatternScanError, PeError, PeImage,
+    RipOperand, SectionFilter,
 };
 
 pub type SharedAddressRepository = Arc<Mutex<AddressRepository>>;
 
+/// 健康报告中每条记录最多列出的接近位置
+const NEAR_MISS_LIMIT: usize = 3;
+
 static ADDRESS_REPOSITORY: Lazy<SharedAddressRepository> =
     Lazy::new(|| Arc::new(Mutex::new(AddressRepository::new())));
 
+/// 定义特征码记录
+///
+/// 在 `#[derive(AddressRecord)]` 的基础上实现 [`SignatureRecord`]，保留特征码信息，
+/// 并为所在模块生成包含全部记录的 `RECORDS` 列表。
+///
+/// 可选的 `#[fallbacks(...)]` 按顺序列出主特征码失效时的备用方式，见 [`Strategy`]：
+///
+/// ```ignore
+/// #[derive(AddressRecord)]
+/// #[record(pattern = "4C 89 B3 10 76 00 00", offset = -60)]
+/// #[fallbacks(
+///     Pattern { pattern: "4C 89 B3 ?? 76 00 00 48 8B", offset: -60 },
+///     FollowOperand { pattern: "E8 ?? ?? ?? ?? 48 8B D8", offset: 0 },
+///     KnownRva { revision: "421631", rva: 0x1C0F0A0 },
+/// )]
+/// pub struct Ctor;
+/// ```
+macro_rules! address_records {
+    ($(
+        $(#[doc = $doc:literal])*
+        #[derive(AddressRecord)]
+        #[record(pattern = $pattern:literal, offset = $($offset:tt)+)]
+        $(#[fallbacks($($fallback:expr),* $(,)?)])?
+        pub struct $name:ident;
+    )*) => {
+        $(
+            $(#[doc = $doc])*
+            #[derive(AddressRecord)]
+            #[record(pattern = $pattern, offset = $($offset)+)]
+            pub struct $name;
+
+            impl super::SignatureRecord for $name {
+                const INFO: super::RecordInfo = super::RecordInfo {
+                    module: module_path!(),
+                    name: stringify!($name),
+                    pattern: $pattern,
+                    offset: $($offset)+,
+                    fallbacks: {
+                        #[allow(unused_imports)]
+                        use super::Strategy::*;
+                        &[$($($fallback),*)?]
+                    },
+                    type_id: std::any::TypeId::of::<$name>,
+                };
+            }
+        )*
+
+        /// 本模块中定义的所有特征码记录
+        pub const RECORDS: &[super::RecordInfo] = &[
+            $(<$name as super::SignatureRecord>::INFO),*
+        ];
+    };
+}
+
+/// 特征码记录的静态信息
+#[derive(Debug, Clone, Copy)]
+pub struct RecordInfo {
+    /// 定义记录的模块路径
+    pub module: &'static str,
+    pub name: &'static str,
+    pub pattern: &'static str,
+    /// 匹配地址到目标地址的偏移
+    pub offset: isize,
+    /// 主特征码没有唯一匹配时依次尝试的备用方式
+    pub fallbacks: &'static [Strategy],
+    pub type_id: fn() -> TypeId,
+}
+
+impl RecordInfo {
+    /// 带分类的记录名，例如 `monster::Ctor`
+    pub fn full_name(&self) -> String {
+        let category = self.module.rsplit("::").next().unwrap_or_default();
+        format!("{}::{}", category, self.name)
+    }
+
+    /// 解析特征码，语法见 [`Pattern`]
+    pub fn parse_pattern(&self) -> Result<Pattern, PatternScanError> {
+        Pattern::parse(self.pattern)
+    }
+}
+
+/// 特征码记录的备用解析方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// 备用特征码，匹配地址加偏移
+    Pattern {
+        pattern: &'static str,
+        offset: isize,
+    },
+    /// 特征码匹配地址加偏移处指令（`call rel32`、`lea`、`mov` 等）的相对寻址目标
+    ///
+    /// 用于通过调用点或引用处定位函数与全局变量，函数本身的字节变化时仍然有效。
+    FollowOperand {
+        pattern: &'static str,
+        offset: isize,
+    },
+    /// 已知版本的RVA，仅在 `GetGameBuildRevision` 返回的版本号相同时使用
+    ///
+    /// 版本号只通过 `GetGameBuildRevision` 的主特征码获取，该记录本身不能使用此方式。
+    KnownRva { revision: &'static str, rva: usize },
+}
+
+/// 成功的备用方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FallbackHit {
+    /// 在 [`RecordInfo::fallbacks`] 中的序号
+    pub index: usize,
+    pub strategy: Strategy,
+    /// 目标地址（已加偏移）的RVA
+    pub rva: usize,
+    pub va: usize,
+}
+
+/// 保留了特征码信息的地址记录
+pub trait SignatureRecord: AddressProvider {
+    const INFO: RecordInfo;
+}
+
+/// 所有内置的特征码记录
+pub fn all_records() -> Vec<&'static RecordInfo> {
+    [
+        core::RECORDS,
+        monster::RECORDS,
+        inline::RECORDS,
+        player::RECORDS,
+        chat::RECORDS,
+        quest::RECORDS,
+        action::RECORDS,
+        weapon::RECORDS,
+        steamwork::RECORDS,
+        c_system::RECORDS,
+    ]
+    .into_iter()
+    .flatten()
+    .collect()
+}
+
 pub struct AddressRepository {
     cache: HashMap<TypeId, usize>,
+    image: Option<PeImage>,
+    /// `GetGameBuildRevision` 的解析结果，只计算一次
+    revision: OnceCell<GameRevision>,
+}
+
+/// `GetGameBuildRevision` 的RVA与返回的版本号
+#[derive(Debug, Clone, Default)]
+struct GameRevision {
+    rva: Option<usize>,
+    revision: Option<String>,
 }
 
 impl AddressRepository {
     fn new() -> Self {
         Self {
             cache: HashMap::new(),
+            image: None,
+            revision: OnceCell::new(),
+        }
+    }
+
+    /// 创建针对指定模块镜像的独立仓库
+    ///
+    /// 通常用于解析从磁盘读取的可执行文件（见 [`PeImage::from_file`]），无需运行游戏。
+    pub fn with_image(image: PeImage) -> Self {
+        Self {
+            cache: HashMap::new(),
+            image: Some(image),
+            revision: OnceCell::new(),
         }
     }
 
@@ -26,10 +199,24 @@ impl AddressRepository {
         ADDRESS_REPOSITORY.clone()
     }
 
+    pub fn image(&self) -> Option<&PeImage> {
+        self.image.as_ref()
+    }
+
+    /// 通过 `AddressProvider` 获取地址
+    ///
+    /// 直接扫描游戏主模块，不使用备用方式；特征码记录请使用 [`get_record_address`](Self::get_record_address)。
+    /// `AddressProvider` 只能扫描运行中的进程，仓库绑定了模块镜像时返回错误。
     pub fn get_address(&mut self, provider: impl AddressProvider) -> Result<usize, String> {
         if let Some(addr) = self.cache.get(&provider.type_id()) {
             return Ok(*addr);
         }
+        if self.image.is_some() {
+            return Err(format!(
+                "Failed to get address of {}: cannot scan a bound image, use get_record_address",
+                provider.name()
+            ));
+        }
 
         match provider.get_address() {
             Ok(addr) => {
@@ -43,199 +230,1245 @@ impl AddressRepository {
             )),
         }
     }
+
+    /// 获取特征码记录的地址
+    ///
+    /// 与 `get_address` 共用缓存，但使用 [`Pattern`] 解析特征码，支持捕获、半字节通配等完整语法。
+    /// 若仓库绑定了模块镜像，则在镜像中扫描，否则扫描游戏主模块。
+    pub fn get_record_address<R: SignatureRecord>(&mut self, _record: R) -> Result<usize, String> {
+        if let Some(addr) = self.cache.get(&TypeId::of::<R>()) {
+            return Ok(*addr);
+        }
+
+        let addr = self
+            .resolve(&R::INFO)
+            .address()
+            .map_err(|e| format!("Failed to get address of {}: {}", R::INFO.full_name(), e))?;
+        self.cache.insert(TypeId::of::<R>(), addr);
+        Ok(addr)
+    }
+
+    /// 在绑定的镜像（未绑定时为游戏主模块）中扫描特征码记录
+    pub fn resolve(&self, info: &RecordInfo) -> RecordResolution {
+        let main_module;
+        let image = match &self.image {
+            Some(image) => image,
+            None => match PeImage::main_module() {
+                Ok(image) => {
+                    main_module = image;
+                    &main_module
+                }
+                Err(e) => return RecordResolution::failed(info, e.into()),
+            },
+        };
+
+        let mut resolution = RecordResolution {
+            name: info.full_name(),
+            offset: info.offset,
+            result: scan_record(image, info),
+            fallback: None,
+        };
+        if !resolution.is_unique() {
+            resolution.fallback = self.try_fallbacks(image, info);
+        }
+        resolution
+    }
+
+    /// 依次尝试备用方式，返回第一个成功的
+    fn try_fallbacks(&self, image: &PeImage, info: &RecordInfo) -> Option<FallbackHit> {
+        // 离线镜像无法调用游戏函数获取版本号，此时版本号为None
+        resolve_fallbacks(image, info.fallbacks, || self.game_build_revision())
+    }
+
+    /// 解码特征码记录所指指令的相对寻址操作数
+    ///
+    /// 记录的地址（匹配加偏移）应指向 `mov reg, [rip+disp32]`、`lea`、`call rel32` 等指令，
+    /// 用于通过特征码定位全局变量或被调用的函数，而不是硬编码地址。
+    pub fn resolve_rip_operand(&self, info: &RecordInfo) -> Result<RipOperand, PatternScanError> {
+        let resolution = self.resolve(info);
+        let matched = resolution.unique_match()?;
+        let operand = match &self.image {
+            Some(image) => matched.rip_operand(image, info.offset)?,
+            None => matched.rip_operand(&PeImage::main_module()?, info.offset)?,
+        };
+        Ok(operand)
+    }
+
+    /// 一次遍历扫描所有特征码记录，可用于检查唯一性或对比不同版本
+    pub fn resolve_all(&self, records: &[&RecordInfo]) -> Vec<RecordResolution> {
+        self.scan_records(records).0
+    }
+
+    /// 一次遍历解析所有特征码记录，并将唯一匹配的地址写入缓存
+    ///
+    /// 之后对这些记录调用 `get_record_address` 将直接命中缓存。
+    pub fn preload(&mut self, records: &[&RecordInfo]) -> PreloadReport {
+        let (resolutions, scan_time) = self.scan_records(records);
+        let mut resolved = 0;
+        for (info, resolution) in records.iter().zip(resolutions.iter()) {
+            if let Ok(addr) = resolution.address() {
+                self.cache.insert((info.type_id)(), addr);
+                resolved += 1;
+            }
+            if let Some(fallback) = &resolution.fallback {
+                log::warn!(
+                    "{} resolved by fallback #{} {:?}",
+                    resolution.name,
+                    fallback.index,
+                    fallback.strategy
+                );
+            }
+        }
+        log::debug!(
+            "preloaded {}/{} address records in {:?}",
+            resolved,
+            records.len(),
+            scan_time
+        );
+
+        PreloadReport {
+            resolutions,
+            resolved,
+            cached: 0,
+            scan_time,
+        }
+    }
+
+    /// 先读取地址缓存，只扫描缓存中没有的记录，并将结果写回缓存文件
+    ///
+    /// 游戏未更新时无需扫描；游戏更新后缓存自动失效，重新扫描所有记录。
+    /// 返回的报告中 `resolutions` 只包含本次扫描的记录。
+    pub fn preload_cached(
+        &mut self,
+        path: impl AsRef<Path>,
+        records: &[&RecordInfo],
+    ) -> Result<PreloadReport, AddressCacheError> {
+        let path = path.as_ref();
+        let file = match AddressCache::load(path) {
+            Ok(cache) => Some(cache),
+            Err(AddressCacheError::Io(e)) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(AddressCacheError::Format(e)) => {
+                log::warn!("ignored invalid address cache {}: {}", path.display(), e);
+                None
+            }
+            Err(e) => return Err(e),
+        };
+        let build = self.cache_build_id(file.as_ref())?;
+        let cached = match &file {
+            Some(cache) => self.apply_cache(cache, &build, records)?,
+            None => 0,
+        };
+
+        let missing: Vec<&RecordInfo> = records
+            .iter()
+            .copied()
+            .filter(|info| !self.cache.contains_key(&(info.type_id)()))
+            .collect();
+        if missing.is_empty() {
+            return Ok(PreloadReport {
+                resolutions: Vec::new(),
+                resolved: 0,
+                cached,
+                scan_time: Duration::ZERO,
+            });
+        }
+
+        let mut report = self.preload(&missing);
+        report.cached = cached;
+        if report.resolved > 0 {
+            self.write_cache(&build, path, records)?;
+        }
+        Ok(report)
+    }
+
+    /// 从文件读取地址缓存并写入内存缓存，返回读取的记录数
+    ///
+    /// 缓存对应的游戏版本与当前不同时视为失效，返回0。
+    pub fn load_cache(
+        &mut self,
+        path: impl AsRef<Path>,
+        records: &[&RecordInfo],
+    ) -> Result<usize, AddressCacheError> {
+        let cache = AddressCache::load(path)?;
+        let build = self.cache_build_id(Some(&cache))?;
+        self.apply_cache(&cache, &build, records)
+    }
+
+    fn apply_cache(
+        &mut self,
+        cache: &AddressCache,
+        build: &BuildId,
+        records: &[&RecordInfo],
+    ) -> Result<usize, AddressCacheError> {
+        if cache.build() != build {
+            log::debug!(
+                "address cache is outdated: {:?}, current build {:?}",
+                cache.build(),
+                build
+            );
+            return Ok(0);
+        }
+
+        let base = self.image_base()?;
+        let mut loaded = 0;
+        for info in records.iter() {
+            if let Some(rva) = cache.get(&info.full_name()) {
+                self.cache.insert((info.type_id)(), base + rva);
+                loaded += 1;
+            }
+        }
+        Ok(loaded)
+    }
+
+    /// 将已解析的记录写入地址缓存文件
+    pub fn save_cache(
+        &self,
+        path: impl AsRef<Path>,
+        records: &[&RecordInfo],
+    ) -> Result<(), AddressCacheError> {
+        self.write_cache(&self.build_id()?, path, records)
+    }
+
+    fn write_cache(
+        &self,
+        build: &BuildId,
+        path: impl AsRef<Path>,
+        records: &[&RecordInfo],
+    ) -> Result<(), AddressCacheError> {
+        let mut cache = AddressCache::new(build.clone());
+        let base = self.image_base()?;
+        for info in records.iter() {
+            if let Some(addr) = self.cache.get(&(info.type_id)()) {
+                cache.insert(&info.full_name(), addr - base);
+            }
+        }
+        cache.save(path)
+    }
+
+    /// 当前游戏版本的标识，用于校验地址缓存
+    ///
+    /// 绑定了模块镜像时无法调用游戏函数，只使用镜像哈希。
+    pub fn build_id(&self) -> Result<BuildId, AddressCacheError> {
+        self.build_id_with_hint(None)
+    }
+
+    /// 与缓存文件比较用的版本标识
+    ///
+    /// 先比较PE头部哈希，一致时使用缓存中记录的 `GetGameBuildRevision` 地址，确认后直接调用，无需扫描。
+    fn cache_build_id(&self, cache: Option<&AddressCache>) -> Result<BuildId, AddressCacheError> {
+        let hint = match cache {
+            Some(cache) if cache.build().image_hash == self.image_hash()? => {
+                cache.build().revision_rva
+            }
+            _ => None,
+        };
+        self.build_id_with_hint(hint)
+    }
+
+    fn build_id_with_hint(&self, hint: Option<usize>) -> Result<BuildId, AddressCacheError> {
+        match &self.image {
+            Some(image) => Ok(BuildId::new(image, None, None)),
+            None => {
+                let revision = self.game_revision(hint);
+                Ok(BuildId::new(
+                    &PeImage::main_module()?,
+                    revision.revision.clone(),
+                    revision.rva,
+                ))
+            }
+        }
+    }
+
+    /// 调用 `GetGameBuildRevision` 读取游戏版本号，绑定了模块镜像或无法解析时为None
+    pub fn game_build_revision(&self) -> Option<String> {
+        self.game_revision(None).revision.clone()
+    }
+
+    /// 解析并调用 `GetGameBuildRevision`，结果只计算一次
+    ///
+    /// 只使用主特征码而不使用备用方式，[`Strategy::KnownRva`] 本身依赖版本号。
+    /// `hint` 为地址缓存中记录的RVA，确认该处仍是此函数后使用，否则重新扫描。
+    fn game_revision(&self, hint: Option<usize>) -> &GameRevision {
+        self.revision.get_or_init(|| {
+            // 离线镜像无法调用游戏函数
+            if self.image.is_some() {
+                return GameRevision::default();
+            }
+            let Ok(image) = PeImage::main_module() else {
+                return GameRevision::default();
+            };
+            let info = &core::GetGameBuildRevision::INFO;
+            let rva = hint
+                .filter(|&rva| is_record_at(&image, info, rva))
+                .or_else(|| match scan_record(&image, info).as_deref() {
+                    Ok([m]) => Some(m.rva.wrapping_add_signed(info.offset)),
+                    _ => None,
+                });
+            GameRevision {
+                rva,
+                revision: rva.and_then(|rva| unsafe { call_revision(image.rva_to_va(rva)) }),
+            }
+        })
+    }
+
+    fn image_hash(&self) -> Result<u64, PeError> {
+        match &self.image {
+            Some(image) => Ok(image_hash(image)),
+            None => Ok(image_hash(&PeImage::main_module()?)),
+        }
+    }
+
+    fn image_base(&self) -> Result<usize, PeError> {
+        match &self.image {
+            Some(image) => Ok(image.base()),
+            None => Ok(PeImage::main_module()?.base()),
+        }
+    }
+
+    /// 诊断单条特征码记录，见 [`AddressRepository::health_report`]
+    pub fn diagnose(&self, info: &RecordInfo) -> RecordDiagnosis {
+        self.health_report(&[info]).records.remove(0)
+    }
+
+    /// 扫描所有特征码记录并生成健康报告
+    ///
+    /// 游戏更新后用于快速确认哪些特征码失效：列出每条记录的匹配数量与候选地址，
+    /// 没有匹配的记录附带最接近的几个位置，以及其中与特征码不一致的字节。
+    ///
+    /// ```ignore
+    /// let report = AddressRepository::get_instance().lock().unwrap().health_report(&all_records());
+    /// info!("{}", report);
+    /// ```
+    pub fn health_report(&self, records: &[&RecordInfo]) -> HealthReport {
+        let start_time = Instant::now();
+        let (resolutions, _) = self.scan_records(records);
+
+        let main_module;
+        let image = match &self.image {
+            Some(image) => Some(image),
+            None => {
+                main_module = PeImage::main_module().ok();
+                main_module.as_ref()
+            }
+        };
+        let records = records
+            .iter()
+            .zip(resolutions)
+            .map(|(info, resolution)| {
+                let near_misses = match (&resolution.result, image) {
+                    (Ok(matches), Some(image)) if matches.is_empty() => near_misses(image, info),
+                    _ => Vec::new(),
+                };
+                RecordDiagnosis::new(info, resolution, near_misses)
+            })
+            .collect();
+
+        HealthReport {
+            records,
+            elapsed: start_time.elapsed(),
+        }
+    }
+
+    fn scan_records(&self, records: &[&RecordInfo]) -> (Vec<RecordResolution>, Duration) {
+        let mut scanner = BatchScanner::new();
+        let patterns: Vec<_> = records
+            .iter()
+            .map(|info| info.parse_pattern().map(|pattern| scanner.add(pattern)))
+            .collect();
+
+        let main_module;
+        let image = match &self.image {
+            Some(image) => image,
+            None => match PeImage::main_module() {
+                Ok(image) => {
+                    main_module = image;
+                    &main_module
+                }
+                Err(e) => {
+                    let resolutions = records
+                        .iter()
+                        .map(|info| RecordResolution::failed(info, e.clone().into()))
+                        .collect();
+                    return (resolutions, Duration::ZERO);
+                }
+            },
+        };
+        let mut report = scanner.scan_module(image, SectionFilter::Executable);
+
+        let resolutions = records
+            .iter()
+            .zip(patterns)
+            .map(|(info, pattern)| {
+                let mut resolution = RecordResolution {
+                    name: info.full_name(),
+                    offset: info.offset,
+                    result: pattern.map(|index| std::mem::take(&mut report.matches[index])),
+                    fallback: None,
+                };
+                if !resolution.is_unique() {
+                    resolution.fallback = self.try_fallbacks(image, info);
+                }
+                resolution
+            })
+            .collect();
+        (resolutions, report.elapsed)
+    }
+}
+
+/// 批量解析的结果
+#[derive(Debug)]
+pub struct PreloadReport {
+    pub resolutions: Vec<RecordResolution>,
+    /// 成功写入缓存的记录数
+    pub resolved: usize,
+    /// 从地址缓存文件读取的记录数
+    pub cached: usize,
+    /// 扫描耗时
+    pub scan_time: Duration,
+}
+
+fn scan_record(image: &PeImage, info: &RecordInfo) -> Result<Vec<PatternMatch>, PatternScanError> {
+    let pattern = info.parse_pattern()?;
+    Ok(PatternScan::scan_module(
+        image,
+        SectionFilter::Executable,
+        &pattern,
+    ))
+}
+
+/// 在镜像中依次尝试备用方式，返回第一个成功的
+///
+/// `revision` 返回当前的游戏版本号，仅用于 [`Strategy::KnownRva`]。
+pub(crate) fn resolve_fallbacks(
+    image: &PeImage,
+    fallbacks: &[Strategy],
+    revision: impl Fn() -> Option<String>,
+) -> Option<FallbackHit> {
+    fallbacks.iter().enumerate().find_map(|(index, strategy)| {
+        let va = match *strategy {
+            Strategy::Pattern { pattern, offset } => {
+                unique_match(image, pattern)?.va.wrapping_add_signed(offset)
+            }
+            Strategy::FollowOperand { pattern, offset } => {
+                unique_match(image, pattern)?
+                    .rip_operand(image, offset)
+                    .ok()?
+                    .target
+            }
+            Strategy::KnownRva {
+                revision: known,
+                rva,
+            } => {
+                if revision().as_deref() != Some(known) {
+                    return None;
+                }
+                image.rva_to_va(rva)
+            }
+        };
+        Some(FallbackHit {
+            index,
+            strategy: *strategy,
+            rva: image.va_to_rva(va)?,
+            va,
+        })
+    })
+}
+
+/// 记录（加偏移后）的RVA处是否位于可执行节中，且与特征码匹配
+fn is_record_at(image: &PeImage, info: &RecordInfo, rva: usize) -> bool {
+    let Ok(pattern) = info.parse_pattern() else {
+        return false;
+    };
+    let executable = image.sections().iter().any(|s| {
+        let start = s.virtual_address as usize;
+        s.is_executable() && (start..start + s.virtual_size as usize).contains(&rva)
+    });
+    let start = rva.wrapping_add_signed(-info.offset);
+    let len = pattern.max_len().min(image.size().saturating_sub(start));
+    executable
+        && image
+            .bytes_at(start, len)
+            .is_some_and(|code| pattern.match_at(code, 0).is_some())
+}
+
+/// 调用 `addr` 处的 `GetGameBuildRevision`
+///
+/// # Safety
+///
+/// `addr` 必须是已确认的 `GetGameBuildRevision` 地址。
+unsafe fn call_revision(addr: usize) -> Option<String> {
+    let get_revision: extern "C" fn() -> *const c_char = std::mem::transmute(addr);
+    let revision = get_revision();
+    if revision.is_null() {
+        return None;
+    }
+    Some(CStr::from_ptr(revision).to_string_lossy().to_string())
+}
+
+/// 备用特征码的唯一匹配，特征码无效或匹配不唯一时为None
+fn unique_match(image: &PeImage, pattern: &str) -> Option<PatternMatch> {
+    let pattern = Pattern::parse(pattern).ok()?;
+    let mut matches = PatternScan::scan_module(image, SectionFilter::Executable, &pattern);
+    (matches.len() == 1).then(|| matches.remove(0))
+}
+
+/// 在可执行节中查找与特征码最接近的位置
+///
+/// 最多允许四分之一的字节不一致，避免过短的特征码在任意位置都能部分匹配。
+fn near_misses(image: &PeImage, info: &RecordInfo) -> Vec<NearMiss> {
+    let Ok(pattern) = info.parse_pattern() else {
+        return Vec::new();
+    };
+    let max_mismatches = (pattern.min_len() / 4).max(1);
+    let mut result: Vec<NearMiss> = image
+        .sections()
+        .iter()
+        .filter(|s| SectionFilter::Executable.matches(s))
+        .flat_map(|section| {
+            pattern
+                .find_partial(
+                    image.section_bytes(section),
+                    max_mismatches,
+                    NEAR_MISS_LIMIT,
+                )
+                .into_iter()
+                .map(|hit| {
+                    let rva = section.virtual_address as usize + hit.start;
+                    NearMiss {
+                        rva,
+                        va: image.rva_to_va(rva),
+                        mismatches: hit.mismatches,
+                    }
+                })
+        })
+        .collect();
+    result.sort_by_key(|m| m.mismatches.len());
+    result.truncate(NEAR_MISS_LIMIT);
+    result
+}
+
+/// 特征码记录的扫描结果
+#[derive(Debug)]
+pub struct RecordResolution {
+    pub name: String,
+    pub offset: isize,
+    /// 主特征码的所有匹配（未加偏移）
+    pub result: Result<Vec<PatternMatch>, PatternScanError>,
+    /// 主特征码没有唯一匹配时，成功的备用方式
+    pub fallback: Option<FallbackHit>,
+}
+
+impl RecordResolution {
+    fn failed(info: &RecordInfo, error: PatternScanError) -> Self {
+        Self {
+            name: info.full_name(),
+            offset: info.offset,
+            result: Err(error),
+            fallback: None,
+        }
+    }
+
+    /// 匹配数量，特征码无效时为0
+    pub fn match_count(&self) -> usize {
+        self.result.as_ref().map(|m| m.len()).unwrap_or(0)
+    }
+
+    pub fn is_unique(&self) -> bool {
+        self.match_count() == 1
+    }
+
+    /// 唯一匹配加偏移后的RVA，主特征码失效时为备用方式的结果
+    pub fn rva(&self) -> Result<usize, PatternScanError> {
+        match (self.unique_match(), &self.fallback) {
+            (Err(_), Some(fallback)) => Ok(fallback.rva),
+            (result, _) => result.map(|m| m.rva.wrapping_add_signed(self.offset)),
+        }
+    }
+
+    /// 唯一匹配加偏移后的虚拟地址，主特征码失效时为备用方式的结果
+    pub fn address(&self) -> Result<usize, PatternScanError> {
+        match (self.unique_match(), &self.fallback) {
+            (Err(_), Some(fallback)) => Ok(fallback.va),
+            (result, _) => result.map(|m| m.va.wrapping_add_signed(self.offset)),
+        }
+    }
+
+    /// 唯一匹配（未加偏移），可从中读取捕获的操作数
+    pub fn unique_match(&self) -> Result<&PatternMatch, PatternScanError> {
+        match self.result.as_deref() {
+            Ok([m]) => Ok(m),
+            Ok([]) => Err(PatternScanError::NotFound),
+            Ok(_) => Err(PatternScanError::MultipleMatchesFound),
+            Err(e) => Err(e.clone()),
+        }
+    }
+}
+
+/// 两次扫描之间发生变化的记录
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordDiff {
+    pub name: String,
+    pub old_rva: Option<usize>,
+    pub new_rva: Option<usize>,
+}
+
+/// 对比两个版本的扫描结果，返回RVA发生变化（包括失效）的记录
+pub fn diff_resolutions(old: &[RecordResolution], new: &[RecordResolution]) -> Vec<RecordDiff> {
+    let old_rvas: HashMap<&str, Option<usize>> = old
+        .iter()
+        .map(|r| (r.name.as_str(), r.rva().ok()))
+        .collect();
+
+    new.iter()
+        .filter_map(|r| {
+            let old_rva = old_rvas.get(r.name.as_str()).copied().flatten();
+            let new_rva = r.rva().ok();
+            (old_rva != new_rva).then(|| RecordDiff {
+                name: r.name.clone(),
+                old_rva,
+                new_rva,
+            })
+        })
+        .collect()
+}
+
+/// 与特征码接近但不完全一致的位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NearMiss {
+    /// 匹配起点的RVA（未加偏移）
+    pub rva: usize,
+    pub va: usize,
+    pub mismatches: Vec<Mismatch>,
+}
+
+/// 单条特征码记录的诊断结果
+#[derive(Debug)]
+pub struct RecordDiagnosis {
+    pub name: String,
+    pub pattern: &'static str,
+    pub offset: isize,
+    /// 所有匹配加偏移后的虚拟地址
+    pub candidates: Vec<usize>,
+    /// 没有任何匹配时，最接近的位置，按不一致的字节数从少到多排列
+    pub near_misses: Vec<NearMiss>,
+    /// 主特征码失效时，成功的备用方式
+    pub fallback: Option<FallbackHit>,
+    /// 特征码无效或无法读取模块
+    pub error: Option<PatternScanError>,
+}
+
+impl RecordDiagnosis {
+    fn new(info: &RecordInfo, resolution: RecordResolution, near_misses: Vec<NearMiss>) -> Self {
+        let (candidates, error) = match resolution.result {
+            Ok(matches) => (
+                matches
+                    .iter()
+                    .map(|m| m.va.wrapping_add_signed(info.offset))
+                    .collect(),
+                None,
+            ),
+            Err(e) => (Vec::new(), Some(e)),
+        };
+        Self {
+            name: resolution.name,
+            pattern: info.pattern,
+            offset: info.offset,
+            candidates,
+            near_misses,
+            fallback: resolution.fallback,
+            error,
+        }
+    }
+
+    pub fn match_count(&self) -> usize {
+        self.candidates.len()
+    }
+
+    /// 主特征码有效且有且仅有一个匹配
+    pub fn is_healthy(&self) -> bool {
+        self.error.is_none() && self.candidates.len() == 1
+    }
+
+    /// 主特征码或任一备用方式可以解析出地址
+    pub fn is_resolved(&self) -> bool {
+        self.is_healthy() || self.fallback.is_some()
+    }
+}
+
+impl fmt::Display for RecordDiagnosis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(e) = &self.error {
+            write!(f, "{}: {}", self.name, e)?;
+            return self.fmt_fallback(f);
+        }
+        match self.candidates.as_slice() {
+            [addr] => write!(f, "{}: ok at 0x{:X}", self.name, addr),
+            [] => {
+                write!(f, "{}: not found", self.name)?;
+                for near_miss in self.near_misses.iter() {
+                    let positions: Vec<String> = near_miss
+                        .mismatches
+                        .iter()
+                        .map(|m| format!("+{} ({:02X} != {:02X})", m.offset, m.actual, m.expected))
+                        .collect();
+                    write!(
+                        f,
+                        "\n    closest at 0x{:X}: {} mismatched bytes at {}",
+                        near_miss.va,
+                        near_miss.mismatches.len(),
+                        positions.join(", ")
+                    )?;
+                }
+                self.fmt_fallback(f)
+            }
+            candidates => {
+                let addrs: Vec<String> = candidates.iter().map(|a| format!("0x{:X}", a)).collect();
+                write!(
+                    f,
+                    "{}: {} matches at {}",
+                    self.name,
+                    candidates.len(),
+                    addrs.join(", ")
+                )?;
+                self.fmt_fallback(f)
+            }
+        }
+    }
+}
+
+impl RecordDiagnosis {
+    fn fmt_fallback(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.fallback {
+            Some(fallback) => write!(
+                f,
+                "\n    resolved by fallback #{} at 0x{:X}: {:?}",
+                fallback.index, fallback.va, fallback.strategy
+            ),
+            None => Ok(()),
+        }
+    }
+}
+
+/// 所有特征码记录的诊断结果
+#[derive(Debug)]
+pub struct HealthReport {
+    pub records: Vec<RecordDiagnosis>,
+    /// 扫描耗时
+    pub elapsed: Duration,
+}
+
+impl HealthReport {
+    pub fn healthy_count(&self) -> usize {
+        self.records.iter().filter(|r| r.is_healthy()).count()
+    }
+
+    /// 主特征码与所有备用方式均失效的记录
+    pub fn broken(&self) -> impl Iterator<Item = &RecordDiagnosis> {
+        self.records.iter().filter(|r| !r.is_resolved())
+    }
+
+    /// 主特征码失效（无匹配、多个匹配或特征码无效），但备用方式成功的记录
+    pub fn degraded(&self) -> impl Iterator<Item = &RecordDiagnosis> {
+        self.records
+            .iter()
+            .filter(|r| !r.is_healthy() && r.is_resolved())
+    }
+}
+
+impl fmt::Display for HealthReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{} address records healthy, scanned in {:?}",
+            self.healthy_count(),
+            self.records.len(),
+            self.elapsed
+        )?;
+        for record in self.records.iter().filter(|r| !r.is_healthy()) {
+            write!(f, "\n{}", record)?;
+        }
+        Ok(())
+    }
 }
 
 pub mod core {
     use address_scanner::AddressRecord;
 
-    #[derive(AddressRecord)]
-    #[record(
-        pattern = "48 83 EC 48 48 8B 05 ?? ?? ?? ?? 4C 8D 0D ?? ?? ?? ?? BA 0A 00 00 00",
-        offset = 0
-    )]
-    pub struct GetGameBuildRevision;
+    address_records! {
+        #[derive(AddressRecord)]
+        #[record(
+            pattern = "48 83 EC 48 48 8B 05 ?? ?? ?? ?? 4C 8D 0D ?? ?? ?? ?? BA 0A 00 00 00",
+            offset = 0
+        )]
+        pub struct GetGameBuildRevision;
+    }
 }
 
 pub mod monster {
     use address_scanner::AddressRecord;
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "4C 89 B3 10 76 00 00", offset = -60)]
-    pub struct Ctor;
+    address_records! {
+        #[derive(AddressRecord)]
+        #[record(pattern = "4C 89 B3 10 76 00 00", offset = -60)]
+        pub struct Ctor;
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "48 83 EC 20 48 8B B9 A0 09 00 00", offset = -20)]
-    pub struct Dtor;
+        #[derive(AddressRecord)]
+        #[record(pattern = "48 83 EC 20 48 8B B9 A0 09 00 00", offset = -20)]
+        pub struct Dtor;
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "48 85 C0 74 ?? 48 89 ?? ?? 48 8B ?? ?? ?? ?? ?? 48 89 ?? ?? 48 89 0A FF ?? ?? ?? ?? ?? 48 89 ?? ?? ?? ?? ?? C3", offset = -7)]
-    pub struct SetTarget;
+        #[derive(AddressRecord)]
+        #[record(pattern = "48 85 C0 74 ?? 48 89 ?? ?? 48 8B ?? ?? ?? ?? ?? 48 89 ?? ?? 48 89 0A FF ?? ?? ?? ?? ?? 48 89 ?? ?? ?? ?? ?? C3", offset = -7)]
+        pub struct SetTarget;
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "48 89 ?? ?? ?? 56 57 41 54 48 ?? ?? ?? 48 8B ?? ?? ?? ?? ?? 49 8B F0 48 8B DA 48 8B F9 41", offset = -5)]
-    pub struct ProcessThkSegment;
+        #[derive(AddressRecord)]
+        #[record(pattern = "48 89 ?? ?? ?? 56 57 41 54 48 ?? ?? ?? 48 8B ?? ?? ?? ?? ?? 49 8B F0 48 8B DA 48 8B F9 41", offset = -5)]
+        pub struct ProcessThkSegment;
+    }
 }
 
 pub mod inline {
     use address_scanner::AddressRecord;
 
-    #[derive(AddressRecord)]
-    #[record(
-        pattern = "0F 57 F6 49 63 ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? 85 D2 7E ?? 49 8B ?? ?? ?? ?? ?? 32 C0 48 85 C9 74 ?? 80 79 ?? ?? 0F 93 C0 EB ??",
-        offset = 10
-    )]
-    pub struct WeaponATK;
+    address_records! {
+        #[derive(AddressRecord)]
+        #[record(
+            pattern = "0F 57 F6 49 63 ?? ?? ?? ?? ?? ?? ?? ?? ?? ?? 85 D2 7E ?? 49 8B ?? ?? ?? ?? ?? 32 C0 48 85 C9 74 ?? 80 79 ?? ?? 0F 93 C0 EB ??",
+            offset = 10
+        )]
+        pub struct WeaponATK;
+    }
 }
 
 pub mod player {
     use address_scanner::AddressRecord;
 
-    // extern "fastcall" fn(*const c_void, *const c_void) -> *const c_void;
-    #[derive(AddressRecord)]
-    #[record(pattern = "8B ?? ?? ?? ?? ?? 48 85 C9 74 ?? E8 ?? ?? ?? ?? 33 C0 48 ?? ?? ?? C3", offset = -5)]
-    pub struct Hit; // 0x141F50480
+    address_records! {
+        // extern "fastcall" fn(*const c_void, *const c_void) -> *const c_void;
+        #[derive(AddressRecord)]
+        #[record(pattern = "8B ?? ?? ?? ?? ?? 48 85 C9 74 ?? E8 ?? ?? ?? ?? 33 C0 48 ?? ?? ?? C3", offset = -5)]
+        pub struct Hit; // 0x141F50480
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "4D 8B D8 4D 85 C0 75 ?? 4C 8B ?? ?? ?? ?? ?? 45 33 C0 4C 8D ?? ?? 45 8B D0 66 90", offset = -5)]
-    pub struct RemoveCatSkill;
+        #[derive(AddressRecord)]
+        #[record(pattern = "4D 8B D8 4D 85 C0 75 ?? 4C 8B ?? ?? ?? ?? ?? 45 33 C0 4C 8D ?? ?? 45 8B D0 66 90", offset = -5)]
+        pub struct RemoveCatSkill;
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "8B 84 ?? ?? ?? ?? ?? C6 44 ?? ?? ?? C7 44 ?? ?? ?? ?? ?? ?? 89 44 ?? ?? 0F B6 ?? ?? ?? ?? ?? ?? 88 44 ?? ?? 8B 84", offset = -13)]
-    pub struct DrawDamage;
+        #[derive(AddressRecord)]
+        #[record(pattern = "8B 84 ?? ?? ?? ?? ?? C6 44 ?? ?? ?? C7 44 ?? ?? ?? ?? ?? ?? 89 44 ?? ?? 0F B6 ?? ?? ?? ?? ?? ?? 88 44 ?? ?? 8B 84", offset = -13)]
+        pub struct DrawDamage;
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "0F ?? ?? ?? ?? ?? ?? 73 ?? F3 0F ?? ?? ?? 0F 57 C9 F3 0F 5D C1 F3 0F ?? ?? ?? C3 0F 57 C0 0F 2F C8 72 ?? F3 0F ?? ?? ?? F3 0F 5D C1", offset = -5)]
-    pub struct StealHealth;
+        #[derive(AddressRecord)]
+        #[record(pattern = "0F ?? ?? ?? ?? ?? ?? 73 ?? F3 0F ?? ?? ?? 0F 57 C9 F3 0F 5D C1 F3 0F ?? ?? ?? C3 0F 57 C0 0F 2F C8 72 ?? F3 0F ?? ?? ?? F3 0F 5D C1", offset = -5)]
+        pub struct StealHealth;
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "0F 57 C0 0F 2F ?? ?? ?? ?? ?? 0F ?? ?? ?? ?? ?? F3 ?? ?? ?? ?? ?? ?? ?? 48 ?? ?? ?? ?? ?? ?? F3 ?? ?? ?? ?? ?? ?? ?? 33 C9 49 89", offset = -10)]
-    pub struct MuteCheck; // 0x141A4FCC0
+        #[derive(AddressRecord)]
+        #[record(pattern = "0F 57 C0 0F 2F ?? ?? ?? ?? ?? 0F ?? ?? ?? ?? ?? F3 ?? ?? ?? ?? ?? ?? ?? 48 ?? ?? ?? ?? ?? ?? F3 ?? ?? ?? ?? ?? ?? ?? 33 C9 49 89", offset = -10)]
+        pub struct MuteCheck; // 0x141A4FCC0
 
-    // extern "fastcall" fn(*const c_void, *const c_void) -> *const c_void;
-    #[derive(AddressRecord)]
-    #[record(pattern = "48 89 ?? ?? ?? 57 48 ?? ?? ?? 48 8B 02 48 8B F1 8B ?? ?? ?? ?? ?? 48 8B CA 48 8B FA FF ?? ?? 3B 58 ?? 0F ?? ?? ?? ?? ?? 0F 10 ?? ?? 0F 11 ?? ?? 0F 10 ?? ?? 0F 11 ?? ?? 8B 46 ?? 89 47 ??", offset = -5)]
-    pub struct ClonePlayerShortInfo; // 0x140F9ED20
+        // extern "fastcall" fn(*const c_void, *const c_void) -> *const c_void;
+        #[derive(AddressRecord)]
+        #[record(pattern = "48 89 ?? ?? ?? 57 48 ?? ?? ?? 48 8B 02 48 8B F1 8B ?? ?? ?? ?? ?? 48 8B CA 48 8B FA FF ?? ?? 3B 58 ?? 0F ?? ?? ?? ?? ?? 0F 10 ?? ?? 0F 11 ?? ?? 0F 10 ?? ?? 0F 11 ?? ?? 8B 46 ?? 89 47 ??", offset = -5)]
+        pub struct ClonePlayerShortInfo; // 0x140F9ED20
 
-    // extern "fastcall" fn(save_data: *const c_void, offset: u32, value: u8);
-    #[derive(AddressRecord)]
-    #[record(pattern = "FF 15 3A 8C AA 01 48 8D 4E 08 40 88 AC 37 D8 03 14 00", offset = -32)]
-    pub struct SetSettings; // 0x1413683A0
+        // extern "fastcall" fn(save_data: *const c_void, offset: u32, value: u8);
+        #[derive(AddressRecord)]
+        #[record(pattern = "FF 15 3A 8C AA 01 48 8D 4E 08 40 88 AC 37 D8 03 14 00", offset = -32)]
+        pub struct SetSettings; // 0x1413683A0
 
-    // extern "fastcall" fn(save_data: *const c_void, add_xp: u32);
-    #[derive(AddressRecord)]
-    #[record(pattern = "48 89 6C 24 10 48 89 74 24 18 48 89 7C 24 20 41 54 41 56 41 57 48 83 EC 20 48 8B F9 8B F2", offset = -5)]
-    pub struct AddHrXp; // 0x14136ACA0 15.23
+        // extern "fastcall" fn(save_data: *const c_void, add_xp: u32);
+        #[derive(AddressRecord)]
+        #[record(pattern = "48 89 6C 24 10 48 89 74 24 18 48 89 7C 24 20 41 54 41 56 41 57 48 83 EC 20 48 8B F9 8B F2", offset = -5)]
+        pub struct AddHrXp; // 0x14136ACA0 15.23
 
-    // extern "fastcall" fn(save_data: *const c_void, add_xp: u32, a3: bool);
-    #[derive(AddressRecord)]
-    #[record(pattern = "48 89 ?? ?? ?? 48 89 ?? ?? ?? 48 89 ?? ?? ?? 41 54 41 56 41 57 48 ?? ?? ?? 48 8B F1 41 0F B6 F8 48 83 C1 08", offset = -5)]
-    pub struct AddMrXp; // 0x14136A720
+        // extern "fastcall" fn(save_data: *const c_void, add_xp: u32, a3: bool);
+        #[derive(AddressRecord)]
+        #[record(pattern = "48 89 ?? ?? ?? 48 89 ?? ?? ?? 48 89 ?? ?? ?? 41 54 41 56 41 57 48 ?? ?? ?? 48 8B F1 41 0F B6 F8 48 83 C1 08", offset = -5)]
+        pub struct AddMrXp; // 0x14136A720
+    }
 }
 
 pub mod chat {
     use address_scanner::AddressRecord;
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "81 08 10 00 00 48 ?? ?? ?? ?? ?? ?? 66 44 89 01 48 3B D0 74 ?? 44 89", offset = -5)]
-    pub struct MessageSent;
+    address_records! {
+        #[derive(AddressRecord)]
+        #[record(pattern = "81 08 10 00 00 48 ?? ?? ?? ?? ?? ?? 66 44 89 01 48 3B D0 74 ?? 44 89", offset = -5)]
+        pub struct MessageSent;
 
-    // Push a message to the chat window
-    // player message, sticker, not including system messages.
-    // extern "fastcall" fn(*const c_void, *const c_void, u32, bool) -> u8;
-    #[derive(AddressRecord)]
-    #[record(pattern = "48 89 ?? ?? ?? 55 57 41 54 41 56 41 57 48 8D AC 24 60 FE FF FF 48 81 EC A0 02 00 00 45 33 F6 C6 85", offset = -5)]
-    pub struct PushMessageToWindow; // 0x141A50D70
+        // Push a message to the chat window
+        // player message, sticker, not including system messages.
+        // extern "fastcall" fn(*const c_void, *const c_void, u32, bool) -> u8;
+        #[derive(AddressRecord)]
+        #[record(pattern = "48 89 ?? ?? ?? 55 57 41 54 41 56 41 57 48 8D AC 24 60 FE FF FF 48 81 EC A0 02 00 00 45 33 F6 C6 85", offset = -5)]
+        pub struct PushMessageToWindow; // 0x141A50D70
 
-    // extern "fastcall" fn(chat_base: *const c_void, msg: *const i8, delay_secs: f32, unk: u32, is_purple: bool) -> *const c_void;
-    #[derive(AddressRecord)]
-    #[record(pattern = "0F 29 B4 24 B0 01 00 00 48 8B DA 0F 28 F2 48 8B F9 75 09", offset = -25)]
-    pub struct SystemMessage; // 0x141A53400
+        // extern "fastcall" fn(chat_base: *const c_void, msg: *const i8, delay_secs: f32, unk: u32, is_purple: bool) -> *const c_void;
+        #[derive(AddressRecord)]
+        #[record(pattern = "0F 29 B4 24 B0 01 00 00 48 8B DA 0F 28 F2 48 8B F9 75 09", offset = -25)]
+        pub struct SystemMessage; // 0x141A53400
+    }
 }
 
 pub mod quest {
     use address_scanner::AddressRecord;
 
-    #[derive(AddressRecord)]
-    #[record(
-        pattern = "40 53 57 41 57 48 83 EC 50 48 8B D9 45 0F B6 F8",
-        offset = 0
-    )]
-    pub struct Accept;
+    address_records! {
+        #[derive(AddressRecord)]
+        #[record(
+            pattern = "40 53 57 41 57 48 83 EC 50 48 8B D9 45 0F B6 F8",
+            offset = 0
+        )]
+        pub struct Accept;
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "48 8B C4 55 48 81 EC F0 01 00 00 33 ED", offset = 0)]
-    pub struct Enter;
+        #[derive(AddressRecord)]
+        #[record(pattern = "48 8B C4 55 48 81 EC F0 01 00 00 33 ED", offset = 0)]
+        pub struct Enter;
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "40 57 48 83 EC 60 83 79 38 02 48 8B F9", offset = 0)]
-    pub struct Return;
+        #[derive(AddressRecord)]
+        #[record(pattern = "40 57 48 83 EC 60 83 79 38 02 48 8B F9", offset = 0)]
+        pub struct Return;
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "00 84 c0 0F 84 BE B0 9E 51 00", offset = -54)]
-    pub struct Leave;
+        #[derive(AddressRecord)]
+        #[record(pattern = "00 84 c0 0F 84 BE B0 9E 51 00", offset = -54)]
+        pub struct Leave;
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "F3 0F 2C C0 F3 0F 11 81 A4 31 01 00", offset = -67)]
-    pub struct Abandon; // 0x141B71570
+        #[derive(AddressRecord)]
+        #[record(pattern = "F3 0F 2C C0 F3 0F 11 81 A4 31 01 00", offset = -67)]
+        pub struct Abandon; // 0x141B71570
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "48 81 EC 60 02 00 00 45 33 FF 48 8B D9", offset = -28)]
-    pub struct Cancel;
+        #[derive(AddressRecord)]
+        #[record(pattern = "48 81 EC 60 02 00 00 45 33 FF 48 8B D9", offset = -28)]
+        pub struct Cancel;
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "41 0F B6 F9 33 D2 41 8B F0 48 8B D9", offset = -37)]
-    pub struct End;
+        #[derive(AddressRecord)]
+        #[record(pattern = "41 0F B6 F9 33 D2 41 8B F0 48 8B D9", offset = -37)]
+        pub struct End;
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "48 8B C4 53 55 48 81 EC 08 02 00 00", offset = 0)]
-    pub struct DepartOn;
+        #[derive(AddressRecord)]
+        #[record(pattern = "48 8B C4 53 55 48 81 EC 08 02 00 00", offset = 0)]
+        pub struct DepartOn;
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "41 56 48 83 EC 20 48 8D B1 A0 AE 00 00", offset = -20)]
-    pub struct GetQuestname;
+        #[derive(AddressRecord)]
+        #[record(pattern = "41 56 48 83 EC 20 48 8D B1 A0 AE 00 00", offset = -20)]
+        pub struct GetQuestname;
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "48 ?? ?? ?? 65 ?? ?? ?? ?? ?? ?? ?? ?? 48 8B F1 44 ?? ?? ?? ?? ?? ?? 41 0F B6 E8 B9 ?? ?? ?? ?? 4C 63 F2 4E 8B 14 C8 41 8B 04 0A 39", offset = -5)]
-    pub struct PlayerDeath; // 0x141B68E00
+        #[derive(AddressRecord)]
+        #[record(pattern = "48 ?? ?? ?? 65 ?? ?? ?? ?? ?? ?? ?? ?? 48 8B F1 44 ?? ?? ?? ?? ?? ?? 41 0F B6 E8 B9 ?? ?? ?? ?? 4C 63 F2 4E 8B 14 C8 41 8B 04 0A 39", offset = -5)]
+        pub struct PlayerDeath; // 0x141B68E00
 
-    // func(*(0x14500CAF0 as *const *const c_void), count: i32)
-    #[derive(AddressRecord)]
-    #[record(pattern = "89 91 ?? ?? ?? ?? 48 8B F9 48 ?? ?? ?? ?? ?? ?? BA 01 00 00 00 E8 ?? ?? ?? ?? 85 C0 78 5A", offset = -6)]
-    pub struct SetTotalPlayers; // 0x141B710B0
+        // func(*(0x14500CAF0 as *const *const c_void), count: i32)
+        #[derive(AddressRecord)]
+        #[record(pattern = "89 91 ?? ?? ?? ?? 48 8B F9 48 ?? ?? ?? ?? ?? ?? BA 01 00 00 00 E8 ?? ?? ?? ?? 85 C0 78 5A", offset = -6)]
+        pub struct SetTotalPlayers; // 0x141B710B0
+    }
 }
 
 pub mod action {
     use address_scanner::AddressRecord;
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "48 63 0A 48 8D ?? ?? 48 ?? ?? ?? 46 3B 04 08 0F ?? ?? ?? ?? ?? 48 03 C9 49 8B ?? ?? ?? 4A ?? ?? ?? ?? 0F ?? ?? ?? ?? ?? 41 C6 ?? ?? ?? ?? ?? ?? 41 8B", offset = -7)]
-    pub struct SetAction; // 0x140269C90
+    address_records! {
+        #[derive(AddressRecord)]
+        #[record(pattern = "48 63 0A 48 8D ?? ?? 48 ?? ?? ?? 46 3B 04 08 0F ?? ?? ?? ?? ?? 48 03 C9 49 8B ?? ?? ?? 4A ?? ?? ?? ?? 0F ?? ?? ?? ?? ?? 41 C6 ?? ?? ?? ?? ?? ?? 41 8B", offset = -7)]
+        pub struct SetAction; // 0x140269C90
+    }
 }
 
 pub mod weapon {
     use address_scanner::AddressRecord;
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "48 ?? ?? ?? ?? ?? ?? 48 89 ?? ?? 45 8B E0 48 89 ?? ?? 48 8D ?? ?? ?? ?? ?? 4C 89 ?? ??", offset = -6)]
-    pub struct Change;
+    address_records! {
+        #[derive(AddressRecord)]
+        #[record(pattern = "48 ?? ?? ?? ?? ?? ?? 48 89 ?? ?? 45 8B E0 48 89 ?? ?? 48 8D ?? ?? ?? ?? ?? 4C 89 ?? ??", offset = -6)]
+        pub struct Change;
+    }
 }
 
 pub mod steamwork {
     use address_scanner::AddressRecord;
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "", offset = 0)]
-    pub struct ChangeFuel; // 0x141349340
+    address_records! {
+        #[derive(AddressRecord)]
+        #[record(pattern = "", offset = 0)]
+        pub struct ChangeFuel; // 0x141349340
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "BA ?? ?? ?? ?? 44 8D ?? ?? 41 FF D1 44 38 ?? ?? ?? ?? ?? 75 ?? 40 38 ?? ?? ?? ?? ?? 74 ?? BE ?? ?? ?? ?? 4B 8D 0C 64 8B D6 48 8B", offset = -2)]
-    pub struct FailureJnzPatch; // 0x140666AFA
+        #[derive(AddressRecord)]
+        #[record(pattern = "BA ?? ?? ?? ?? 44 8D ?? ?? 41 FF D1 44 38 ?? ?? ?? ?? ?? 75 ?? 40 38 ?? ?? ?? ?? ?? 74 ?? BE ?? ?? ?? ?? 4B 8D 0C 64 8B D6 48 8B", offset = -2)]
+        pub struct FailureJnzPatch; // 0x140666AFA
+    }
 }
 
 pub mod c_system {
     use address_scanner::AddressRecord;
 
-    #[derive(AddressRecord)]
-    #[record(pattern = "48 83 C1 08 FF 15 ? ? ? ? 48 8B C3 C6 43 30 01 48 83 C4 20 5B C3", offset = -19)]
-    pub struct Ctor; // 0x14225A130
+    address_records! {
+        #[derive(AddressRecord)]
+        #[record(pattern = "48 83 C1 08 FF 15 ? ? ? ? 48 8B C3 C6 43 30 01 48 83 C4 20 5B C3", offset = -19)]
+        pub struct Ctor; // 0x14225A130
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::{
+        game::address_registry::AddressRegistry,
+        utils::{self, build_headers},
+    };
+
+    use super::*;
+
+    /// 只有一个 `.text` 节（RVA 0x1000，文件偏移0x400）的镜像文件，`code` 为写入节内偏移处的字节
+    pub(crate) fn build_text_file(code: &[(usize, &[u8])]) -> Vec<u8> {
+        let mut file = build_headers(&[(".text", 0x1000, 0x400, 0x400, 0x400, 0x6000_0020)]);
+        file.resize(0x800, 0xCC);
+        for &(offset, bytes) in code {
+            file[0x400 + offset..0x400 + offset + bytes.len()].copy_from_slice(bytes);
+        }
+        file
+    }
+
+    pub(crate) fn build_image_with(code: &[(usize, &[u8])]) -> PeImage {
+        PeImage::from_file_bytes("MonsterHunterWorld.exe", &build_text_file(code)).unwrap()
+    }
+
+    /// `.text` 节偏移 `text_offset` 处为 monster::Ctor 特征码的镜像
+    fn build_image(text_offset: usize) -> PeImage {
+        let pattern = utils::space_hex_to_bytes(monster::Ctor::INFO.pattern).unwrap();
+        build_image_with(&[(text_offset, &pattern)])
+    }
+
+    #[test]
+    fn test_image_hash_relocated() {
+        let file = build_text_file(&[]);
+        let mut relocated = file.clone();
+        // 加载器将 OptionalHeader.ImageBase 改写为实际基址
+        relocated[0x98 + 24..0x98 + 32].copy_from_slice(&0x7FF6_0000_0000_u64.to_le_bytes());
+        let image = PeImage::from_file_bytes("MonsterHunterWorld.exe", &file).unwrap();
+        let relocated = PeImage::from_file_bytes("MonsterHunterWorld.exe", &relocated).unwrap();
+        assert_ne!(image.headers().image_base, relocated.headers().image_base);
+        assert_eq!(image_hash(&image), image_hash(&relocated));
+    }
+
+    #[test]
+    fn test_offline_resolve() {
+        let mut repository = AddressRepository::with_image(build_image(0x100));
+        let addr = repository.get_record_address(monster::Ctor).unwrap();
+        assert_eq!(addr, 0x140001100 - 60);
+
+        // 绑定镜像时不扫描运行中的进程
+        assert!(repository.get_address(monster::Dtor).is_err());
+
+        let resolution = repository.resolve(&monster::Ctor::INFO);
+        assert_eq!(resolution.name, "monster::Ctor");
+        assert!(resolution.is_unique());
+        assert_eq!(resolution.rva().unwrap(), 0x1100 - 60);
+
+        assert!(matches!(
+            repository.resolve(&monster::Dtor::INFO).address(),
+            Err(PatternScanError::NotFound)
+        ));
+
+        // 保留原始错误，而不是再包装一层
+        let invalid = RecordInfo {
+            pattern: "4X",
+            ..monster::Ctor::INFO
+        };
+        let resolution = repository.resolve(&invalid);
+        let error = resolution.unique_match().unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            resolution.result.as_ref().unwrap_err().to_string()
+        );
+    }
+
+    #[test]
+    fn test_resolve_rip_operand() {
+        // mov rcx, [rip+0x2000]; test rcx, rcx; jz
+        let code = [
+            0x48, 0x8B, 0x0D, 0x00, 0x20, 0x00, 0x00, 0x48, 0x85, 0xC9, 0x74,
+        ];
+        let repository = AddressRepository::with_image(build_image_with(&[(0x200, &code)]));
+
+        let info = RecordInfo {
+            module: "globals",
+            name: "PlayerBase",
+            pattern: "48 85 C9 74",
+            offset: -7,
+            fallbacks: &[],
+            type_id: TypeId::of::<()>,
+        };
+        let operand = repository.resolve_rip_operand(&info).unwrap();
+        assert_eq!(operand.address, 0x140001200);
+        assert_eq!(operand.target, 0x140001207 + 0x2000);
+    }
+
+    #[test]
+    fn test_health_report() {
+        // 第4个字节被修改的 monster::Ctor，以及两份 monster::Dtor
+        let ctor = [0x4C, 0x89, 0xB3, 0x18, 0x76, 0x00, 0x00];
+        let dtor = utils::space_hex_to_bytes(monster::Dtor::INFO.pattern).unwrap();
+        let repository = AddressRepository::with_image(build_image_with(&[
+            (0x100, &ctor),
+            (0x200, &dtor),
+            (0x300, &dtor),
+        ]));
+
+        let report = repository.health_report(&[
+            &monster::Ctor::INFO,
+            &monster::Dtor::INFO,
+            &steamwork::ChangeFuel::INFO,
+        ]);
+        assert_eq!(report.healthy_count(), 0);
+        let [ctor, dtor, fuel] = report.records.as_slice() else {
+            panic!("unexpected record count");
+        };
+
+        assert_eq!(ctor.match_count(), 0);
+        assert_eq!(ctor.near_misses.len(), 1);
+        assert_eq!(ctor.near_misses[0].rva, 0x1100);
+        assert_eq!(
+            ctor.near_misses[0].mismatches,
+            vec![Mismatch {
+                offset: 3,
+                expected: 0x10,
+                mask: 0xFF,
+                actual: 0x18,
+            }]
+        );
+        assert_eq!(dtor.candidates, vec![0x140001200 - 20, 0x140001300 - 20]);
+        assert!(dtor.near_misses.is_empty());
+        // 空特征码没有匹配，也不会有接近的位置
+        assert_eq!(fuel.match_count(), 0);
+        assert!(fuel.near_misses.is_empty());
+        assert!(report
+            .to_string()
+            .contains("closest at 0x140001100: 1 mismatched bytes at +3 (18 != 10)"));
+    }
+
+    #[test]
+    fn test_preload_cached() {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!(
+            "mhw_toolkit_test_address_cache_{}_{}.json",
+            std::process::id(),
+            nanos
+        ));
+        let records = [&monster::Ctor::INFO];
+
+        let mut repository = AddressRepository::with_image(build_image(0x100));
+        let report = repository.preload_cached(&path, &records).unwrap();
+        assert_eq!((report.resolved, report.cached), (1, 0));
+
+        // 同一版本无需扫描
+        let mut repository = AddressRepository::with_image(build_image(0x100));
+        let report = repository.preload_cached(&path, &records).unwrap();
+        assert_eq!((report.resolved, report.cached), (0, 1));
+        assert!(report.resolutions.is_empty());
+        assert_eq!(
+            repository.get_record_address(monster::Ctor).unwrap(),
+            0x140001100 - 60
+        );
+
+        // PE头部变化（例如时间戳）时缓存失效
+        let pattern = utils::space_hex_to_bytes(monster::Ctor::INFO.pattern).unwrap();
+        let mut file = build_text_file(&[(0x180, &pattern)]);
+        let nt_offset = u32::from_le_bytes(file[0x3C..0x40].try_into().unwrap()) as usize;
+        file[nt_offset + 8] ^= 0xFF;
+        let image = PeImage::from_file_bytes("MonsterHunterWorld.exe", &file).unwrap();
+        let mut repository = AddressRepository::with_image(image);
+        let report = repository.preload_cached(&path, &records).unwrap();
+        assert_eq!((report.resolved, report.cached), (1, 0));
+        assert_eq!(
+            repository.get_record_address(monster::Ctor).unwrap(),
+            0x140001180 - 60
+        );
+        let _ = std::fs::remove_file(&path);
+
+        // 缓存中的函数地址只有在仍与特征码匹配时才会被调用
+        let image = build_image(0x100);
+        assert!(is_record_at(&image, &monster::Ctor::INFO, 0x1100 - 60));
+        assert!(!is_record_at(&image, &monster::Ctor::INFO, 0x1180 - 60));
+        assert!(!is_record_at(&image, &monster::Ctor::INFO, 0x10));
+    }
+
+    mod fallback_records {
+        use address_scanner::AddressRecord;
+
+        address_records! {
+            #[derive(AddressRecord)]
+            #[record(pattern = "4C 89 B3 10 76 00 01", offset = -60)]
+            #[fallbacks(
+                Pattern { pattern: "4C 89 B3 10 76 00 02", offset: -60 },
+                Pattern { pattern: "4C 89 B3 10 76 00 00", offset: -60 },
+            )]
+            pub struct Ctor;
+
+            #[derive(AddressRecord)]
+            #[record(pattern = "4C 89 B3 10 76 00 01", offset = -60)]
+            #[fallbacks(FollowOperand { pattern: "E8 ?? ?? ?? ?? 48 8B D8", offset: 0 })]
+            pub struct CtorCaller;
+
+            #[derive(AddressRecord)]
+            #[record(pattern = "4C 89 B3 10 76 00 01", offset = -60)]
+            #[fallbacks(KnownRva { revision: "421631", rva: 0x10C4 })]
+            pub struct CtorKnown;
+        }
+    }
+
+    #[test]
+    fn test_fallbacks() {
+        let pattern = utils::space_hex_to_bytes(monster::Ctor::INFO.pattern).unwrap();
+        // call monster::Ctor; mov rbx, rax
+        let rel = (0x10C4_i32 - 0x1205).to_le_bytes();
+        let call = [0xE8, rel[0], rel[1], rel[2], rel[3], 0x48, 0x8B, 0xD8];
+        let code: [(usize, &[u8]); 2] = [(0x100, &pattern), (0x200, &call)];
+        let mut repository = AddressRepository::with_image(build_image_with(&code));
+
+        let resolution = repository.resolve(&fallback_records::Ctor::INFO);
+        assert_eq!(resolution.match_count(), 0);
+        assert_eq!(resolution.fallback.unwrap().index, 1);
+        assert_eq!(resolution.address().unwrap(), 0x140001100 - 60);
+        assert_eq!(
+            repository
+                .get_record_address(fallback_records::CtorCaller)
+                .unwrap(),
+            0x140001100 - 60
+        );
+
+        // 离线镜像没有版本号，已知RVA不可用
+        let records: Vec<&RecordInfo> = fallback_records::RECORDS.iter().collect();
+        let report = repository.health_report(&records);
+        assert_eq!(report.healthy_count(), 0);
+        assert_eq!(report.degraded().count(), 2);
+        let broken: Vec<&str> = report.broken().map(|r| r.name.as_str()).collect();
+        assert_eq!(broken, ["fallback_records::CtorKnown"]);
+        assert!(report
+            .to_string()
+            .contains("resolved by fallback #0 at 0x1400010C4"));
+
+        // 注册表中的记录同样使用备用方式
+        let mut registry = AddressRegistry::with_image(build_image_with(&code));
+        assert_eq!(
+            registry.get_record(fallback_records::CtorCaller).unwrap(),
+            0x140001100 - 60
+        );
+    }
+
+    #[test]
+    fn test_diff_resolutions() {
+        let records = [&monster::Ctor::INFO, &monster::Dtor::INFO];
+        let old = AddressRepository::with_image(build_image(0x100)).resolve_all(&records);
+        let new = AddressRepository::with_image(build_image(0x180)).resolve_all(&records);
+
+        let diff = diff_resolutions(&old, &new);
+        assert_eq!(
+            diff,
+            vec![RecordDiff {
+                name: "monster::Ctor".to_string(),
+                old_rva: Some(0x1100 - 60),
+                new_rva: Some(0x1180 - 60),
+            }]
+        );
+    }
 }
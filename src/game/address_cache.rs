@@ -0,0 +1,107 @@
+use std::{collections::BTreeMap, fs, path::Path};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::utils::{image_base_field, PeError, PeImage};
+
+const FNV_OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+#[derive(Debug, Error)]
+pub enum AddressCacheError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid address cache file: {0}")]
+    Format(String),
+    #[error("failed to read module: {0}")]
+    Module(#[from] PeError),
+}
+
+/// 游戏版本标识，任一字段不同时缓存失效
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BuildId {
+    /// `GetGameBuildRevision` 返回的版本号，离线镜像无法获取时为None
+    pub revision: Option<String>,
+    /// PE头部（包含时间戳与节表）的FNV-1a哈希
+    pub image_hash: u64,
+    /// `GetGameBuildRevision` 的RVA，头部哈希一致时直接使用，无需再次扫描
+    #[serde(default)]
+    pub revision_rva: Option<usize>,
+}
+
+impl BuildId {
+    pub fn new(image: &PeImage, revision: Option<String>, revision_rva: Option<usize>) -> Self {
+        Self {
+            revision,
+            image_hash: image_hash(image),
+            revision_rva,
+        }
+    }
+}
+
+/// 持久化的特征码扫描结果
+///
+/// 以记录名（见 [`RecordInfo::full_name`](super::address::RecordInfo::full_name)）为键保存RVA，
+/// 不依赖模块基址与 `TypeId`，可以跨进程复用。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressCache {
+    build: BuildId,
+    records: BTreeMap<String, usize>,
+}
+
+impl AddressCache {
+    pub fn new(build: BuildId) -> Self {
+        Self {
+            build,
+            records: BTreeMap::new(),
+        }
+    }
+
+    pub fn build(&self) -> &BuildId {
+        &self.build
+    }
+
+    /// 记录的RVA
+    pub fn get(&self, name: &str) -> Option<usize> {
+        self.records.get(name).copied()
+    }
+
+    pub fn insert(&mut self, name: &str, rva: usize) {
+        self.records.insert(name.to_string(), rva);
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), AddressCacheError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| AddressCacheError::Format(e.to_string()))?;
+        Ok(fs::write(path, json)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, AddressCacheError> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(|e| AddressCacheError::Format(e.to_string()))
+    }
+}
+
+/// 对PE头部计算哈希
+///
+/// 头部不受其他插件对代码段的补丁影响。模块重定位时加载器会改写 `ImageBase` 字段，
+/// 计算前将其置零，因此对运行中的模块与磁盘上的文件结果相同。
+pub(crate) fn image_hash(image: &PeImage) -> u64 {
+    let len = (image.headers().size_of_headers as usize).min(image.size());
+    let mut bytes = image.bytes_at(0, len).unwrap_or_default().to_vec();
+    if let Some(field) = image_base_field(&bytes).filter(|field| field.end <= bytes.len()) {
+        bytes[field].fill(0);
+    }
+    bytes.iter().fold(FNV_OFFSET_BASIS, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(FNV_PRIME)
+    })
+}
@@ -0,0 +1,498 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::address::{resolve_fallbacks, AddressRepository, RecordInfo, SignatureRecord, Strategy};
+use crate::utils::{
+    BatchScanner, Pattern, PatternMatch, PatternScanError, PeError, PeImage, SectionFilter,
+};
+
+pub type SharedAddressRegistry = Arc<Mutex<AddressRegistry>>;
+
+static ADDRESS_REGISTRY: Lazy<SharedAddressRegistry> =
+    Lazy::new(|| Arc::new(Mutex::new(AddressRegistry::new())));
+
+#[derive(Debug, Error)]
+pub enum AddressRegistryError {
+    #[error("signature {0} is already registered")]
+    Duplicate(String),
+    #[error("signature {0} is not registered")]
+    NotRegistered(String),
+    #[error("invalid signature {name}: {source}")]
+    Pattern {
+        name: String,
+        source: PatternScanError,
+    },
+    #[error("failed to resolve {name}: {source}")]
+    Resolve {
+        name: String,
+        source: PatternScanError,
+    },
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid signature file: {0}")]
+    Format(String),
+}
+
+/// 运行时注册的特征码
+///
+/// 可以从配置文件读取（见 [`AddressRegistry::load`]），也可以由 [`RecordInfo`] 转换。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Signature {
+    pub name: String,
+    /// 特征码，语法见 [`Pattern`]
+    pub pattern: String,
+    /// 匹配地址到目标地址的偏移
+    #[serde(default)]
+    pub offset: isize,
+    /// 所在模块，为None时为游戏主模块
+    #[serde(default)]
+    pub module: Option<String>,
+    /// 主特征码没有唯一匹配时依次尝试的备用方式，仅由 [`RecordInfo`] 转换时保留
+    #[serde(skip)]
+    pub fallbacks: &'static [Strategy],
+}
+
+impl Signature {
+    pub fn new(name: &str, pattern: &str, offset: isize) -> Self {
+        Self {
+            name: name.to_string(),
+            pattern: pattern.to_string(),
+            offset,
+            module: None,
+            fallbacks: &[],
+        }
+    }
+
+    pub fn with_module(mut self, module: &str) -> Self {
+        self.module = Some(module.to_string());
+        self
+    }
+}
+
+impl From<&RecordInfo> for Signature {
+    /// 以 [`RecordInfo::full_name`] 为名称，保留备用方式
+    fn from(info: &RecordInfo) -> Self {
+        Self {
+            fallbacks: info.fallbacks,
+            ..Self::new(&info.full_name(), info.pattern, info.offset)
+        }
+    }
+}
+
+/// [`AddressRegistry::entries`] 列出的特征码
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegistryEntry {
+    pub signature: Signature,
+    /// 已解析的地址，尚未解析或解析失败时为None
+    pub address: Option<usize>,
+}
+
+struct Entry {
+    signature: Signature,
+    pattern: Pattern,
+    address: Option<usize>,
+}
+
+/// [`AddressRegistry::resolve_all`] 的结果
+#[derive(Debug)]
+pub struct RegistryReport {
+    /// 解析失败的特征码
+    pub failures: Vec<AddressRegistryError>,
+    /// 成功解析的特征码数量（包括之前已解析的）
+    pub resolved: usize,
+    pub elapsed: Duration,
+}
+
+/// 以名称为键的特征码注册表
+///
+/// 与 [`AddressRepository`](super::address::AddressRepository) 不同，特征码可以在运行时注册，
+/// 例如插件从配置文件读取；也可以注册 `address_records!` 定义的记录，名称为 [`RecordInfo::full_name`]。
+///
+/// ```ignore
+/// let registry = AddressRegistry::get_instance();
+/// let mut registry = registry.lock().unwrap();
+/// registry.register_records(&all_records())?;
+/// registry.load("signatures.json")?;
+/// let report = registry.resolve_all();
+/// let addr = registry.get("monster::Ctor")?;
+/// ```
+#[derive(Default)]
+pub struct AddressRegistry {
+    entries: BTreeMap<String, Entry>,
+    image: Option<PeImage>,
+}
+
+impl AddressRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 创建针对指定模块镜像的独立注册表，指定了其他模块的特征码无法解析
+    pub fn with_image(image: PeImage) -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            image: Some(image),
+        }
+    }
+
+    pub fn get_instance() -> SharedAddressRegistry {
+        ADDRESS_REGISTRY.clone()
+    }
+
+    /// 注册特征码，名称已存在或特征码无效时返回错误
+    pub fn register(&mut self, signature: Signature) -> Result<(), AddressRegistryError> {
+        let entry = self.validate(signature)?;
+        self.entries.insert(entry.signature.name.clone(), entry);
+        Ok(())
+    }
+
+    fn validate(&self, signature: Signature) -> Result<Entry, AddressRegistryError> {
+        if self.entries.contains_key(&signature.name) {
+            return Err(AddressRegistryError::Duplicate(signature.name));
+        }
+        let pattern =
+            Pattern::parse(&signature.pattern).map_err(|source| AddressRegistryError::Pattern {
+                name: signature.name.clone(),
+                source,
+            })?;
+        Ok(Entry {
+            signature,
+            pattern,
+            address: None,
+        })
+    }
+
+    /// 注册 `address_records!` 定义的记录，已注册的记录将被跳过
+    pub fn register_records(
+        &mut self,
+        records: &[&RecordInfo],
+    ) -> Result<(), AddressRegistryError> {
+        for info in records.iter() {
+            match self.register(Signature::from(*info)) {
+                Ok(()) | Err(AddressRegistryError::Duplicate(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    /// 从JSON文件读取特征码列表并注册，返回注册的数量
+    ///
+    /// 先检查所有特征码，任一无效或重复时不注册文件中的任何特征码。
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<usize, AddressRegistryError> {
+        let json = fs::read_to_string(path)?;
+        let signatures: Vec<Signature> =
+            serde_json::from_str(&json).map_err(|e| AddressRegistryError::Format(e.to_string()))?;
+        let mut entries: BTreeMap<String, Entry> = BTreeMap::new();
+        for signature in signatures {
+            if entries.contains_key(&signature.name) {
+                return Err(AddressRegistryError::Duplicate(signature.name));
+            }
+            let entry = self.validate(signature)?;
+            entries.insert(entry.signature.name.clone(), entry);
+        }
+        let count = entries.len();
+        self.entries.extend(entries);
+        Ok(count)
+    }
+
+    /// 移除特征码，成功返回true
+    pub fn unregister(&mut self, name: &str) -> bool {
+        self.entries.remove(name).is_some()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.entries.contains_key(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 按名称排列的所有特征码
+    pub fn entries(&self) -> Vec<RegistryEntry> {
+        self.entries
+            .values()
+            .map(|entry| RegistryEntry {
+                signature: entry.signature.clone(),
+                address: entry.address,
+            })
+            .collect()
+    }
+
+    /// 获取特征码的地址，尚未解析时扫描并缓存
+    pub fn get(&mut self, name: &str) -> Result<usize, AddressRegistryError> {
+        let entry = self
+            .entries
+            .get(name)
+            .ok_or_else(|| AddressRegistryError::NotRegistered(name.to_string()))?;
+        if let Some(addr) = entry.address {
+            return Ok(addr);
+        }
+
+        let result = self.with_module(entry.signature.module.as_deref(), |image| {
+            let matches = scan(image, &[&entry.pattern]).remove(0);
+            self.resolve_entry(image, entry, &matches)
+        });
+        let addr = result.map_err(|e| resolve_error(entry, e))??;
+        self.entries.get_mut(name).unwrap().address = Some(addr);
+        Ok(addr)
+    }
+
+    /// 获取 `address_records!` 定义的记录的地址，未注册时自动注册
+    pub fn get_record<R: SignatureRecord>(
+        &mut self,
+        _record: R,
+    ) -> Result<usize, AddressRegistryError> {
+        let name = R::INFO.full_name();
+        if !self.contains(&name) {
+            self.register(Signature::from(&R::INFO))?;
+        }
+        self.get(&name)
+    }
+
+    /// 按模块分组，一次遍历解析所有尚未解析的特征码
+    pub fn resolve_all(&mut self) -> RegistryReport {
+        let start_time = Instant::now();
+        let mut groups: BTreeMap<Option<String>, Vec<String>> = BTreeMap::new();
+        for (name, entry) in self.entries.iter() {
+            if entry.address.is_none() {
+                groups
+                    .entry(entry.signature.module.clone())
+                    .or_default()
+                    .push(name.clone());
+            }
+        }
+
+        let mut failures = Vec::new();
+        for (module, names) in groups {
+            let entries: Vec<&Entry> = names.iter().map(|n| &self.entries[n]).collect();
+            let results = self.with_module(module.as_deref(), |image| {
+                let patterns: Vec<&Pattern> = entries.iter().map(|e| &e.pattern).collect();
+                scan(image, &patterns)
+                    .iter()
+                    .zip(entries.iter())
+                    .map(|(matches, entry)| self.resolve_entry(image, entry, matches))
+                    .collect::<Vec<_>>()
+            });
+            let results: Vec<Result<usize, AddressRegistryError>> = match results {
+                Ok(results) => results,
+                Err(e) => entries
+                    .iter()
+                    .map(|entry| Err(resolve_error(entry, e.clone())))
+                    .collect(),
+            };
+            for (name, result) in names.iter().zip(results) {
+                match result {
+                    Ok(addr) => self.entries.get_mut(name).unwrap().address = Some(addr),
+                    Err(e) => failures.push(e),
+                }
+            }
+        }
+
+        let resolved = self
+            .entries
+            .values()
+            .filter(|e| e.address.is_some())
+            .count();
+        log::debug!(
+            "resolved {}/{} registered signatures",
+            resolved,
+            self.entries.len()
+        );
+        RegistryReport {
+            failures,
+            resolved,
+            elapsed: start_time.elapsed(),
+        }
+    }
+
+    /// 在特征码所在的模块中执行 `f`，模块为None时为绑定的镜像或游戏主模块
+    fn with_module<T>(
+        &self,
+        module: Option<&str>,
+        f: impl FnOnce(&PeImage) -> T,
+    ) -> Result<T, PatternScanError> {
+        let loaded;
+        let image = match (&self.image, module) {
+            (Some(image), None) => image,
+            (Some(image), Some(name)) if image.name().eq_ignore_ascii_case(name) => image,
+            (Some(_), Some(name)) => {
+                return Err(PeError::ModuleNotFound(name.to_string()).into());
+            }
+            (None, None) => {
+                loaded = PeImage::main_module()?;
+                &loaded
+            }
+            (None, Some(name)) => {
+                loaded = PeImage::find_module(name)?;
+                &loaded
+            }
+        };
+        Ok(f(image))
+    }
+
+    /// 唯一匹配加偏移后的地址，没有唯一匹配时依次尝试备用方式
+    fn resolve_entry(
+        &self,
+        image: &PeImage,
+        entry: &Entry,
+        matches: &[PatternMatch],
+    ) -> Result<usize, AddressRegistryError> {
+        if let [m] = matches {
+            return Ok(m.va.wrapping_add_signed(entry.signature.offset));
+        }
+        // 绑定了镜像时无法调用游戏函数获取版本号
+        let revision = || match self.image {
+            Some(_) => None,
+            None => AddressRepository::get_instance()
+                .lock()
+                .unwrap()
+                .game_build_revision(),
+        };
+        if let Some(hit) = resolve_fallbacks(image, entry.signature.fallbacks, revision) {
+            log::warn!(
+                "{} resolved by fallback #{} {:?}",
+                entry.signature.name,
+                hit.index,
+                hit.strategy
+            );
+            return Ok(hit.va);
+        }
+        Err(resolve_error(
+            entry,
+            match matches {
+                [] => PatternScanError::NotFound,
+                _ => PatternScanError::MultipleMatchesFound,
+            },
+        ))
+    }
+}
+
+fn scan(image: &PeImage, patterns: &[&Pattern]) -> Vec<Vec<PatternMatch>> {
+    let mut scanner = BatchScanner::new();
+    for pattern in patterns.iter() {
+        scanner.add((*pattern).clone());
+    }
+    scanner
+        .scan_module(image, SectionFilter::Executable)
+        .matches
+}
+
+fn resolve_error(entry: &Entry, source: PatternScanError) -> AddressRegistryError {
+    AddressRegistryError::Resolve {
+        name: entry.signature.name.clone(),
+        source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        game::address::{monster, tests::build_image_with},
+        utils,
+    };
+
+    #[test]
+    fn test_address_registry() {
+        let ctor = utils::space_hex_to_bytes(monster::Ctor::INFO.pattern).unwrap();
+        let image = build_image_with(&[(0x100, &ctor), (0x200, &[0x48, 0x85, 0xC9, 0x74])]);
+
+        let mut registry = AddressRegistry::with_image(image);
+        registry
+            .register_records(&[&monster::Ctor::INFO, &monster::Dtor::INFO])
+            .unwrap();
+        registry
+            .register(Signature::new("plugin::Test", "48 85 C9 74", 2))
+            .unwrap();
+        registry
+            .register(Signature::new("plugin::Other", "CC CC", 0).with_module("other.dll"))
+            .unwrap();
+        assert!(matches!(
+            registry.register(Signature::new("plugin::Test", "00", 0)),
+            Err(AddressRegistryError::Duplicate(_))
+        ));
+        assert!(matches!(
+            registry.register(Signature::new("plugin::Bad", "4X", 0)),
+            Err(AddressRegistryError::Pattern { .. })
+        ));
+
+        let report = registry.resolve_all();
+        assert_eq!(report.resolved, 2);
+        assert_eq!(report.failures.len(), 2);
+        let names: Vec<String> = registry
+            .entries()
+            .into_iter()
+            .map(|e| e.signature.name)
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "monster::Ctor",
+                "monster::Dtor",
+                "plugin::Other",
+                "plugin::Test"
+            ]
+        );
+        assert_eq!(registry.get("plugin::Test").unwrap(), 0x140001202);
+        assert_eq!(
+            registry.get_record(monster::Ctor).unwrap(),
+            0x140001100 - 60
+        );
+        assert!(matches!(
+            registry.get("plugin::Missing"),
+            Err(AddressRegistryError::NotRegistered(_))
+        ));
+    }
+
+    #[test]
+    fn test_load_is_atomic() {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!(
+            "mhw_toolkit_test_signatures_{}_{}.json",
+            std::process::id(),
+            nanos
+        ));
+        let mut registry = AddressRegistry::new();
+        registry
+            .register(Signature::new("plugin::Test", "48 85", 0))
+            .unwrap();
+
+        // 文件内任一特征码无效或重复时不注册任何特征码
+        for json in [
+            r#"[{"name":"plugin::A","pattern":"CC","offset":0},{"name":"plugin::B","pattern":"4X","offset":0}]"#,
+            r#"[{"name":"plugin::A","pattern":"CC","offset":0},{"name":"plugin::A","pattern":"CC","offset":0}]"#,
+            r#"[{"name":"plugin::A","pattern":"CC","offset":0},{"name":"plugin::Test","pattern":"CC","offset":0}]"#,
+        ] {
+            fs::write(&path, json).unwrap();
+            assert!(registry.load(&path).is_err());
+            assert!(!registry.contains("plugin::A"));
+        }
+
+        fs::write(
+            &path,
+            r#"[{"name":"plugin::A","pattern":"CC","offset":0},{"name":"plugin::B","pattern":"CC ??","offset":1}]"#,
+        )
+        .unwrap();
+        assert_eq!(registry.load(&path).unwrap(), 2);
+        assert!(registry.contains("plugin::A") && registry.contains("plugin::B"));
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,326 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+use log::{info, warn};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use strum::{AsRefStr, EnumIter, EnumString, IntoEnumIterator};
+use thiserror::Error;
+
+use super::address_cache::image_hash;
+use crate::utils::PeImage;
+
+/// 当前使用的地址表，默认为最新的内置版本
+static ACTIVE_TABLE: Lazy<RwLock<Arc<AddressTable>>> =
+    Lazy::new(|| RwLock::new(Arc::new(AddressTable::latest())));
+
+#[derive(Debug, Error)]
+pub enum AddressTableError {
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid address table file: {0}")]
+    Format(String),
+}
+
+/// 工具库使用的静态地址
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, AsRefStr, EnumString, EnumIter)]
+pub enum StaticAddress {
+    XboxPad,
+    PlayerBase,
+    QuestBase,
+    SessionBase,
+    ChatMain,
+    PlayerShortInfoBase,
+    PlayerFrameSpeedBase,
+    UGuiChatBase,
+    MessageBase,
+    SaveBase,
+}
+
+/// 某个游戏版本的静态地址表
+///
+/// 文件格式为JSON，地址使用十六进制字符串：
+///
+/// ```json
+/// {
+///   "version": "15.23.00",
+///   "revision": "421631",
+///   "image_hash": "0x8F3A5C2E4B1D6079",
+///   "addresses": { "PlayerBase": "0x1450139A0" }
+/// }
+/// ```
+///
+/// 键不限于 [`StaticAddress`]，插件可以在表中加入自己的地址并通过 [`AddressTable::get`] 读取。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AddressTable {
+    version: String,
+    /// `GetGameBuildRevision` 返回的版本号，为None时仅在没有匹配的表时作为默认值
+    #[serde(default)]
+    revision: Option<String>,
+    /// 主模块PE头部的哈希，无需调用游戏函数即可识别版本，见 [`BuildId::image_hash`](super::address_cache::BuildId::image_hash)
+    #[serde(default, with = "hex_hash", skip_serializing_if = "Option::is_none")]
+    image_hash: Option<u64>,
+    #[serde(with = "hex_addresses")]
+    addresses: BTreeMap<String, usize>,
+}
+
+impl AddressTable {
+    pub fn new(version: &str, revision: Option<&str>) -> Self {
+        Self {
+            version: version.to_string(),
+            revision: revision.map(|r| r.to_string()),
+            image_hash: None,
+            addresses: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_image_hash(mut self, image_hash: u64) -> Self {
+        self.image_hash = Some(image_hash);
+        self
+    }
+
+    pub fn with(mut self, name: impl AsRef<str>, address: usize) -> Self {
+        self.insert(name, address);
+        self
+    }
+
+    pub fn insert(&mut self, name: impl AsRef<str>, address: usize) {
+        self.addresses.insert(name.as_ref().to_string(), address);
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    pub fn revision(&self) -> Option<&str> {
+        self.revision.as_deref()
+    }
+
+    pub fn image_hash(&self) -> Option<u64> {
+        self.image_hash
+    }
+
+    pub fn get(&self, name: impl AsRef<str>) -> Option<usize> {
+        self.addresses.get(name.as_ref()).copied()
+    }
+
+    /// 表中缺少的工具库地址
+    pub fn missing(&self) -> Vec<StaticAddress> {
+        StaticAddress::iter()
+            .filter(|addr| self.get(addr).is_none())
+            .collect()
+    }
+
+    /// 内置的地址表，按版本从旧到新排列
+    ///
+    /// 内置表没有记录版本号与头部哈希，不参与按版本选择，只作为默认值；
+    /// 其他版本请通过 `dir` 中的JSON地址表支持，见 [`select_address_table`]。
+    /// `SaveBase` 尚未在 15.23.00 中确认，需要时由JSON地址表提供。
+    pub fn builtin() -> Vec<AddressTable> {
+        use StaticAddress::*;
+        vec![AddressTable::new("15.23.00", None)
+            .with(XboxPad, 0x1451C4558)
+            .with(PlayerBase, 0x1450139A0)
+            .with(QuestBase, 0x14500ED30)
+            .with(SessionBase, 0x1451C46B8)
+            .with(ChatMain, 0x14500CE70)
+            .with(PlayerShortInfoBase, 0x145013530)
+            .with(PlayerFrameSpeedBase, 0x1451238C8)
+            .with(UGuiChatBase, 0x1451C4640)
+            .with(MessageBase, 0x144F87FF0)]
+    }
+
+    /// 最新的内置地址表
+    pub fn latest() -> AddressTable {
+        Self::builtin().pop().unwrap()
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, AddressTableError> {
+        serde_json::from_str(json).map_err(|e| AddressTableError::Format(e.to_string()))
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, AddressTableError> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// 读取目录下所有的 `.json` 地址表
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<Vec<Self>, AddressTableError> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        paths.iter().map(Self::load).collect()
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), AddressTableError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| AddressTableError::Format(e.to_string()))?;
+        Ok(fs::write(path, json)?)
+    }
+
+    /// 按游戏版本号与PE头部哈希选择地址表
+    ///
+    /// 依次选择版本号相同、头部哈希相同的表；都没有时选择最后一个两者均未指定的表。
+    pub fn select<'a>(
+        tables: &'a [AddressTable],
+        revision: Option<&str>,
+        image_hash: Option<u64>,
+    ) -> Option<&'a AddressTable> {
+        let find = |f: &dyn Fn(&AddressTable) -> bool| tables.iter().rev().find(|t| f(t));
+        revision
+            .and_then(|rev| find(&|t| t.revision() == Some(rev)))
+            .or_else(|| image_hash.and_then(|hash| find(&|t| t.image_hash == Some(hash))))
+            .or_else(|| find(&|t| t.revision.is_none() && t.image_hash.is_none()))
+    }
+}
+
+/// 当前使用的地址表
+pub fn active_table() -> Arc<AddressTable> {
+    ACTIVE_TABLE.read().unwrap().clone()
+}
+
+/// 替换当前使用的地址表
+pub fn set_active_table(table: AddressTable) {
+    let missing = table.missing();
+    if !missing.is_empty() {
+        warn!(
+            "address table {} is missing: {:?}",
+            table.version(),
+            missing
+        );
+    }
+    *ACTIVE_TABLE.write().unwrap() = Arc::new(table);
+}
+
+/// 根据游戏版本号与主模块的PE头部哈希从内置表与 `dir` 下的表中选择地址表，返回选中的版本
+///
+/// 只有 `dir` 中记录了版本号或头部哈希的表会按版本选择，没有匹配时使用内置表。
+/// 版本号可由 [`AddressRepository::build_id`](super::address::AddressRepository::build_id) 获取，
+/// 为None时只按头部哈希选择，无需扫描特征码。
+pub fn select_address_table(
+    revision: Option<&str>,
+    dir: Option<&Path>,
+) -> Result<String, AddressTableError> {
+    let mut tables = AddressTable::builtin();
+    if let Some(dir) = dir {
+        tables.extend(AddressTable::load_dir(dir)?);
+    }
+    let table = AddressTable::select(&tables, revision, main_image_hash())
+        .cloned()
+        .unwrap_or_else(AddressTable::latest);
+    info!(
+        "using address table {} for game revision {:?}",
+        table.version(),
+        revision
+    );
+    let version = table.version().to_string();
+    set_active_table(table);
+    Ok(version)
+}
+
+/// 游戏主模块PE头部的哈希，与地址缓存使用的版本标识相同
+fn main_image_hash() -> Option<u64> {
+    PeImage::main_module().ok().map(|image| image_hash(&image))
+}
+
+/// 当前地址表中的地址，缺少时为0（读取时按空指针处理）
+pub fn static_address(addr: StaticAddress) -> usize {
+    ACTIVE_TABLE.read().unwrap().get(addr).unwrap_or(0)
+}
+
+mod hex_addresses {
+    use super::*;
+
+    pub fn serialize<S>(
+        addresses: &BTreeMap<String, usize>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let hex: BTreeMap<&String, String> = addresses
+            .iter()
+            .map(|(name, addr)| (name, format!("0x{:X}", addr)))
+            .collect();
+        hex.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<BTreeMap<String, usize>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let hex = BTreeMap::<String, String>::deserialize(deserializer)?;
+        hex.into_iter()
+            .map(|(name, addr)| {
+                let digits = addr.trim_start_matches("0x").trim_start_matches("0X");
+                usize::from_str_radix(digits, 16)
+                    .map(|addr| (name, addr))
+                    .map_err(|_| serde::de::Error::custom(format!("invalid address: {}", addr)))
+            })
+            .collect()
+    }
+}
+
+mod hex_hash {
+    use super::*;
+
+    pub fn serialize<S>(hash: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        hash.map(|hash| format!("0x{:016X}", hash))
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<u64>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|hex| {
+                let digits = hex.trim_start_matches("0x").trim_start_matches("0X");
+                u64::from_str_radix(digits, 16)
+                    .map_err(|_| serde::de::Error::custom(format!("invalid hash: {}", hex)))
+            })
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_address_table() {
+        let json = r#"{
+            "version": "15.24.00",
+            "revision": "999999",
+            "image_hash": "0x0123456789ABCDEF",
+            "addresses": { "PlayerBase": "0x145015BE0", "MyPluginBase": "0x140001000" }
+        }"#;
+        let table = AddressTable::from_json(json).unwrap();
+        assert_eq!(table.get(StaticAddress::PlayerBase), Some(0x145015BE0));
+        assert_eq!(table.get("MyPluginBase"), Some(0x140001000));
+        assert_eq!(table.image_hash(), Some(0x0123456789ABCDEF));
+        assert!(table.missing().contains(&StaticAddress::SaveBase));
+        let roundtrip = serde_json::to_string(&table).unwrap();
+        assert!(roundtrip.contains("\"0x145015BE0\""));
+        assert_eq!(AddressTable::from_json(&roundtrip).unwrap(), table);
+
+        let mut tables = AddressTable::builtin();
+        tables.push(table);
+        let select = |rev, hash| AddressTable::select(&tables, rev, hash).unwrap().version();
+        assert_eq!(select(Some("999999"), None), "15.24.00");
+        assert_eq!(select(None, Some(0x0123456789ABCDEF)), "15.24.00");
+        assert_eq!(select(Some("123456"), Some(1)), "15.23.00");
+        assert_eq!(select(None, None), "15.23.00");
+    }
+}
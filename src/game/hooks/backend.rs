@@ -0,0 +1,194 @@
+use std::{ffi::c_void, ptr, sync::Mutex};
+
+use super::{init_mh, HookError};
+
+/// MinHook 中目标函数已存在钩子时的错误码
+const MH_ERROR_ALREADY_CREATED: i32 = 3;
+
+/// 函数钩子的底层实现
+///
+/// 默认使用 [`MinHookBackend`]，测试中默认使用 [`MockBackend`]，见 [`default_backend`]。
+pub trait HookBackend: Send + Sync {
+    /// 创建钩子，返回用于调用原始函数的地址
+    ///
+    /// # Safety
+    ///
+    /// `target` 与 `detour` 必须是签名一致的函数。
+    unsafe fn create(&self, target: usize, detour: usize) -> Result<usize, HookError>;
+
+    fn enable(&self, target: usize) -> Result<(), HookError>;
+
+    fn disable(&self, target: usize) -> Result<(), HookError>;
+
+    fn remove(&self, target: usize) -> Result<(), HookError>;
+}
+
+/// [`HookTarget`](super::HookTarget) 未指定钩子实现时使用的实现
+///
+/// 测试中为进程内共享的 [`MockBackend`]，不会修改任何代码。
+pub fn default_backend() -> &'static dyn HookBackend {
+    #[cfg(test)]
+    {
+        static BACKEND: MockBackend = MockBackend::new();
+        &BACKEND
+    }
+    #[cfg(not(test))]
+    {
+        &MinHookBackend
+    }
+}
+
+/// 使用 MinHook 库修改目标函数
+pub struct MinHookBackend;
+
+impl HookBackend for MinHookBackend {
+    unsafe fn create(&self, target: usize, detour: usize) -> Result<usize, HookError> {
+        init_mh();
+        let mut original: *mut c_void = ptr::null_mut();
+        let status =
+            minhook_sys::MH_CreateHook(target as *mut c_void, detour as *mut c_void, &mut original);
+        if status != minhook_sys::MH_OK {
+            return Err(HookError::CreateHook(status));
+        }
+        Ok(original as usize)
+    }
+
+    fn enable(&self, target: usize) -> Result<(), HookError> {
+        let status = unsafe { minhook_sys::MH_QueueEnableHook(target as *mut c_void) };
+        if status != minhook_sys::MH_OK {
+            return Err(HookError::EnableHook(status));
+        }
+        apply_queued()
+    }
+
+    fn disable(&self, target: usize) -> Result<(), HookError> {
+        let status = unsafe { minhook_sys::MH_QueueDisableHook(target as *mut c_void) };
+        if status != minhook_sys::MH_OK {
+            return Err(HookError::DisableHook(status));
+        }
+        apply_queued()
+    }
+
+    fn remove(&self, target: usize) -> Result<(), HookError> {
+        let status = unsafe { minhook_sys::MH_RemoveHook(target as *mut c_void) };
+        if status != minhook_sys::MH_OK {
+            return Err(HookError::RemoveHook(status));
+        }
+        Ok(())
+    }
+}
+
+fn apply_queued() -> Result<(), HookError> {
+    let status = unsafe { minhook_sys::MH_ApplyQueued() };
+    if status != minhook_sys::MH_OK {
+        return Err(HookError::ApplyQueued(status));
+    }
+    Ok(())
+}
+
+/// [`MockBackend`] 中的钩子
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MockHook {
+    pub target: usize,
+    pub detour: usize,
+    pub enabled: bool,
+}
+
+/// 不修改任何代码的钩子实现，记录创建的钩子
+///
+/// 原始函数地址即为目标地址，测试中可以通过 [`MockBackend::detour`] 取得替换函数并手动调用。
+///
+/// ```ignore
+/// static BACKEND: MockBackend = MockBackend::new();
+/// let detour: extern "C" fn(i32) -> i32 = unsafe { std::mem::transmute(BACKEND.detour(target).unwrap()) };
+/// detour(1);
+/// ```
+#[derive(Default)]
+pub struct MockBackend {
+    hooks: Mutex<Vec<MockHook>>,
+    /// 下一次启用时返回的错误码
+    enable_error: Mutex<Option<i32>>,
+}
+
+impl MockBackend {
+    pub const fn new() -> Self {
+        Self {
+            hooks: Mutex::new(Vec::new()),
+            enable_error: Mutex::new(None),
+        }
+    }
+
+    /// 所有已创建的钩子
+    pub fn hooks(&self) -> Vec<MockHook> {
+        self.hooks.lock().unwrap().clone()
+    }
+
+    /// 目标函数的替换函数地址
+    pub fn detour(&self, target: usize) -> Option<usize> {
+        self.find(target).map(|hook| hook.detour)
+    }
+
+    pub fn is_enabled(&self, target: usize) -> bool {
+        self.find(target).is_some_and(|hook| hook.enabled)
+    }
+
+    /// 令下一次启用失败
+    pub fn fail_next_enable(&self, code: i32) {
+        *self.enable_error.lock().unwrap() = Some(code);
+    }
+
+    fn find(&self, target: usize) -> Option<MockHook> {
+        self.hooks
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|hook| hook.target == target)
+            .copied()
+    }
+
+    fn set_enabled(&self, target: usize, enabled: bool) -> Result<(), HookError> {
+        let mut hooks = self.hooks.lock().unwrap();
+        let hook = hooks
+            .iter_mut()
+            .find(|hook| hook.target == target)
+            .ok_or(HookError::HookNotSet)?;
+        hook.enabled = enabled;
+        Ok(())
+    }
+}
+
+impl HookBackend for MockBackend {
+    unsafe fn create(&self, target: usize, detour: usize) -> Result<usize, HookError> {
+        let mut hooks = self.hooks.lock().unwrap();
+        if hooks.iter().any(|hook| hook.target == target) {
+            return Err(HookError::CreateHook(MH_ERROR_ALREADY_CREATED));
+        }
+        hooks.push(MockHook {
+            target,
+            detour,
+            enabled: false,
+        });
+        Ok(target)
+    }
+
+    fn enable(&self, target: usize) -> Result<(), HookError> {
+        if let Some(code) = self.enable_error.lock().unwrap().take() {
+            return Err(HookError::EnableHook(code));
+        }
+        self.set_enabled(target, true)
+    }
+
+    fn disable(&self, target: usize) -> Result<(), HookError> {
+        self.set_enabled(target, false)
+    }
+
+    fn remove(&self, target: usize) -> Result<(), HookError> {
+        let mut hooks = self.hooks.lock().unwrap();
+        let len = hooks.len();
+        hooks.retain(|hook| hook.target != target);
+        if hooks.len() == len {
+            return Err(HookError::HookNotSet);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,116 @@
+use std::ffi::c_void;
+
+use crate::game::transaction::{Change, ChangeError};
+
+use super::{HookBackend, HookError, MinHookBackend};
+
+/// 可启用与禁用的函数钩子
+///
+/// 首次启用时创建钩子，禁用时保留钩子，释放时移除钩子。
+pub struct Detour {
+    name: String,
+    target: *mut c_void,
+    detour: *mut c_void,
+    original: *mut *mut c_void,
+    backend: &'static dyn HookBackend,
+    created: bool,
+    enabled: bool,
+}
+
+// 仅保存函数地址与用于写入原函数地址的静态变量，可以在线程间转移
+unsafe impl Send for Detour {}
+
+impl Detour {
+    /// # Safety
+    ///
+    /// 参数与 `MH_CreateHook` 相同：`target` 与 `detour` 必须是签名一致的函数，
+    /// `original` 必须在钩子存在期间保持有效（通常为静态变量）。
+    pub unsafe fn new(
+        name: &str,
+        target: *mut c_void,
+        detour: *mut c_void,
+        original: *mut *mut c_void,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            target,
+            detour,
+            original,
+            backend: &MinHookBackend,
+            created: false,
+            enabled: false,
+        }
+    }
+
+    /// 使用其他钩子实现，例如测试中的 [`MockBackend`](super::MockBackend)
+    pub fn with_backend(mut self, backend: &'static dyn HookBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn target(&self) -> *mut c_void {
+        self.target
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn enable(&mut self) -> Result<(), HookError> {
+        if self.enabled {
+            return Ok(());
+        }
+        if !self.created {
+            unsafe {
+                let original = self
+                    .backend
+                    .create(self.target as usize, self.detour as usize)?;
+                *self.original = original as *mut c_void;
+            }
+            self.created = true;
+        }
+        self.backend.enable(self.target as usize)?;
+        self.enabled = true;
+        Ok(())
+    }
+
+    pub fn disable(&mut self) -> Result<(), HookError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        self.backend.disable(self.target as usize)?;
+        self.enabled = false;
+        Ok(())
+    }
+}
+
+impl Drop for Detour {
+    fn drop(&mut self) {
+        if let Err(e) = self.disable() {
+            log::error!("failed to disable hook `{}`: {}", self.name, e);
+        }
+        if self.created {
+            if let Err(e) = self.backend.remove(self.target as usize) {
+                log::error!("failed to remove hook `{}`: {}", self.name, e);
+            }
+        }
+    }
+}
+
+impl Change for Detour {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn apply(&mut self) -> Result<(), ChangeError> {
+        Ok(self.enable()?)
+    }
+
+    fn revert(&mut self) -> Result<(), ChangeError> {
+        Ok(self.disable()?)
+    }
+}
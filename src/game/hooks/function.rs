@@ -0,0 +1,589 @@
+use std::{
+    ffi::c_void,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+use super::{
+    default_backend, AfterHookHandle, CallbackPosition, Detour, HookBackend, HookError, HookHandle,
+};
+
+type CallbackFn<A> = Box<dyn Fn(A) + 'static + Send + Sync>;
+type AfterCallbackFn<A, R> = Box<dyn Fn(A, &R) + 'static + Send + Sync>;
+
+enum Callback<A, R> {
+    Before(CallbackFn<A>),
+    After(AfterCallbackFn<A, R>),
+}
+
+impl<A, R> Callback<A, R> {
+    fn position(&self) -> CallbackPosition {
+        match self {
+            Callback::Before(_) => CallbackPosition::Before,
+            Callback::After(_) => CallbackPosition::After,
+        }
+    }
+}
+
+/// 定义函数钩子
+///
+/// 生成包装 [`FunctionHook`] 的钩子类型，以及调用回调与原始函数的 `extern "C"` 函数。
+/// `args` 由原始参数构造回调参数，每个回调各构造一次，且全部在调用原始函数前构造：
+/// After 回调得到的是调用时的参数，而不是原始函数返回后重新读取的值。
+/// 参数中指向游戏内存的引用仍会看到原始函数的修改，需要调用前的内容时应在 `args` 中复制，
+/// 例如 [`InputDispatchHook`](super::InputDispatchHook) 复制输入框中的文本。
+/// 标记 `skippable` 的钩子才能通过 [`HookHandle::skip_call`] 跳过原始函数，其余钩子的 `skip_call` 不做任何事。
+///
+/// ```ignore
+/// function_hook! {
+///     /// 玩家受击
+///     pub struct HitHook(address::player::Hit) {
+///         fn(arg1: *mut c_void, arg2: *mut c_void) -> i64;
+///         args: (*mut c_void, *mut c_void) = (arg1, arg2);
+///         skippable;
+///     }
+/// }
+/// ```
+macro_rules! function_hook {
+    (@output) => { () };
+    (@output $ret:ty) => { $ret };
+    (@skip_call) => {};
+    (@skip_call skippable) => {
+        fn skip_call(&self, skip: bool) -> bool {
+            self.0.skip_call(skip)
+        }
+    };
+    (
+        $(#[$meta:meta])*
+        pub struct $name:ident($provider:path) {
+            fn($($arg:ident: $arg_ty:ty),* $(,)?) $(-> $ret:ty)?;
+            args: $args_ty:ty = $args:expr;
+            $($skippable:ident;)?
+        }
+    ) => {
+        $(#[$meta])*
+        pub struct $name(
+            $crate::game::hooks::FunctionHook<$args_ty, function_hook!(@output $($ret)?)>,
+        );
+
+        impl $name {
+            pub fn new() -> Self {
+                Self($crate::game::hooks::FunctionHook::new(Self::target()))
+            }
+
+            fn target() -> &'static $crate::game::hooks::HookTarget<
+                $args_ty,
+                function_hook!(@output $($ret)?),
+            > {
+                static TARGET: $crate::game::hooks::HookTarget<
+                    $args_ty,
+                    function_hook!(@output $($ret)?),
+                > = $crate::game::hooks::HookTarget::new(
+                    stringify!($name),
+                    || {
+                        $crate::game::address::AddressRepository::get_instance()
+                            .lock()
+                            .unwrap()
+                            .get_record_address($provider)
+                    },
+                    || $name::hooked_function as *const () as usize,
+                );
+                &TARGET
+            }
+
+            extern "C" fn hooked_function($($arg: $arg_ty),*) $(-> $ret)? {
+                let target = Self::target();
+                target.call(
+                    || $args,
+                    || {
+                        let original: extern "C" fn($($arg_ty),*) $(-> $ret)? =
+                            unsafe { std::mem::transmute(target.original()) };
+                        original($($arg),*)
+                    },
+                )
+            }
+        }
+
+        impl $crate::game::hooks::HookHandle for $name {
+            type Args = $args_ty;
+
+            fn set_hook<F>(
+                &mut self,
+                position: $crate::game::hooks::CallbackPosition,
+                f: F,
+            ) -> Result<(), $crate::game::hooks::HookError>
+            where
+                F: Fn(Self::Args) + 'static + Send + Sync,
+            {
+                self.0.set_hook(position, f)
+            }
+
+            fn unset_hook(&mut self) -> Result<(), $crate::game::hooks::HookError> {
+                self.0.unset_hook()
+            }
+
+            fn is_hooked(&self) -> bool {
+                self.0.is_hooked()
+            }
+
+            function_hook!(@skip_call $($skippable)?);
+        }
+
+        impl $crate::game::hooks::AfterHookHandle for $name {
+            type Output = function_hook!(@output $($ret)?);
+
+            fn set_after_hook<F>(&mut self, f: F) -> Result<(), $crate::game::hooks::HookError>
+            where
+                F: Fn(Self::Args, &Self::Output) + 'static + Send + Sync,
+            {
+                self.0.set_after_hook(f)
+            }
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::new()
+            }
+        }
+    };
+}
+
+/// 按注册顺序调用的回调表
+///
+/// After 回调除参数外还可以读取原始函数的返回值。
+pub struct CallbackRegistry<A, R> {
+    callbacks: Mutex<Vec<(u64, Callback<A, R>)>>,
+}
+
+impl<A, R> Default for CallbackRegistry<A, R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A, R> CallbackRegistry<A, R> {
+    pub const fn new() -> Self {
+        Self {
+            callbacks: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn add_before<F>(&self, id: u64, f: F)
+    where
+        F: Fn(A) + 'static + Send + Sync,
+    {
+        self.push(id, Callback::Before(Box::new(f)));
+    }
+
+    pub fn add_after<F>(&self, id: u64, f: F)
+    where
+        F: Fn(A, &R) + 'static + Send + Sync,
+    {
+        self.push(id, Callback::After(Box::new(f)));
+    }
+
+    fn push(&self, id: u64, callback: Callback<A, R>) {
+        self.callbacks.lock().unwrap().push((id, callback));
+    }
+
+    /// 移除一个回调，成功返回true
+    pub fn remove(&self, id: u64, position: CallbackPosition) -> bool {
+        let mut callbacks = self.callbacks.lock().unwrap();
+        match callbacks
+            .iter()
+            .position(|(cb_id, callback)| *cb_id == id && callback.position() == position)
+        {
+            Some(index) => {
+                let _ = callbacks.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.callbacks.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 调用所有 Before 回调，参数在每个回调调用前由 `args` 构造
+    pub fn dispatch_before(&self, args: impl Fn() -> A) {
+        for (_, callback) in self.callbacks.lock().unwrap().iter() {
+            if let Callback::Before(f) = callback {
+                f(args());
+            }
+        }
+    }
+
+    /// After 回调的数量
+    pub fn after_len(&self) -> usize {
+        self.callbacks
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, callback)| matches!(callback, Callback::After(_)))
+            .count()
+    }
+
+    /// 按顺序调用 After 回调，每个回调使用 `args` 中的一个参数
+    ///
+    /// 参数通常在调用原始函数前构造，见 [`HookTarget::call`]
+    pub fn dispatch_after(&self, args: impl IntoIterator<Item = A>, result: &R) {
+        let callbacks = self.callbacks.lock().unwrap();
+        let after = callbacks.iter().filter_map(|(_, callback)| match callback {
+            Callback::After(f) => Some(f),
+            Callback::Before(_) => None,
+        });
+        for (f, args) in after.zip(args) {
+            f(args, result);
+        }
+    }
+}
+
+/// 函数钩子的共享状态：原始函数、回调表与安装状态
+///
+/// 每个被钩住的游戏函数对应一个静态的 `HookTarget`，通常由 `function_hook!` 生成。
+/// 安装前可以通过 [`HookTarget::set_backend`] 与 [`HookTarget::set_address`] 替换钩子实现与目标地址，
+/// 未指定时使用 [`default_backend`] 与地址仓库中的地址。
+pub struct HookTarget<A, R> {
+    name: &'static str,
+    address: fn() -> Result<usize, String>,
+    detour: fn() -> usize,
+    backend: Mutex<Option<&'static dyn HookBackend>>,
+    /// 指定的目标地址，为0时通过 `address` 获取
+    target: AtomicUsize,
+    original: AtomicUsize,
+    installed: Mutex<Option<Detour>>,
+    skip: AtomicBool,
+    callbacks: CallbackRegistry<A, R>,
+}
+
+impl<A, R> HookTarget<A, R> {
+    /// `address` 返回目标函数地址，`detour` 返回替换函数地址
+    pub const fn new(
+        name: &'static str,
+        address: fn() -> Result<usize, String>,
+        detour: fn() -> usize,
+    ) -> Self {
+        Self {
+            name,
+            address,
+            detour,
+            backend: Mutex::new(None),
+            target: AtomicUsize::new(0),
+            original: AtomicUsize::new(0),
+            installed: Mutex::new(None),
+            skip: AtomicBool::new(false),
+            callbacks: CallbackRegistry::new(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// 原始函数（跳板）地址
+    pub fn original(&self) -> *const c_void {
+        self.original.load(Ordering::SeqCst) as *const c_void
+    }
+
+    pub fn is_installed(&self) -> bool {
+        self.installed.lock().unwrap().is_some()
+    }
+
+    pub fn callbacks(&self) -> &CallbackRegistry<A, R> {
+        &self.callbacks
+    }
+
+    /// 指定钩子实现，安装后不再生效
+    pub fn set_backend(&self, backend: &'static dyn HookBackend) {
+        *self.backend.lock().unwrap() = Some(backend);
+    }
+
+    /// 指定目标函数地址而不从地址仓库获取，安装后不再生效
+    pub fn set_address(&self, address: usize) {
+        self.target.store(address, Ordering::SeqCst);
+    }
+
+    /// 首次调用时创建并启用钩子
+    pub fn install(&self) -> Result<(), HookError> {
+        if self.is_installed() {
+            return Ok(());
+        }
+        let target = match self.target.load(Ordering::SeqCst) {
+            0 => (self.address)().map_err(HookError::CannotFindAddress)?,
+            target => target,
+        };
+        let backend = self.backend.lock().unwrap().unwrap_or_else(default_backend);
+        self.install_with(backend, target)
+    }
+
+    /// 使用指定的钩子实现与目标地址创建并启用钩子，已创建时不做任何事
+    pub fn install_with(
+        &self,
+        backend: &'static dyn HookBackend,
+        target: usize,
+    ) -> Result<(), HookError> {
+        let mut installed = self.installed.lock().unwrap();
+        if installed.is_some() {
+            return Ok(());
+        }
+        let mut detour = unsafe {
+            Detour::new(
+                self.name,
+                target as *mut c_void,
+                (self.detour)() as *mut c_void,
+                self.original.as_ptr() as *mut *mut c_void,
+            )
+        }
+        .with_backend(backend);
+        detour.enable()?;
+        *installed = Some(detour);
+        Ok(())
+    }
+
+    /// 设置是否跳过原始函数与回调，状态改变时返回true
+    pub fn set_skip(&self, skip: bool) -> bool {
+        self.skip
+            .compare_exchange(!skip, skip, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+    }
+}
+
+impl<A, R: Default> HookTarget<A, R> {
+    /// 替换函数的实现：依次调用 Before 回调、原始函数与 After 回调
+    ///
+    /// After 回调的参数在调用原始函数前构造。跳过调用时直接返回默认值。
+    pub fn call(&self, args: impl Fn() -> A, original: impl FnOnce() -> R) -> R {
+        if self.skip.load(Ordering::SeqCst) {
+            return R::default();
+        }
+        self.callbacks.dispatch_before(&args);
+        let after_args: Vec<A> = (0..self.callbacks.after_len()).map(|_| args()).collect();
+        let result = original();
+        self.callbacks.dispatch_after(after_args, &result);
+        result
+    }
+}
+
+/// 函数钩子的回调句柄，释放时移除回调
+pub struct FunctionHook<A: 'static, R: 'static> {
+    target: &'static HookTarget<A, R>,
+    id: u64,
+    position: Option<CallbackPosition>,
+}
+
+impl<A: 'static, R: 'static> FunctionHook<A, R> {
+    pub fn new(target: &'static HookTarget<A, R>) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        Self {
+            target,
+            id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+            position: None,
+        }
+    }
+
+    pub fn target(&self) -> &'static HookTarget<A, R> {
+        self.target
+    }
+}
+
+impl<A: 'static, R: 'static> HookHandle for FunctionHook<A, R> {
+    type Args = A;
+
+    fn set_hook<F>(&mut self, position: CallbackPosition, f: F) -> Result<(), HookError>
+    where
+        F: Fn(Self::Args) + 'static + Send + Sync,
+    {
+        self.target.install()?;
+        self.position = Some(position);
+        match position {
+            CallbackPosition::Before => self.target.callbacks.add_before(self.id, f),
+            CallbackPosition::After => self
+                .target
+                .callbacks
+                .add_after(self.id, move |args, _| f(args)),
+        }
+        Ok(())
+    }
+
+    fn unset_hook(&mut self) -> Result<(), HookError> {
+        let position = self.position.ok_or(HookError::HookNotSet)?;
+        if !self.target.callbacks.remove(self.id, position) {
+            return Err(HookError::HookNotSet);
+        }
+        self.position = None;
+        Ok(())
+    }
+
+    fn is_hooked(&self) -> bool {
+        self.position.is_some()
+    }
+
+    fn skip_call(&self, skip: bool) -> bool {
+        if self.target.install().is_err() {
+            return false;
+        }
+        self.target.set_skip(skip)
+    }
+}
+
+impl<A: 'static, R: 'static> AfterHookHandle for FunctionHook<A, R> {
+    type Output = R;
+
+    fn set_after_hook<F>(&mut self, f: F) -> Result<(), HookError>
+    where
+        F: Fn(Self::Args, &Self::Output) + 'static + Send + Sync,
+    {
+        self.target.install()?;
+        self.position = Some(CallbackPosition::After);
+        self.target.callbacks.add_after(self.id, f);
+        Ok(())
+    }
+}
+
+impl<A: 'static, R: 'static> Drop for FunctionHook<A, R> {
+    fn drop(&mut self) {
+        let _ = self.unset_hook();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::game::hooks::MockBackend;
+
+    static BACKEND: MockBackend = MockBackend::new();
+
+    extern "C" fn add(a: i32, b: i32) -> i32 {
+        a + b
+    }
+
+    extern "C" fn neg(a: i32) -> i32 {
+        -a
+    }
+
+    extern "C" fn take(value: *mut i32) -> i32 {
+        unsafe { std::mem::take(&mut *value) }
+    }
+
+    function_hook! {
+        pub struct AddHook(crate::game::address::monster::Ctor) {
+            fn(a: i32, b: i32) -> i32;
+            args: (i32, i32) = (a, b);
+            skippable;
+        }
+    }
+
+    function_hook! {
+        pub struct NegHook(crate::game::address::monster::Dtor) {
+            fn(a: i32) -> i32;
+            args: i32 = a;
+        }
+    }
+
+    function_hook! {
+        pub struct TakeHook(crate::game::address::player::Hit) {
+            fn(value: *mut i32) -> i32;
+            args: i32 = unsafe { *value };
+        }
+    }
+
+    #[test]
+    fn test_after_args() {
+        TakeHook::target().set_address(take as *const () as usize);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut hook = TakeHook::new();
+        let after_seen = seen.clone();
+        hook.set_after_hook(move |value, result| after_seen.lock().unwrap().push((value, *result)))
+            .unwrap();
+
+        // After 回调的参数在调用原始函数前构造
+        let mut value = 7;
+        assert_eq!(TakeHook::hooked_function(&mut value), 7);
+        assert_eq!(value, 0);
+        assert_eq!(*seen.lock().unwrap(), vec![(7, 7)]);
+    }
+
+    #[test]
+    fn test_function_hook() {
+        let target = add as *const () as usize;
+        AddHook::target().set_backend(&BACKEND);
+        AddHook::target().set_address(target);
+        BACKEND.fail_next_enable(5);
+        assert!(matches!(
+            AddHook::target().install(),
+            Err(HookError::EnableHook(5))
+        ));
+        assert!(BACKEND.hooks().is_empty());
+        AddHook::target().install().unwrap();
+        assert!(BACKEND.is_enabled(target));
+        let detour: extern "C" fn(i32, i32) -> i32 =
+            unsafe { std::mem::transmute(BACKEND.detour(target).unwrap()) };
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let add_hook = |name: &'static str| {
+            let calls = calls.clone();
+            let mut hook = AddHook::new();
+            hook.set_hook(CallbackPosition::Before, move |args| {
+                calls.lock().unwrap().push((name, args, None))
+            })
+            .unwrap();
+            hook
+        };
+        let mut first = add_hook("first");
+        let mut after = AddHook::new();
+        let after_calls = calls.clone();
+        after
+            .set_after_hook(move |args, result| {
+                after_calls
+                    .lock()
+                    .unwrap()
+                    .push(("after", args, Some(*result)))
+            })
+            .unwrap();
+        let second = add_hook("second");
+        assert_eq!(detour(1, 2), 3);
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![
+                ("first", (1, 2), None),
+                ("second", (1, 2), None),
+                ("after", (1, 2), Some(3)),
+            ]
+        );
+
+        // 移除后不再调用
+        first.unset_hook().unwrap();
+        assert!(matches!(first.unset_hook(), Err(HookError::HookNotSet)));
+        drop(second);
+        calls.lock().unwrap().clear();
+        assert_eq!(detour(3, 4), 7);
+        assert_eq!(*calls.lock().unwrap(), vec![("after", (3, 4), Some(7))]);
+
+        // 跳过调用时返回默认值，不调用回调
+        assert!(after.skip_call(true));
+        assert_eq!(detour(3, 4), 0);
+        assert!(after.skip_call(false));
+        assert_eq!(calls.lock().unwrap().len(), 1);
+        after.unset_hook().unwrap();
+        assert!(AddHook::target().callbacks().is_empty());
+
+        // 未指定钩子实现时测试中使用 MockBackend
+        NegHook::target().set_address(neg as *const () as usize);
+        NegHook::target().install().unwrap();
+        assert!(NegHook::target().is_installed());
+
+        // 未标记 skippable 的钩子不会跳过原始函数
+        let neg = NegHook::new();
+        neg.skip_call(true);
+        assert_eq!(NegHook::target().call(|| 1, || -1), -1);
+    }
+}
@@ -1,12 +1,19 @@
+#[macro_use]
+mod function;
 mod action;
+mod backend;
 mod chat;
+mod detour;
 mod hit;
 mod monster;
 
 use std::sync::Once;
 
 pub use action::*;
+pub use backend::*;
 pub use chat::*;
+pub use detour::*;
+pub use function::*;
 pub use hit::*;
 pub use monster::*;
 
@@ -18,8 +25,17 @@ static INITIALIZE_ONCE: Once = Once::new();
 pub enum HookError {
     #[error("failed to create hook (code {0})")]
     CreateHook(i32),
+    #[error("failed to enable hook (code {0})")]
+    EnableHook(i32),
+    #[error("failed to disable hook (code {0})")]
+    DisableHook(i32),
+    #[error("failed to remove hook (code {0})")]
+    RemoveHook(i32),
+    #[error("failed to apply queued hooks (code {0})")]
+    ApplyQueued(i32),
     #[error("hook not set")]
     HookNotSet,
+    #[deprecated(note = "所有钩子均支持 Before 与 After 回调，不再返回此错误")]
     #[error("the hook position is unsuppported")]
     UnsupportedPosition,
     #[error("cannot find address of {0}")]
@@ -52,7 +68,19 @@ pub trait HookHandle {
 
     fn is_hooked(&self) -> bool;
 
+    /// 设置是否跳过原始函数，不支持跳过的钩子不做任何事
     fn skip_call(&self, skip: bool) -> bool {
         skip
     } // with default implementation
 }
+
+/// 可以在 After 回调中读取原始函数返回值的钩子
+pub trait AfterHookHandle: HookHandle {
+    /// 原始函数的返回值
+    type Output;
+
+    /// 设置 After 回调，参数为函数参数与原始函数的返回值
+    fn set_after_hook<F>(&mut self, f: F) -> Result<(), HookError>
+    where
+        F: Fn(Self::Args, &Self::Output) + 'static + Send + Sync;
+}
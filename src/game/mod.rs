@@ -1,6 +1,11 @@
 pub mod address;
+pub mod address_cache;
+pub mod address_registry;
+pub mod address_table;
 pub mod mt_types;
 pub mod resources;
+pub mod snapshot;
+pub mod transaction;
 
 #[cfg(feature = "hooks")]
 pub mod hooks;
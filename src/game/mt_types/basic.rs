@@ -1,3 +1,5 @@
+use crate::utils::{self, MemoryError};
+
 use super::MtDti;
 
 /// Mt对象
@@ -13,19 +15,49 @@ pub trait MtObject {
 /// 提供资源内字段的访问操作
 pub trait Resource: MtObject {
     /// 获得对象的成员的引用
+    ///
+    /// 仅可用于当前进程的内存，内存快照请使用 [`Resource::get_value_copy`]
     fn get_value_ref<T>(&self, offset: isize) -> &'static T {
-        unsafe {
-            let ptr: *const T = (self.get_instance() as isize + offset) as *const T;
-            ptr.as_ref().unwrap()
-        }
+        let addr = self.get_instance().wrapping_add_signed(offset);
+        utils::get_ref_with_offset(addr as *const T, &[])
+            .unwrap_or_else(|| panic!("failed to access memory at 0x{:X}", addr))
     }
 
     /// 获得对象的成员的可变引用
+    ///
+    /// 仅可用于当前进程的内存，内存快照请使用 [`Resource::with_value_mut`]
     fn get_value_mut<T>(&self, offset: isize) -> &'static mut T {
-        unsafe {
-            let ptr: *const T = (self.get_instance() as isize + offset) as *const T;
-            ptr.cast_mut().as_mut().unwrap()
-        }
+        let addr = self.get_instance().wrapping_add_signed(offset);
+        utils::get_mut_with_offset(addr as *mut T, &[])
+            .unwrap_or_else(|| panic!("failed to access memory at 0x{:X}", addr))
+    }
+
+    /// 在闭包内修改对象的成员，引用不会超出闭包
+    ///
+    /// 当前进程的内存直接修改；其他数据源读取副本，闭包返回后写回。
+    /// 地址不可读，或当前进程的内存中地址未按 `T` 对齐时返回None
+    fn with_value_mut<T, R>(&self, offset: isize, f: impl FnOnce(&mut T) -> R) -> Option<R>
+    where
+        T: Copy,
+    {
+        let addr = self.get_instance().wrapping_add_signed(offset);
+        let len = std::mem::size_of::<T>();
+        utils::with_memory_source(|source| {
+            if !source.is_readable(addr, len) {
+                return None;
+            }
+            if source.is_live() {
+                let ptr = source.host_ptr(addr, len)? as *mut T;
+                if !ptr.is_aligned() {
+                    return None;
+                }
+                return Some(f(unsafe { &mut *ptr }));
+            }
+            let mut value = source.read_value::<T>(addr)?;
+            let result = f(&mut value);
+            source.write_value(addr, value).ok()?;
+            Some(result)
+        })
     }
 
     /// 获得对象的成员的副本
@@ -33,9 +65,43 @@ pub trait Resource: MtObject {
     where
         T: Copy,
     {
-        unsafe {
-            let ptr = (self.get_instance() as isize + offset) as *const T;
-            *ptr
+        let addr = self.get_instance().wrapping_add_signed(offset);
+        utils::with_memory_source(|source| source.read_value(addr))
+            .unwrap_or_else(|| panic!("failed to read memory at 0x{:X}", addr))
+    }
+
+    /// 获得对象的成员的副本，读取前确认地址可读
+    fn try_get_value<T>(&self, offset: isize) -> Result<T, MemoryError>
+    where
+        T: Copy,
+    {
+        let addr = self.get_instance().wrapping_add_signed(offset);
+        utils::with_memory_source(|source| source.try_read_value(addr))
+    }
+
+    /// 从对象的成员开始按多级偏移取值，每一级取值前确认地址可读
+    ///
+    /// `offset` 处的成员为第0级指针
+    fn try_get_value_with_offsets<T>(
+        &self,
+        offset: isize,
+        offsets: &[isize],
+    ) -> Result<T, MemoryError>
+    where
+        T: Copy,
+    {
+        let addr = self.get_instance().wrapping_add_signed(offset);
+        utils::try_get_value_with_offset(addr as *const T, offsets)
+    }
+
+    /// 获得对象的MtObject成员（指针指向的对象），指针不可读或为空时返回错误
+    fn try_get_object<T>(&self, offset: isize) -> Result<T, MemoryError>
+    where
+        T: MtObject,
+    {
+        match self.try_get_value::<usize>(offset)? {
+            0 => Err(MemoryError::NullPointer { index: 0 }),
+            ptr => Ok(T::from_instance(ptr)),
         }
     }
 
@@ -44,10 +110,42 @@ pub trait Resource: MtObject {
     where
         T: MtObject,
     {
-        unsafe {
-            let ptr = (self.get_instance() as isize + offset) as *const *const T;
-            T::from_instance(*ptr as usize)
-        }
+        T::from_instance(self.get_value_copy::<usize>(offset))
+    }
+
+    /// 获得对象的以空字节结尾的字符串成员（的副本）
+    ///
+    /// 读取失败时返回空字符串，见 [`Resource::try_get_c_str`]
+    fn get_c_str(&self, offset: isize) -> String {
+        self.try_get_c_str(offset).unwrap_or_default()
+    }
+
+    /// 获得对象的以空字节结尾的字符串成员（的副本）
+    ///
+    /// 按内存页逐段确认可读后读取，直到空字节；读到不可读的地址或无效的UTF-8时返回None
+    fn try_get_c_str(&self, offset: isize) -> Option<String> {
+        const PAGE_SIZE: usize = 0x1000;
+        let addr = self.get_instance().wrapping_add_signed(offset);
+        utils::with_memory_source(|source| {
+            let mut bytes = Vec::new();
+            let mut cursor = addr;
+            loop {
+                let len = PAGE_SIZE - cursor % PAGE_SIZE;
+                if !source.is_readable(cursor, len) {
+                    return None;
+                }
+                let start = bytes.len();
+                bytes.resize(start + len, 0);
+                if !source.read_bytes(cursor, &mut bytes[start..]) {
+                    return None;
+                }
+                if let Some(nul) = bytes[start..].iter().position(|&b| b == 0) {
+                    bytes.truncate(start + nul);
+                    return String::from_utf8(bytes).ok();
+                }
+                cursor += len;
+            }
+        })
     }
 
     /// 获得对象的MtObject成员（inline对象）
@@ -61,7 +159,7 @@ pub trait Resource: MtObject {
 
     /// 获取对象的虚函数
     unsafe fn get_virtual_function(&self, index: isize) -> usize {
-        let vtable = *(self.get_instance() as *const *const usize);
+        let vtable = self.get_value_copy::<*const usize>(0);
         let vfptr = vtable.offset(index);
 
         vfptr as usize
@@ -1,5 +1,7 @@
 use std::marker::PhantomData;
 
+use crate::utils;
+
 use super::{MtObject, Resource};
 
 pub struct MtArray<T> {
@@ -52,7 +54,8 @@ where
 
     /// 数据域
     unsafe fn data(&self) -> *const T {
-        *(self.get_value_copy::<usize>(0x18) as *const *const T)
+        let data_ptr = self.get_value_copy::<usize>(0x18) as *const *const T;
+        utils::get_value_with_offset(data_ptr, &[]).unwrap_or(std::ptr::null())
     }
 
     pub fn object_at(&self, index: isize) -> T {
@@ -1,13 +1,12 @@
 use std::str::FromStr;
 
-use crate::utils;
+use crate::{game_export, utils};
 
 use super::{
     consumables::ConsumableService, debuffs::DebuffService, Consumable, Debuff, HUEAbnormality,
     PalicoAbnormality, SkillAbnormality,
 };
 
-const BASE_ADDRESS: *const usize = 0x145011760 as *const usize;
 const OFFSETS: &[isize] = &[0x50, 0x7D20];
 
 pub struct AbnormalityService {
@@ -18,7 +17,8 @@ pub struct AbnormalityService {
 
 impl AbnormalityService {
     pub fn new() -> Option<Self> {
-        let instance = utils::get_ptr_with_offset(BASE_ADDRESS, OFFSETS).map(|ptr| ptr as usize)?;
+        let instance = utils::get_ptr_with_offset(game_export::player_base(), OFFSETS)
+            .map(|ptr| ptr as usize)?;
         Some(Self {
             instance,
             consumable_service: ConsumableService::new(instance),
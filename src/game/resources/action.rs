@@ -40,9 +40,7 @@ impl ActionController {
     }
 
     pub fn force_derive(&self, action: ActionInfo) {
-        let current_action = self.get_value_mut::<ActionInfo>(0xBC);
-        current_action.set = action.set;
-        current_action.id = action.id;
+        self.with_value_mut(0xBC, |next: &mut ActionInfo| *next = action);
     }
 
     /// 控制器持有者（Entity）
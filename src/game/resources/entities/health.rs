@@ -30,4 +30,12 @@ impl Health {
     pub fn current_mut(&self) -> &mut f32 {
         self.get_value_mut(0x64)
     }
+
+    pub fn set_max(&self, max: f32) {
+        self.with_value_mut(0x60, |val: &mut f32| *val = max);
+    }
+
+    pub fn set_current(&self, current: f32) {
+        self.with_value_mut(0x64, |val: &mut f32| *val = current);
+    }
 }
@@ -1,6 +1,6 @@
 use crate::game::mt_types::{Model, MtObject, Resource};
 
-use super::Entity;
+use super::{Entity, Health};
 
 #[derive(Clone)]
 pub struct Monster {
@@ -30,23 +30,38 @@ impl Monster {
         self.get_value_copy(0x12288)
     }
 
+    /// 仅可用于当前进程的内存，内存快照请使用 [`Monster::health_value`]
     pub fn health(&self) -> &'static f32 {
-        let ptr = self.get_value_copy::<usize>(0x7670) + 0x64;
-        unsafe { (ptr as *const f32).as_ref().unwrap() }
+        self.get_object::<Health>(0x7670).get_value_ref(0x64)
     }
 
+    /// 仅可用于当前进程的内存，内存快照请使用 [`Monster::max_health_value`]
     pub fn max_health(&self) -> &'static f32 {
-        let ptr = self.get_value_copy::<usize>(0x7670) + 0x60;
-        unsafe { (ptr as *const f32).as_ref().unwrap() }
+        self.get_object::<Health>(0x7670).get_value_ref(0x60)
     }
 
+    /// 当前生命值（的副本），可用于内存快照
+    pub fn health_value(&self) -> f32 {
+        self.get_object::<Health>(0x7670).current()
+    }
+
+    /// 最大生命值（的副本），可用于内存快照
+    pub fn max_health_value(&self) -> f32 {
+        self.get_object::<Health>(0x7670).max()
+    }
+
+    /// 仅可用于当前进程的内存，内存快照请使用 [`Monster::speed_value`]
     pub fn speed(&self) -> &'static f32 {
         self.get_value_ref(0x1D8A8)
     }
 
+    /// 速度（的副本），可用于内存快照
+    pub fn speed_value(&self) -> f32 {
+        self.get_value_copy(0x1D8A8)
+    }
+
     pub fn set_speed(&self, speed: f32) {
-        let val = self.get_value_mut(0x1D8A8);
-        *val = speed;
+        self.with_value_mut(0x1D8A8, |val: &mut f32| *val = speed);
     }
 
     pub fn ai_data(&self) -> usize {
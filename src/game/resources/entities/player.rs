@@ -40,8 +40,11 @@ impl std::fmt::Debug for Player {
 impl Player {
     /// 获取当前操控的玩家对象
     pub fn current_player() -> Option<Self> {
-        let player_addr =
-            utils::get_value_with_offset(game_export::PLAYER_BASE, game_export::PLAYER_OFFSET)?;
+        let player_addr = utils::try_get_value_with_offset(
+            game_export::player_base(),
+            game_export::PLAYER_OFFSET,
+        )
+        .ok()?;
         if player_addr == 0 {
             return None;
         }
@@ -57,7 +60,8 @@ impl Player {
             return Self::current_player();
         }
         let offset = 0x58 + 0x740 * index;
-        let player_addr = utils::get_value_with_offset(game_export::PLAYER_BASE, &[offset])?;
+        let player_addr =
+            utils::try_get_value_with_offset(game_export::player_base(), &[offset]).ok()?;
         if !(0x10000..0x150000000).contains(&player_addr) && player_addr != u32::MAX as usize {
             return None;
         }
@@ -67,14 +71,11 @@ impl Player {
 
     pub fn frame_speed_multiplier_mut(&self) -> &'static mut f32 {
         let addr = self.frame_speed_multiplier_addr();
-        unsafe { (addr as *mut f32).as_mut().unwrap() }
+        utils::get_mut_with_offset(addr as *mut f32, &[]).unwrap()
     }
 
     pub fn info(&self) -> Option<PlayerInfo> {
-        let info_addr = utils::get_value_with_offset(
-            (self.get_instance() + 0xC0) as *const usize,
-            &[0x8, 0x78],
-        )?;
+        let info_addr = self.try_get_value_with_offsets(0xC0, &[0x8, 0x78]).ok()?;
 
         Some(PlayerInfo::from_instance(info_addr))
     }
@@ -101,12 +102,11 @@ impl Player {
     }
 
     fn frame_speed_multiplier_addr(&self) -> usize {
-        unsafe {
-            let a = *(game_export::PLAYER_FRAME_SPEED_BASE) as usize;
-            let b = *((self.get_instance() + 0x10) as *const i32) as usize;
+        let a = utils::get_value_with_offset(game_export::player_frame_speed_base(), &[])
+            .unwrap_or_default() as usize;
+        let b = self.get_value_copy::<i32>(0x10) as usize;
 
-            a + b * 0xF8 + 0x9C
-        }
+        a + b * 0xF8 + 0x9C
     }
 }
 
@@ -135,7 +135,7 @@ impl PlayerInfo {
         }
 
         let offset = 0x58 + 0x740 * index;
-        let info_ptr = utils::get_ptr_with_offset(game_export::PLAYER_BASE, &[offset])?;
+        let info_ptr = utils::get_ptr_with_offset(game_export::player_base(), &[offset])?;
         if !(0x10000..0x150000000).contains(&(info_ptr as usize)) {
             return None;
         }
@@ -163,6 +163,11 @@ impl PlayerInfo {
         unsafe { CStr::from_ptr(name_ptr).to_str().unwrap_or_default() }
     }
 
+    /// 玩家名称（的副本），读取前确认地址可读，可用于内存快照
+    pub fn try_name(&self) -> Option<String> {
+        self.try_get_c_str(0x78)
+    }
+
     pub fn steam_id(&self) -> u64 {
         self.get_value_copy(0xE8)
     }
@@ -192,8 +197,10 @@ impl PlayerShortInfo {
             return None;
         }
         let offset = index * 0x58;
-        let ptr =
-            utils::get_value_with_offset(game_export::PLAYER_SHORT_INFO_BASE, &[0x1AB0 + offset])?;
+        let ptr = utils::get_value_with_offset(
+            game_export::player_short_info_base(),
+            &[0x1AB0 + offset],
+        )?;
         if ptr == 0 {
             return None;
         }
@@ -223,6 +230,11 @@ impl PlayerShortInfo {
         unsafe { CStr::from_ptr(name_ptr).to_str().unwrap_or_default() }
     }
 
+    /// 玩家名称（的副本），读取前确认地址可读，可用于内存快照
+    pub fn try_name(&self) -> Option<String> {
+        self.try_get_c_str(0x49)
+    }
+
     pub fn level(&self) -> ShortLevelInfo {
         self.get_value_copy(0x70)
     }
@@ -1,6 +1,6 @@
 use crate::{
     game::prelude::{MtObject, Resource},
-    game_export,
+    game_export, utils,
 };
 
 pub struct Quest {
@@ -19,7 +19,7 @@ impl MtObject for Quest {
 
 impl Quest {
     pub fn new_static() -> Option<Self> {
-        let ptr = unsafe { *(game_export::QUEST_BASE) };
+        let ptr = utils::get_value_with_offset(game_export::quest_base(), &[])?;
         if ptr < 65536 {
             None
         } else {
@@ -35,15 +35,35 @@ impl Quest {
         self.get_value_mut(0x38)
     }
 
+    pub fn set_quest_state(&self, state: i32) {
+        self.with_value_mut(0x38, |val: &mut i32| *val = state);
+    }
+
     pub fn quest_timer_max(&self) -> f32 {
         self.get_value_copy(0x13198 + 0x0C)
     }
 
+    pub fn quest_timer(&self) -> f32 {
+        self.get_value_copy(0x13198 + 0x08)
+    }
+
     pub fn quest_timer_mut(&self) -> &mut f32 {
         self.get_value_mut(0x13198 + 0x08)
     }
 
+    pub fn set_quest_timer(&self, timer: f32) {
+        self.with_value_mut(0x13198 + 0x08, |val: &mut f32| *val = timer);
+    }
+
+    pub fn ensurance_state(&self) -> i8 {
+        self.get_value_copy(0x17384)
+    }
+
     pub fn ensurance_state_mut(&self) -> &mut i8 {
         self.get_value_mut(0x17384)
     }
+
+    pub fn set_ensurance_state(&self, state: i8) {
+        self.with_value_mut(0x17384, |val: &mut i8| *val = state);
+    }
 }
@@ -1,10 +1,8 @@
 use crate::{
     game::prelude::{MtObject, Resource},
-    utils,
+    game_export, utils,
 };
 
-const SAVE_BASE: *const usize = 0x145011710 as *const usize;
-
 pub struct SaveData {
     instance: usize,
     save_offset: usize,
@@ -28,7 +26,7 @@ impl SaveData {
         if !(0..=2).contains(&index) {
             return None;
         }
-        let instance = utils::get_value_with_offset(SAVE_BASE, &[0xA8])?;
+        let instance = utils::get_value_with_offset(game_export::save_base(), &[0xA8])?;
         let save_offset = index as usize * 0x26CC00;
 
         Some(Self {
@@ -43,6 +41,6 @@ impl SaveData {
     }
 
     fn current_save_slot() -> Option<i32> {
-        utils::get_value_with_offset(SAVE_BASE as *const i32, &[0xA0])
+        utils::get_value_with_offset(game_export::save_base() as *const i32, &[0xA0])
     }
 }
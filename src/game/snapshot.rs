@@ -0,0 +1,391 @@
+use std::{fs, ops::Range, path::Path, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::{
+    game::mt_types::MtObject,
+    utils::{self, MemoryError},
+};
+
+pub use crate::utils::{Comparison, FieldType, FieldValue};
+
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error(transparent)]
+    Memory(#[from] MemoryError),
+    #[error("snapshots have different ranges: 0x{0:X}+0x{1:X} and 0x{2:X}+0x{3:X}")]
+    RangeMismatch(usize, usize, usize, usize),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid snapshot file: {0}")]
+    Format(String),
+}
+
+/// 字段在两次快照中的值
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FieldChange {
+    pub offset: usize,
+    pub old: FieldValue,
+    pub new: FieldValue,
+}
+
+/// 一段内存（通常为一个 `MtObject`）在某一时刻的副本
+///
+/// 用于比较不同时刻的内存，查找随游戏状态变化的字段偏移。
+/// 快照可以保存到文件，离线进行分析。
+///
+/// ```ignore
+/// let before = StructSnapshot::of(&quest, 0x18000)?;
+/// // 在游戏中改变状态 ...
+/// let after = StructSnapshot::of(&quest, 0x18000)?;
+/// for change in before.compare(&after, FieldType::I8, Comparison::Increased)? {
+///     println!("0x{:X}: {:?} -> {:?}", change.offset, change.old, change.new);
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StructSnapshot {
+    base: usize,
+    label: String,
+    /// 捕获时间，UNIX时间戳（毫秒）
+    timestamp: u64,
+    #[serde(with = "hex_bytes")]
+    bytes: Vec<u8>,
+}
+
+impl StructSnapshot {
+    /// 由已有的字节创建快照
+    pub fn new(base: usize, bytes: &[u8]) -> Self {
+        Self {
+            base,
+            label: String::new(),
+            timestamp: now_millis(),
+            bytes: bytes.to_vec(),
+        }
+    }
+
+    /// 读取 `[base, base + len)` 的内存，读取前确认地址可读
+    pub fn capture(base: usize, len: usize) -> Result<Self, SnapshotError> {
+        let mut bytes = vec![0_u8; len];
+        utils::with_memory_source(|source| {
+            if source.is_readable(base, len) && source.read_bytes(base, &mut bytes) {
+                Ok(())
+            } else {
+                Err(MemoryError::Unreadable { address: base, len })
+            }
+        })?;
+        Ok(Self::new(base, &bytes))
+    }
+
+    /// 读取对象起始的 `len` 字节
+    pub fn of(object: &impl MtObject, len: usize) -> Result<Self, SnapshotError> {
+        Self::capture(object.get_instance(), len)
+    }
+
+    /// 重新读取同一段内存
+    pub fn recapture(&self) -> Result<Self, SnapshotError> {
+        Self::capture(self.base, self.bytes.len())
+    }
+
+    pub fn with_label(mut self, label: &str) -> Self {
+        self.label = label.to_string();
+        self
+    }
+
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// 读取偏移处的字段，超出快照范围时返回None
+    pub fn field(&self, offset: usize, field_type: FieldType) -> Option<FieldValue> {
+        let bytes = self
+            .bytes
+            .get(offset..offset.checked_add(field_type.size())?)?;
+        Some(field_type.decode(bytes))
+    }
+
+    /// 内容发生变化的字节范围
+    pub fn changed_ranges(&self, later: &Self) -> Result<Vec<Range<usize>>, SnapshotError> {
+        self.check_range(later)?;
+        Ok(byte_ranges(&self.bytes, &later.bytes, |a, b| a != b))
+    }
+
+    /// 内容未变化的字节范围
+    pub fn unchanged_ranges(&self, later: &Self) -> Result<Vec<Range<usize>>, SnapshotError> {
+        self.check_range(later)?;
+        Ok(byte_ranges(&self.bytes, &later.bytes, |a, b| a == b))
+    }
+
+    /// 按字段类型比较两次快照，返回满足条件的字段
+    ///
+    /// 字段按自身大小对齐（最多4字节），与游戏中结构体的布局一致
+    pub fn compare(
+        &self,
+        later: &Self,
+        field_type: FieldType,
+        comparison: Comparison,
+    ) -> Result<Vec<FieldChange>, SnapshotError> {
+        self.compare_by(later, field_type, |old, new| comparison.matches(old, new))
+    }
+
+    /// 按字段类型比较两次快照，返回满足自定义条件的字段
+    pub fn compare_by(
+        &self,
+        later: &Self,
+        field_type: FieldType,
+        predicate: impl Fn(FieldValue, FieldValue) -> bool,
+    ) -> Result<Vec<FieldChange>, SnapshotError> {
+        self.check_range(later)?;
+        Ok(aligned_offsets(self.len(), field_type)
+            .filter_map(|offset| {
+                let old = self.field(offset, field_type)?;
+                let new = later.field(offset, field_type)?;
+                predicate(old, new).then_some(FieldChange { offset, old, new })
+            })
+            .collect())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SnapshotError> {
+        let json = serde_json::to_string(self).map_err(|e| SnapshotError::Format(e.to_string()))?;
+        Ok(fs::write(path, json)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SnapshotError> {
+        let json = fs::read_to_string(path)?;
+        serde_json::from_str(&json).map_err(|e| SnapshotError::Format(e.to_string()))
+    }
+
+    fn check_range(&self, other: &Self) -> Result<(), SnapshotError> {
+        if self.base != other.base || self.len() != other.len() {
+            return Err(SnapshotError::RangeMismatch(
+                self.base,
+                self.len(),
+                other.base,
+                other.len(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 多次快照之间逐步缩小候选字段
+///
+/// 与CE的“未知初始值”搜索类似：每次捕获新快照后，只保留满足条件的偏移。
+///
+/// ```ignore
+/// let mut scan = FieldScan::new(StructSnapshot::of(&monster, 0x20000)?, FieldType::F32);
+/// // 怪物减速后
+/// scan.capture_next(Comparison::Decreased)?;
+/// // 怪物恢复速度后
+/// scan.capture_next(Comparison::Increased)?;
+/// println!("{:X?}", scan.candidates());
+/// ```
+#[derive(Debug, Clone)]
+pub struct FieldScan {
+    field_type: FieldType,
+    candidates: Vec<usize>,
+    last: StructSnapshot,
+}
+
+impl FieldScan {
+    /// 以初始快照开始搜索，所有对齐的偏移均为候选
+    pub fn new(initial: StructSnapshot, field_type: FieldType) -> Self {
+        Self {
+            field_type,
+            candidates: aligned_offsets(initial.len(), field_type).collect(),
+            last: initial,
+        }
+    }
+
+    pub fn field_type(&self) -> FieldType {
+        self.field_type
+    }
+
+    pub fn candidates(&self) -> &[usize] {
+        &self.candidates
+    }
+
+    /// 最近一次快照
+    pub fn last(&self) -> &StructSnapshot {
+        &self.last
+    }
+
+    /// 用新快照筛选候选偏移，返回剩余的候选
+    pub fn next(
+        &mut self,
+        snapshot: StructSnapshot,
+        comparison: Comparison,
+    ) -> Result<&[usize], SnapshotError> {
+        self.last.check_range(&snapshot)?;
+        let field_type = self.field_type;
+        let last = &self.last;
+        self.candidates.retain(|&offset| {
+            match (
+                last.field(offset, field_type),
+                snapshot.field(offset, field_type),
+            ) {
+                (Some(old), Some(new)) => comparison.matches(old, new),
+                _ => false,
+            }
+        });
+        self.last = snapshot;
+        Ok(&self.candidates)
+    }
+
+    /// 重新读取内存并筛选候选偏移
+    pub fn capture_next(&mut self, comparison: Comparison) -> Result<&[usize], SnapshotError> {
+        let snapshot = self.last.recapture()?;
+        self.next(snapshot, comparison)
+    }
+
+    /// 候选偏移在最近一次快照中的值
+    pub fn values(&self) -> Vec<(usize, FieldValue)> {
+        self.candidates
+            .iter()
+            .filter_map(|&offset| Some((offset, self.last.field(offset, self.field_type)?)))
+            .collect()
+    }
+}
+
+fn aligned_offsets(len: usize, field_type: FieldType) -> impl Iterator<Item = usize> {
+    let size = field_type.size();
+    let align = size.min(4);
+    (0..len.saturating_sub(size - 1)).step_by(align)
+}
+
+fn byte_ranges(a: &[u8], b: &[u8], keep: impl Fn(u8, u8) -> bool) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for (i, (&x, &y)) in a.iter().zip(b.iter()).enumerate() {
+        if !keep(x, y) {
+            continue;
+        }
+        match ranges.last_mut() {
+            Some(range) if range.end == i => range.end = i + 1,
+            _ => ranges.push(i..i + 1),
+        }
+    }
+    ranges
+}
+
+fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// 以十六进制字符串序列化字节
+mod hex_bytes {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::utils;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&utils::bytes_to_space_hex(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        utils::space_hex_to_bytes(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        game::resources::Quest,
+        utils::{set_thread_memory_source, ByteSnapshot},
+    };
+
+    #[test]
+    fn test_struct_snapshot_diff() {
+        let mut memory = ByteSnapshot::zeroed(0x20000, 0x100);
+        memory.write_value(0x20010_usize, 100.0_f32);
+        memory.write_value(0x20038_usize, 1_i32);
+        let memory = Arc::new(memory);
+        let _guard = set_thread_memory_source(memory.clone());
+
+        let quest = Quest::from_instance(0x20000);
+        let before = StructSnapshot::of(&quest, 0x100).unwrap();
+        let write = |addr: usize, bytes: &[u8]| {
+            utils::with_memory_source(|source| source.write_bytes(addr, bytes)).unwrap()
+        };
+        write(0x20010, &80.0_f32.to_le_bytes());
+        write(0x20038, &2_i32.to_le_bytes());
+        let after = before.recapture().unwrap();
+
+        assert_eq!(
+            before.changed_ranges(&after).unwrap(),
+            [0x12..0x13, 0x38..0x39]
+        );
+        let fell = before
+            .compare(&after, FieldType::F32, Comparison::Decreased)
+            .unwrap();
+        assert_eq!(fell.len(), 1);
+        assert_eq!(fell[0].offset, 0x10);
+        assert_eq!(fell[0].new, FieldValue::Float(80.0));
+        let increased = before
+            .compare(
+                &after,
+                FieldType::I32,
+                Comparison::IncreasedBy(FieldValue::Int(1)),
+            )
+            .unwrap();
+        assert_eq!(
+            increased.iter().map(|c| c.offset).collect::<Vec<_>>(),
+            [0x38]
+        );
+
+        let mut scan = FieldScan::new(before.clone(), FieldType::I32);
+        scan.next(after.clone(), Comparison::Changed).unwrap();
+        write(0x20038, &3_i32.to_le_bytes());
+        assert_eq!(
+            scan.capture_next(Comparison::Equals(FieldValue::Int(3)))
+                .unwrap(),
+            [0x38]
+        );
+
+        // 保存到文件后离线分析
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let path = std::env::temp_dir().join(format!(
+            "mhw_toolkit_snapshot_test_{}_{}.json",
+            std::process::id(),
+            nanos
+        ));
+        after.clone().with_label("after").save(&path).unwrap();
+        let loaded = StructSnapshot::load(&path).unwrap();
+        let _ = fs::remove_file(&path);
+        assert_eq!(loaded.label(), "after");
+        assert_eq!(loaded.bytes(), after.bytes());
+        assert!(matches!(
+            StructSnapshot::capture(0x30000, 0x10),
+            Err(SnapshotError::Memory(_))
+        ));
+    }
+}
@@ -0,0 +1,260 @@
+use thiserror::Error;
+
+#[cfg(feature = "hooks")]
+use crate::game::hooks::HookError;
+use crate::utils::{Injection, Patch, PatchError};
+
+#[derive(Debug, Error)]
+pub enum ChangeError {
+    #[error(transparent)]
+    Patch(#[from] PatchError),
+    #[cfg(feature = "hooks")]
+    #[error(transparent)]
+    Hook(#[from] HookError),
+    #[error("{0}")]
+    Other(String),
+}
+
+#[derive(Debug, Error)]
+pub enum TransactionError {
+    #[error("transaction `{0}` is already applied")]
+    AlreadyApplied(String),
+    #[error("failed to apply `{change}`: {source}")]
+    Apply {
+        change: String,
+        source: ChangeError,
+        /// 回滚已应用的修改时发生的错误
+        rollback_errors: Vec<(String, ChangeError)>,
+    },
+    #[error("failed to revert {} change(s)", .0.len())]
+    Revert(Vec<(String, ChangeError)>),
+}
+
+/// 可应用与撤销的修改，例如字节补丁或函数钩子
+pub trait Change: Send {
+    fn name(&self) -> String;
+
+    fn apply(&mut self) -> Result<(), ChangeError>;
+
+    fn revert(&mut self) -> Result<(), ChangeError>;
+}
+
+impl Change for Patch {
+    fn name(&self) -> String {
+        Patch::name(self).to_string()
+    }
+
+    fn apply(&mut self) -> Result<(), ChangeError> {
+        Ok(self.enable()?)
+    }
+
+    fn revert(&mut self) -> Result<(), ChangeError> {
+        Ok(self.disable()?)
+    }
+}
+
+impl Change for Injection {
+    fn name(&self) -> String {
+        self.patch().name().to_string()
+    }
+
+    fn apply(&mut self) -> Result<(), ChangeError> {
+        Ok(self.patch_mut().enable()?)
+    }
+
+    fn revert(&mut self) -> Result<(), ChangeError> {
+        Ok(self.patch_mut().disable()?)
+    }
+}
+
+/// 一组需要同时生效的修改
+///
+/// `apply` 按添加顺序应用所有修改，任一修改失败时按相反顺序撤销已应用的修改，
+/// 保证要么全部生效，要么全部不生效。`rollback` 或释放时撤销整组修改。
+///
+/// ```ignore
+/// let mut transaction = Transaction::new("steamwork")
+///     .with(Patch::new("failure_jnz", jnz_addr, &[0x75], &[0xEB])?)
+///     .with(unsafe { Detour::new("change_fuel", target, hooked as _, addr_of_mut!(ORIGINAL)) });
+/// transaction.apply()?;
+/// // ...
+/// transaction.rollback()?;
+/// ```
+pub struct Transaction {
+    name: String,
+    changes: Vec<Box<dyn Change>>,
+    /// 已应用的修改数量，已应用的修改总是 `changes` 的前缀
+    applied: usize,
+}
+
+impl Transaction {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            changes: Vec::new(),
+            applied: 0,
+        }
+    }
+
+    /// 添加修改
+    pub fn with(mut self, change: impl Change + 'static) -> Self {
+        self.stage(change);
+        self
+    }
+
+    /// 添加修改，已应用的事务需要先回滚才能添加
+    pub fn stage(&mut self, change: impl Change + 'static) -> &mut Self {
+        self.changes.push(Box::new(change));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    pub fn is_applied(&self) -> bool {
+        self.applied != 0 && self.applied == self.changes.len()
+    }
+
+    /// 应用所有修改
+    pub fn apply(&mut self) -> Result<(), TransactionError> {
+        if self.applied != 0 {
+            return Err(TransactionError::AlreadyApplied(self.name.clone()));
+        }
+        for index in 0..self.changes.len() {
+            if let Err(source) = self.changes[index].apply() {
+                let change = self.changes[index].name();
+                let rollback_errors = self.revert_applied();
+                return Err(TransactionError::Apply {
+                    change,
+                    source,
+                    rollback_errors,
+                });
+            }
+            self.applied = index + 1;
+        }
+        Ok(())
+    }
+
+    /// 按相反顺序撤销所有已应用的修改
+    ///
+    /// 即使部分修改撤销失败，也会继续撤销其余修改，事务回到未应用状态。
+    pub fn rollback(&mut self) -> Result<(), TransactionError> {
+        let errors = self.revert_applied();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(TransactionError::Revert(errors))
+        }
+    }
+
+    fn revert_applied(&mut self) -> Vec<(String, ChangeError)> {
+        let mut errors = Vec::new();
+        while self.applied > 0 {
+            self.applied -= 1;
+            let change = &mut self.changes[self.applied];
+            if let Err(e) = change.revert() {
+                errors.push((change.name(), e));
+            }
+        }
+        errors
+    }
+}
+
+impl Drop for Transaction {
+    fn drop(&mut self) {
+        for (change, e) in self.revert_applied() {
+            log::error!(
+                "failed to revert `{}` of transaction `{}`: {}",
+                change,
+                self.name,
+                e
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    /// 记录操作顺序的修改
+    struct Recorder {
+        name: &'static str,
+        fail_apply: bool,
+        log: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl Change for Recorder {
+        fn name(&self) -> String {
+            self.name.to_string()
+        }
+
+        fn apply(&mut self) -> Result<(), ChangeError> {
+            if self.fail_apply {
+                return Err(ChangeError::Other("boom".to_string()));
+            }
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("apply {}", self.name));
+            Ok(())
+        }
+
+        fn revert(&mut self) -> Result<(), ChangeError> {
+            self.log
+                .lock()
+                .unwrap()
+                .push(format!("revert {}", self.name));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_transaction_rollback() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let recorder = |name, fail_apply| Recorder {
+            name,
+            fail_apply,
+            log: log.clone(),
+        };
+
+        let mut transaction = Transaction::new("failing")
+            .with(recorder("a", false))
+            .with(recorder("b", false))
+            .with(recorder("c", true));
+        let err = transaction.apply().unwrap_err();
+        assert!(matches!(err, TransactionError::Apply { ref change, .. } if change == "c"));
+        assert!(!transaction.is_applied());
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["apply a", "apply b", "revert b", "revert a"]
+        );
+
+        log.lock().unwrap().clear();
+        let mut transaction = Transaction::new("ok")
+            .with(recorder("a", false))
+            .with(recorder("b", false));
+        transaction.apply().unwrap();
+        assert!(transaction.is_applied());
+        assert!(matches!(
+            transaction.apply(),
+            Err(TransactionError::AlreadyApplied(_))
+        ));
+        drop(transaction);
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["apply a", "apply b", "revert b", "revert a"]
+        );
+    }
+}
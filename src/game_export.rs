@@ -1,27 +1,133 @@
-// updated to 15.23.00
-pub const XBOX_PAD_PTR: *const f32 = 0x1451C4558 as *const f32; // 0x1451C2318 15.22
+// 基址来自当前的地址表，见 [`crate::game::address_table`]
+// 大写的常量为 15.23.00 的固定地址，仅为兼容保留
+use crate::game::address_table::{static_address, StaticAddress};
+use crate::utils::{CeValueType, PointerDefinition, PointerPath};
 
-pub const PLAYER_BASE: *const usize = 0x1450139A0 as *const usize; // 0x145011760 15.22
+pub fn xbox_pad_ptr() -> *const f32 {
+    static_address(StaticAddress::XboxPad) as *const _
+}
+#[deprecated(note = "使用 `xbox_pad_ptr()`，地址随当前地址表变化")]
+pub const XBOX_PAD_PTR: *const f32 = 0x1451C4558 as *const f32;
+
+pub fn player_base() -> *const usize {
+    static_address(StaticAddress::PlayerBase) as *const _
+}
+#[deprecated(note = "使用 `player_base()`，地址随当前地址表变化")]
+pub const PLAYER_BASE: *const usize = 0x1450139A0 as *const usize;
 pub const PLAYER_OFFSET: &[isize] = &[0x50];
-pub const PLAYER_DATA_PTR: *const i32 = 0x1450139A0 as *const i32; // 0x145011760 15.22
+pub fn player_data_ptr() -> *const i32 {
+    static_address(StaticAddress::PlayerBase) as *const _
+}
+#[deprecated(note = "使用 `player_data_ptr()`，地址随当前地址表变化")]
+pub const PLAYER_DATA_PTR: *const i32 = 0x1450139A0 as *const i32;
 pub const PLAYER_DATA_OFFSET: &[isize] = &[0x50, 0xC0, 0x98, 0x18, 0x70, 0xC8, 0xD0, 0x5D0, 0x20];
 
-pub const QUEST_BASE: *const usize = 0x14500ED30 as *const usize; // 0x14500CAF0 15.22
+pub fn quest_base() -> *const usize {
+    static_address(StaticAddress::QuestBase) as *const _
+}
+#[deprecated(note = "使用 `quest_base()`，地址随当前地址表变化")]
+pub const QUEST_BASE: *const usize = 0x14500ED30 as *const usize;
 
+pub fn session_base() -> *const i32 {
+    static_address(StaticAddress::SessionBase) as *const _
+}
+#[deprecated(note = "使用 `session_base()`，地址随当前地址表变化")]
 pub const SESSION_BASE: *const i32 = 0x1451C46B8 as *const i32;
 pub const SESSION_PARTY_SIZE_OFFSETS: &[isize] = &[0x258, 0x10, 0x6574];
 
-pub const CHAT_MAIN_PTR: *const usize = 0x14500CE70 as *const usize; // 0x14500AC30 15.22
+pub fn chat_main_ptr() -> *const usize {
+    static_address(StaticAddress::ChatMain) as *const _
+}
+#[deprecated(note = "使用 `chat_main_ptr()`，地址随当前地址表变化")]
+pub const CHAT_MAIN_PTR: *const usize = 0x14500CE70 as *const usize;
 
-pub const PLAYER_SHORT_INFO_BASE: *const usize = 0x145013530 as *const _;
-pub const PLAYER_FRAME_SPEED_BASE: *const u32 = 0x1451238C8 as *const _;
+pub fn player_short_info_base() -> *const usize {
+    static_address(StaticAddress::PlayerShortInfoBase) as *const _
+}
+#[deprecated(note = "使用 `player_short_info_base()`，地址随当前地址表变化")]
+pub const PLAYER_SHORT_INFO_BASE: *const usize = 0x145013530 as *const usize;
+pub fn player_frame_speed_base() -> *const u32 {
+    static_address(StaticAddress::PlayerFrameSpeedBase) as *const _
+}
+#[deprecated(note = "使用 `player_frame_speed_base()`，地址随当前地址表变化")]
+pub const PLAYER_FRAME_SPEED_BASE: *const u32 = 0x1451238C8 as *const u32;
 
-pub const U_GUI_CHAT_BASE: *const usize = 0x1451C4640 as *const usize; // 0x1451C2400 15.22
+pub fn u_gui_chat_base() -> *const usize {
+    static_address(StaticAddress::UGuiChatBase) as *const _
+}
+#[deprecated(note = "使用 `u_gui_chat_base()`，地址随当前地址表变化")]
+pub const U_GUI_CHAT_BASE: *const usize = 0x1451C4640 as *const usize;
 pub const U_GUI_CHAT_STRUCT_OFFSETS: &[isize] = &[0x13FD0, 0x28F8];
 pub const U_GUI_CHAT_SEND_OFFSETS: &[isize] = &[0x13FD0, 0x325E];
 pub const U_GUI_CHAT_SEND_TARGET_OFFSETS: &[isize] = &[0x14748];
 pub const U_GUI_CHAT_SEND_TARGET_PLAYER_OFFSETS: &[isize] = &[0x14748 + 0x8];
 
-pub const MESSAGE_BASE: *const i32 = 0x144F87FF0 as *const i32; // 0x144F85DB0 15.22
+pub fn message_base() -> *const i32 {
+    static_address(StaticAddress::MessageBase) as *const _
+}
+#[deprecated(note = "使用 `message_base()`，地址随当前地址表变化")]
+pub const MESSAGE_BASE: *const i32 = 0x144F87FF0 as *const i32;
 pub const MESSAGE_LEN_OFFSETS: &[isize] = &[0xBC];
 pub const MESSAGE_BODY_OFFSETS: &[isize] = &[0xC0];
+
+/// 内置地址表中没有该地址，需要由JSON地址表提供，缺少时为空指针
+pub fn save_base() -> *const usize {
+    static_address(StaticAddress::SaveBase) as *const _
+}
+
+/// 已知的指针，可通过 [`CheatTable::from_definitions`](crate::utils::CheatTable::from_definitions) 导出到CE
+pub fn known_pointers() -> Vec<PointerDefinition> {
+    let pointer = |name, path| PointerDefinition::new(name, path, CeValueType::Bytes8);
+    vec![
+        pointer(
+            "Player",
+            PointerPath::absolute(player_base(), PLAYER_OFFSET),
+        ),
+        PointerDefinition::new(
+            "PlayerData",
+            PointerPath::absolute(player_data_ptr(), PLAYER_DATA_OFFSET),
+            CeValueType::Bytes4,
+        ),
+        pointer("Quest", PointerPath::absolute(quest_base(), &[])),
+        PointerDefinition::new(
+            "SessionPartySize",
+            PointerPath::absolute(session_base(), SESSION_PARTY_SIZE_OFFSETS),
+            CeValueType::Bytes4,
+        ),
+        pointer("ChatMain", PointerPath::absolute(chat_main_ptr(), &[])),
+        pointer(
+            "PlayerShortInfo",
+            PointerPath::absolute(player_short_info_base(), &[]),
+        ),
+        PointerDefinition::new(
+            "PlayerFrameSpeed",
+            PointerPath::absolute(player_frame_speed_base(), &[]),
+            CeValueType::Bytes4,
+        ),
+        PointerDefinition::new(
+            "XboxPad",
+            PointerPath::absolute(xbox_pad_ptr(), &[]),
+            CeValueType::Float,
+        ),
+        PointerDefinition::new(
+            "UGuiChatSend",
+            PointerPath::absolute(u_gui_chat_base(), U_GUI_CHAT_SEND_OFFSETS),
+            CeValueType::Byte,
+        ),
+        PointerDefinition::new(
+            "UGuiChatSendTarget",
+            PointerPath::absolute(u_gui_chat_base(), U_GUI_CHAT_SEND_TARGET_OFFSETS),
+            CeValueType::Bytes4,
+        )
+        .signed(true),
+        pointer(
+            "UGuiChatSendTargetPlayer",
+            PointerPath::absolute(u_gui_chat_base(), U_GUI_CHAT_SEND_TARGET_PLAYER_OFFSETS),
+        ),
+        PointerDefinition::new(
+            "MessageLen",
+            PointerPath::absolute(message_base(), MESSAGE_LEN_OFFSETS),
+            CeValueType::Bytes4,
+        ),
+    ]
+}
@@ -84,7 +84,7 @@ struct UGUIChat {
 pub fn show_game_message(message: &str) {
     // 为了防止panic，通过检查玩家基址是否为空判断是否进入游戏场景
     // 可能存在不稳定性，待测试
-    if utils::get_ptr_with_offset(game_export::PLAYER_BASE, game_export::PLAYER_OFFSET)
+    if utils::get_ptr_with_offset(game_export::player_base(), game_export::PLAYER_OFFSET)
         .map_or(true, |ptr| ptr.is_null())
     {
         return;
@@ -94,7 +94,7 @@ pub fn show_game_message(message: &str) {
         unsafe { std::mem::transmute(0x141A53400_i64) };
     let message_cstring = CString::new(message).unwrap();
     show_message(
-        unsafe { *game_export::CHAT_MAIN_PTR as *const usize },
+        unsafe { *game_export::chat_main_ptr() as *const usize },
         message_cstring.as_ptr(),
         message.len() as i32,
         -1,
@@ -114,7 +114,7 @@ pub enum SystemMessageColor {
 pub fn show_system_message(message: &str, color: SystemMessageColor) {
     // 为了防止panic，通过检查玩家基址是否为空判断是否进入游戏场景
     // 可能存在不稳定性，待测试
-    if utils::get_ptr_with_offset(game_export::PLAYER_BASE, game_export::PLAYER_OFFSET)
+    if utils::get_ptr_with_offset(game_export::player_base(), game_export::PLAYER_OFFSET)
         .map_or(true, |ptr| ptr.is_null())
     {
         return;
@@ -123,7 +123,7 @@ pub fn show_system_message(message: &str, color: SystemMessageColor) {
     let func_addr = match AddressRepository::get_instance()
         .lock()
         .unwrap()
-        .get_address(address::chat::SystemMessage)
+        .get_record_address(address::chat::SystemMessage)
     {
         Ok(addr) => addr,
         Err(_) => {
@@ -135,7 +135,7 @@ pub fn show_system_message(message: &str, color: SystemMessageColor) {
         unsafe { std::mem::transmute(func_addr) };
     let message_cstring = CString::new(message).unwrap();
     show_message(
-        unsafe { *game_export::CHAT_MAIN_PTR as *const c_void },
+        unsafe { *game_export::chat_main_ptr() as *const c_void },
         message_cstring.as_ptr(),
         0.0,
         -1,
@@ -151,7 +151,7 @@ pub fn send_chat_message(message: &str) {
     let message_cstring = CString::new(message).unwrap();
     // 获取 UGUIChat 结构
     let chat = match utils::get_ptr_with_offset(
-        game_export::U_GUI_CHAT_BASE as *const UGUIChat,
+        game_export::u_gui_chat_base() as *const UGUIChat,
         game_export::U_GUI_CHAT_STRUCT_OFFSETS,
     ) {
         Some(chat) => chat as *mut UGUIChat,
@@ -173,7 +173,7 @@ pub fn send_chat_message(message: &str) {
     // 发送
     unsafe {
         if let Some(send_flag) = utils::get_ptr_with_offset(
-            game_export::U_GUI_CHAT_BASE as *const bool,
+            game_export::u_gui_chat_base() as *const bool,
             game_export::U_GUI_CHAT_SEND_OFFSETS,
         ) {
             *(send_flag.cast_mut()) = true;
@@ -314,7 +314,7 @@ pub mod chat {
         ///  获取当前设置的发送目标
         pub fn get_current_send_target() -> SendTarget {
             let send_target_i32 = utils::get_value_with_offset(
-                game_export::U_GUI_CHAT_BASE as *const i32,
+                game_export::u_gui_chat_base() as *const i32,
                 game_export::U_GUI_CHAT_SEND_TARGET_OFFSETS,
             )
             .unwrap_or(SendTarget::All.as_i32());
@@ -329,7 +329,7 @@ pub mod chat {
         /// 仅在SendTarget::Specified下有效
         fn get_current_send_player_target() -> Option<u64> {
             utils::get_value_with_offset(
-                game_export::U_GUI_CHAT_BASE as *const u64,
+                game_export::u_gui_chat_base() as *const u64,
                 game_export::U_GUI_CHAT_SEND_TARGET_PLAYER_OFFSETS,
             )
         }
@@ -337,7 +337,7 @@ pub mod chat {
         fn can_send() -> bool {
             // 如果是false则可以发送
             utils::get_value_with_offset(
-                game_export::U_GUI_CHAT_BASE as *const bool,
+                game_export::u_gui_chat_base() as *const bool,
                 game_export::U_GUI_CHAT_SEND_OFFSETS,
             )
             .map(|res| !res)
@@ -346,7 +346,7 @@ pub mod chat {
 
         fn set_send_target(target: SendTarget) {
             if let Some(send_target_i32) = utils::get_ptr_with_offset(
-                game_export::U_GUI_CHAT_BASE as *const i32,
+                game_export::u_gui_chat_base() as *const i32,
                 game_export::U_GUI_CHAT_SEND_TARGET_OFFSETS,
             ) {
                 unsafe {
@@ -359,7 +359,7 @@ pub mod chat {
 
         fn set_send_target_player(player_id: u64) {
             if let Some(player_u64) = utils::get_ptr_with_offset(
-                game_export::U_GUI_CHAT_BASE as *const u64,
+                game_export::u_gui_chat_base() as *const u64,
                 game_export::U_GUI_CHAT_SEND_TARGET_PLAYER_OFFSETS,
             ) {
                 unsafe {
@@ -400,12 +400,12 @@ pub mod chat {
 
         pub fn try_recv(&self) -> Option<String> {
             let msg_ptr = utils::get_ptr_with_offset(
-                game_export::MESSAGE_BASE,
+                game_export::message_base(),
                 game_export::MESSAGE_BODY_OFFSETS,
             )?;
             let msg_ptr = msg_ptr as *mut u8;
             let msg_len_ptr = utils::get_ptr_with_offset(
-                game_export::MESSAGE_BASE,
+                game_export::message_base(),
                 game_export::MESSAGE_LEN_OFFSETS,
             )?;
             let msg_len = unsafe { *msg_len_ptr };
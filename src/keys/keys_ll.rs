@@ -3,7 +3,7 @@ use std::collections::{HashMap, HashSet};
 use windows::Win32::UI::Input::KeyboardAndMouse::GetKeyState;
 
 use super::keycode::*;
-use crate::game_export::XBOX_PAD_PTR;
+use crate::game_export;
 use crate::utils;
 
 type KeyEventCallback = Box<dyn Fn(&KeyEvent) + 'static + Send + Sync>;
@@ -263,7 +263,7 @@ impl KeyBindEngine {
 
     #[inline]
     fn get_xbox_state(offset: isize) -> f32 {
-        utils::get_value_with_offset(XBOX_PAD_PTR, &[offset]).unwrap_or(-1.0)
+        utils::get_value_with_offset(game_export::xbox_pad_ptr(), &[offset]).unwrap_or(-1.0)
     }
 }
 
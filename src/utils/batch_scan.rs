@@ -0,0 +1,258 @@
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{Pattern, PatternHit, PatternMatch, PeImage, SectionFilter};
+
+/// 每个线程至少处理的字节数，数据过小时不值得开线程
+const MIN_BYTES_PER_THREAD: usize = 0x100000;
+
+/// x86-64代码中出现频率较高的字节，选择锚点时尽量避开
+const COMMON_CODE_BYTES: &[u8] = &[
+    0x00, 0xCC, 0x48, 0x8B, 0x89, 0x0F, 0x24, 0x44, 0x4C, 0x8D, 0x83, 0xC0, 0x85, 0x01, 0x20, 0x10,
+    0x08, 0x45, 0x41, 0x49, 0xE8, 0xC3, 0x74, 0x33, 0xC7, 0x4D, 0x40, 0x90,
+];
+
+/// 批量特征码扫描器
+///
+/// 对所有特征码只遍历一次数据：每个特征码在第一段中选取一个尽量少见的非通配字节作为锚点，
+/// 遍历时按字节查表得到候选特征码，再校验完整特征码。
+/// 数据较大时按块分配给多个线程并行扫描。
+pub struct BatchScanner {
+    patterns: Vec<Pattern>,
+    /// 按锚点字节索引：(特征码序号, 锚点在特征码中的位置)
+    anchors: Vec<Vec<(usize, usize)>>,
+    /// 没有可用锚点的特征码，单独扫描
+    unanchored: Vec<usize>,
+    max_anchor_pos: usize,
+    threads: usize,
+}
+
+/// 批量扫描结果
+#[derive(Debug, Clone)]
+pub struct BatchScanReport {
+    /// 与添加顺序对应的每个特征码的所有匹配
+    pub matches: Vec<Vec<PatternMatch>>,
+    pub bytes_scanned: usize,
+    /// 实际使用的线程数，节较小时少于配置的线程数
+    pub threads: usize,
+    pub elapsed: Duration,
+}
+
+impl Default for BatchScanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BatchScanner {
+    pub fn new() -> Self {
+        Self {
+            patterns: Vec::new(),
+            anchors: vec![Vec::new(); 256],
+            unanchored: Vec::new(),
+            max_anchor_pos: 0,
+            threads: thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1),
+        }
+    }
+
+    /// 设置最大线程数，至少为1
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// 添加特征码，返回其序号
+    pub fn add(&mut self, pattern: Pattern) -> usize {
+        let index = self.patterns.len();
+        let (bytes, mask) = pattern.prefix();
+        match select_anchor(bytes, mask) {
+            Some(anchor) => {
+                self.anchors[bytes[anchor] as usize].push((index, anchor));
+                self.max_anchor_pos = self.max_anchor_pos.max(anchor);
+            }
+            None if !pattern.is_empty() => self.unanchored.push(index),
+            None => {}
+        }
+        self.patterns.push(pattern);
+        index
+    }
+
+    pub fn len(&self) -> usize {
+        self.patterns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    /// 单线程扫描，返回每个特征码的匹配位置
+    pub fn search(&self, text: &[u8]) -> Vec<Vec<PatternHit>> {
+        let mut result = vec![Vec::new(); self.patterns.len()];
+        self.search_range(text, 0, text.len(), &mut result);
+        result
+    }
+
+    /// 多线程扫描，返回每个特征码的匹配位置
+    pub fn search_parallel(&self, text: &[u8]) -> Vec<Vec<PatternHit>> {
+        let threads = self.effective_threads(text.len());
+        if threads == 1 {
+            return self.search(text);
+        }
+
+        let chunk_size = text.len().div_ceil(threads);
+        let partials: Vec<Vec<Vec<PatternHit>>> = thread::scope(|scope| {
+            let handles: Vec<_> = (0..text.len())
+                .step_by(chunk_size)
+                .map(|start| {
+                    let end = (start + chunk_size).min(text.len());
+                    scope.spawn(move || {
+                        let mut result = vec![Vec::new(); self.patterns.len()];
+                        self.search_range(text, start, end, &mut result);
+                        result
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        let mut result = vec![Vec::new(); self.patterns.len()];
+        for partial in partials {
+            for (all, part) in result.iter_mut().zip(partial) {
+                all.extend(part);
+            }
+        }
+        result
+    }
+
+    /// 扫描模块中满足条件的节
+    pub fn scan_module(&self, image: &PeImage, filter: SectionFilter) -> BatchScanReport {
+        let start_time = Instant::now();
+        let mut matches = vec![Vec::new(); self.patterns.len()];
+        let mut bytes_scanned = 0;
+        let mut threads = 1;
+        for section in image.sections().iter().filter(|s| filter.matches(s)) {
+            let bytes = image.section_bytes(section);
+            bytes_scanned += bytes.len();
+            threads = threads.max(self.effective_threads(bytes.len()));
+            for (index, hits) in self.search_parallel(bytes).into_iter().enumerate() {
+                matches[index].extend(hits.into_iter().map(|hit| {
+                    PatternMatch::from_hit(image, section.virtual_address as usize, hit)
+                }));
+            }
+        }
+
+        BatchScanReport {
+            matches,
+            bytes_scanned,
+            threads,
+            elapsed: start_time.elapsed(),
+        }
+    }
+
+    /// 扫描 `len` 字节时实际使用的线程数
+    fn effective_threads(&self, len: usize) -> usize {
+        self.threads.min(len / MIN_BYTES_PER_THREAD).max(1)
+    }
+
+    /// 查找起点位于 `[start, end)` 的所有匹配
+    ///
+    /// 特征码可以越过 `end` 延伸到后续数据中，因此各线程的区间无需重叠。
+    fn search_range(&self, text: &[u8], start: usize, end: usize, result: &mut [Vec<PatternHit>]) {
+        for &index in self.unanchored.iter() {
+            result[index] = self.patterns[index].find_in_range(text, start, end);
+        }
+
+        // 锚点可能位于特征码中间，遍历范围需向后延伸
+        let scan_end = (end + self.max_anchor_pos).min(text.len());
+        for (i, &byte) in text.iter().enumerate().take(scan_end).skip(start) {
+            for &(index, anchor) in self.anchors[byte as usize].iter() {
+                let Some(pos) = i.checked_sub(anchor) else {
+                    continue;
+                };
+                if pos < start || pos >= end {
+                    continue;
+                }
+                if let Some(hit) = self.patterns[index].match_at(text, pos) {
+                    result[index].push(hit);
+                }
+            }
+        }
+    }
+}
+
+/// 选择特征码的锚点位置：只考虑完整匹配的字节，优先选择不常见的字节
+fn select_anchor(bytes: &[u8], mask: &[u8]) -> Option<usize> {
+    let mut candidates = bytes
+        .iter()
+        .zip(mask.iter())
+        .enumerate()
+        .filter(|(_, (_, &m))| m == 0xFF)
+        .map(|(i, (&b, _))| (i, b));
+    let first = candidates.next()?;
+    std::iter::once(first)
+        .chain(candidates)
+        .find(|(_, b)| !COMMON_CODE_BYTES.contains(b))
+        .or(Some(first))
+        .map(|(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use address_scanner::hex_str_to_bytes;
+
+    use super::*;
+    use crate::utils::boyer_moore_search_all;
+
+    #[test]
+    fn test_batch_search() {
+        let text: Vec<u8> = (0..0x400000_u32)
+            .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+            .collect();
+        let patterns = [
+            text[0x1234..0x1240].to_vec(),
+            hex_str_to_bytes!("48 8B ** ** 00").to_vec(),
+            text[0x3FFFF8..0x400000].to_vec(),
+        ];
+
+        let mut scanner = BatchScanner::new().with_threads(4);
+        for pattern in patterns.iter() {
+            scanner.add(Pattern::from_wildcard_bytes(pattern, 0xFF));
+        }
+        scanner.add(Pattern::parse("?? ?? 5?").unwrap());
+        scanner.add(Pattern::parse("").unwrap());
+        let single = scanner.search(&text);
+        let parallel = scanner.search_parallel(&text);
+        assert_eq!(single, parallel);
+
+        let starts = |hits: &[PatternHit]| hits.iter().map(|h| h.start).collect::<Vec<_>>();
+        for (index, pattern) in patterns.iter().enumerate() {
+            assert_eq!(
+                starts(&single[index]),
+                boyer_moore_search_all(&text, pattern, 0xFF)
+            );
+        }
+        assert!(starts(&single[0]).contains(&0x1234));
+        assert!(starts(&single[2]).contains(&0x3FFFF8));
+        // 没有锚点的特征码
+        let expected: Vec<_> = (0..text.len() - 2)
+            .filter(|&i| text[i + 2] >> 4 == 5)
+            .collect();
+        assert_eq!(starts(&single[3]), expected);
+        assert!(single[4].is_empty());
+    }
+
+    #[test]
+    fn test_report_threads() {
+        let image = crate::game::address::tests::build_image_with(&[(0x10, &[0x48, 0x8B, 0xC4])]);
+        let mut scanner = BatchScanner::new().with_threads(4);
+        scanner.add(Pattern::parse("48 8B C4").unwrap());
+        let report = scanner.scan_module(&image, SectionFilter::Executable);
+        assert_eq!(report.matches[0].len(), 1);
+        // 节小于单线程的最小扫描量，只使用一个线程
+        assert_eq!(report.threads, 1);
+    }
+}
@@ -0,0 +1,833 @@
+use std::{fmt::Write as _, fs, path::Path};
+
+use thiserror::Error;
+
+use super::{with_memory_source, MemoryError, PathBase, PointerPath, PointerPathError};
+
+#[derive(Debug, Error)]
+pub enum CheatTableError {
+    #[error("invalid XML at byte {position}: {reason}")]
+    Xml { position: usize, reason: String },
+    #[error("invalid cheat entry `{entry}`: {reason}")]
+    Entry { entry: String, reason: String },
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// CE中的数值类型（`VariableType`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CeValueType {
+    Byte,
+    /// `2 Bytes`
+    Bytes2,
+    /// `4 Bytes`
+    Bytes4,
+    /// `8 Bytes`
+    Bytes8,
+    Float,
+    Double,
+    /// 字符串，`len` 为最大长度（字节数或字符数）
+    String {
+        len: usize,
+        unicode: bool,
+    },
+    /// `Array of byte`
+    ByteArray {
+        len: usize,
+    },
+}
+
+impl CeValueType {
+    /// 值的字节数
+    pub fn size(&self) -> usize {
+        match self {
+            CeValueType::Byte => 1,
+            CeValueType::Bytes2 => 2,
+            CeValueType::Bytes4 | CeValueType::Float => 4,
+            CeValueType::Bytes8 | CeValueType::Double => 8,
+            CeValueType::String { len, unicode } => len * if *unicode { 2 } else { 1 },
+            CeValueType::ByteArray { len } => *len,
+        }
+    }
+
+    /// CE表格中 `VariableType` 的名称
+    pub fn ce_name(&self) -> &'static str {
+        match self {
+            CeValueType::Byte => "Byte",
+            CeValueType::Bytes2 => "2 Bytes",
+            CeValueType::Bytes4 => "4 Bytes",
+            CeValueType::Bytes8 => "8 Bytes",
+            CeValueType::Float => "Float",
+            CeValueType::Double => "Double",
+            CeValueType::String { .. } => "String",
+            CeValueType::ByteArray { .. } => "Array of byte",
+        }
+    }
+}
+
+/// 按 [`CeValueType`] 读取到的值
+#[derive(Debug, Clone, PartialEq)]
+pub enum CeValue {
+    Integer(i64),
+    Unsigned(u64),
+    Float(f32),
+    Double(f64),
+    String(String),
+    Bytes(Vec<u8>),
+}
+
+/// 带类型的指针定义
+#[derive(Debug, Clone, PartialEq)]
+pub struct PointerDefinition {
+    /// 名称，嵌套条目以 `/` 连接父条目的名称
+    pub name: String,
+    pub path: PointerPath,
+    pub value_type: CeValueType,
+    /// 整数是否按有符号数显示与读取
+    pub signed: bool,
+}
+
+impl PointerDefinition {
+    pub fn new(name: &str, path: PointerPath, value_type: CeValueType) -> Self {
+        Self {
+            name: name.to_string(),
+            path,
+            value_type,
+            signed: false,
+        }
+    }
+
+    pub fn signed(mut self, signed: bool) -> Self {
+        self.signed = signed;
+        self
+    }
+
+    /// 按定义的类型读取值，每一级取值前确认地址可读
+    pub fn read(&self) -> Result<CeValue, PointerPathError> {
+        let addr = self.path.try_resolve()?;
+        let mut buf = vec![0_u8; self.value_type.size()];
+        with_memory_source(|source| {
+            if source.is_readable(addr, buf.len()) && source.read_bytes(addr, &mut buf) {
+                Ok(())
+            } else {
+                Err(MemoryError::Unreadable {
+                    address: addr,
+                    len: buf.len(),
+                })
+            }
+        })?;
+        Ok(self.decode(&buf))
+    }
+
+    fn decode(&self, buf: &[u8]) -> CeValue {
+        let int = |bytes: &[u8]| {
+            let mut raw = [0_u8; 8];
+            raw[..bytes.len()].copy_from_slice(bytes);
+            let value = u64::from_le_bytes(raw);
+            if self.signed {
+                // 符号扩展
+                let shift = 64 - bytes.len() * 8;
+                CeValue::Integer(((value << shift) as i64) >> shift)
+            } else {
+                CeValue::Unsigned(value)
+            }
+        };
+        match self.value_type {
+            CeValueType::Byte | CeValueType::Bytes2 | CeValueType::Bytes4 | CeValueType::Bytes8 => {
+                int(buf)
+            }
+            CeValueType::Float => CeValue::Float(f32::from_le_bytes(buf.try_into().unwrap())),
+            CeValueType::Double => CeValue::Double(f64::from_le_bytes(buf.try_into().unwrap())),
+            CeValueType::String { unicode: false, .. } => {
+                let end = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+                CeValue::String(String::from_utf8_lossy(&buf[..end]).to_string())
+            }
+            CeValueType::String { unicode: true, .. } => {
+                let wide: Vec<u16> = buf
+                    .chunks_exact(2)
+                    .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                    .take_while(|&c| c != 0)
+                    .collect();
+                CeValue::String(String::from_utf16_lossy(&wide))
+            }
+            CeValueType::ByteArray { .. } => CeValue::Bytes(buf.to_vec()),
+        }
+    }
+}
+
+/// CE表格中的一个条目
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheatEntry {
+    pub id: Option<u32>,
+    pub description: String,
+    /// 分组条目、脚本等没有类型
+    pub value_type: Option<CeValueType>,
+    pub signed: bool,
+    /// 条目的指针路径，偏移按取值顺序排列（与CE表格中的顺序相反）
+    ///
+    /// 相对父条目的地址（如 `+10`）已展开为完整路径
+    pub path: Option<PointerPath>,
+    pub children: Vec<CheatEntry>,
+}
+
+impl CheatEntry {
+    /// 转换为指针定义，没有地址或类型时返回None
+    pub fn definition(&self) -> Option<PointerDefinition> {
+        Some(
+            PointerDefinition::new(&self.description, self.path.clone()?, self.value_type?)
+                .signed(self.signed),
+        )
+    }
+}
+
+/// CE表格（`.CT` 文件）
+///
+/// 仅处理 `CheatEntries`，忽略脚本、结构体定义等其他内容。
+///
+/// ```ignore
+/// let table = CheatTable::from_file("MonsterHunterWorld.CT")?;
+/// for definition in table.definitions() {
+///     println!("{} = {:?}", definition.name, definition.read());
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CheatTable {
+    pub entries: Vec<CheatEntry>,
+}
+
+impl CheatTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 由指针定义创建表格，用于导出到CE
+    pub fn from_definitions(definitions: &[PointerDefinition]) -> Self {
+        let entries = definitions
+            .iter()
+            .map(|definition| CheatEntry {
+                id: None,
+                description: definition.name.clone(),
+                value_type: Some(definition.value_type),
+                signed: definition.signed,
+                path: Some(definition.path.clone()),
+                children: Vec::new(),
+            })
+            .collect();
+        Self { entries }
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, CheatTableError> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(xml: &str) -> Result<Self, CheatTableError> {
+        let root = XmlParser::new(xml).parse_document()?;
+        if root.name != "CheatTable" {
+            return Err(CheatTableError::Xml {
+                position: 0,
+                reason: format!("expected <CheatTable>, found <{}>", root.name),
+            });
+        }
+        let entries = match root.child("CheatEntries") {
+            Some(entries) => parse_entries(entries, None)?,
+            None => Vec::new(),
+        };
+        Ok(Self { entries })
+    }
+
+    /// 所有带地址与类型的条目（包括嵌套条目）的指针定义
+    pub fn definitions(&self) -> Vec<PointerDefinition> {
+        fn collect(entries: &[CheatEntry], prefix: &str, output: &mut Vec<PointerDefinition>) {
+            for entry in entries {
+                let name = if prefix.is_empty() {
+                    entry.description.clone()
+                } else {
+                    format!("{}/{}", prefix, entry.description)
+                };
+                if let Some(mut definition) = entry.definition() {
+                    definition.name = name.clone();
+                    output.push(definition);
+                }
+                collect(&entry.children, &name, output);
+            }
+        }
+        let mut output = Vec::new();
+        collect(&self.entries, "", &mut output);
+        output
+    }
+
+    /// 按名称查找指针定义，嵌套条目的名称以 `/` 连接
+    pub fn definition(&self, name: &str) -> Option<PointerDefinition> {
+        self.definitions().into_iter().find(|d| d.name == name)
+    }
+
+    /// 输出为CE表格
+    pub fn to_xml(&self) -> String {
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<CheatTable>\n");
+        let mut next_id = self.max_id().map_or(0, |id| id + 1);
+        write_entries(&mut xml, &self.entries, 1, &mut next_id);
+        xml.push_str("</CheatTable>\n");
+        xml
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), CheatTableError> {
+        Ok(fs::write(path, self.to_xml())?)
+    }
+
+    fn max_id(&self) -> Option<u32> {
+        fn max_id(entries: &[CheatEntry]) -> Option<u32> {
+            entries
+                .iter()
+                .flat_map(|e| e.id.into_iter().chain(max_id(&e.children)))
+                .max()
+        }
+        max_id(&self.entries)
+    }
+}
+
+fn parse_entries(
+    entries: &XmlElement,
+    parent: Option<&PointerPath>,
+) -> Result<Vec<CheatEntry>, CheatTableError> {
+    entries
+        .children
+        .iter()
+        .filter(|e| e.name == "CheatEntry")
+        .map(|e| parse_entry(e, parent))
+        .collect()
+}
+
+fn parse_entry(
+    element: &XmlElement,
+    parent: Option<&PointerPath>,
+) -> Result<CheatEntry, CheatTableError> {
+    let text = |name: &str| element.child(name).map(|e| e.text.trim());
+    let description = text("Description")
+        .unwrap_or_default()
+        .trim_matches('"')
+        .to_string();
+    let error = |reason: String| CheatTableError::Entry {
+        entry: description.clone(),
+        reason,
+    };
+    let number = |name: &str| -> Result<Option<usize>, CheatTableError> {
+        text(name)
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| error(format!("invalid {} `{}`", name, value)))
+            })
+            .transpose()
+    };
+
+    let value_type = match text("VariableType") {
+        Some("Byte") => Some(CeValueType::Byte),
+        Some("2 Bytes") => Some(CeValueType::Bytes2),
+        Some("4 Bytes") => Some(CeValueType::Bytes4),
+        Some("8 Bytes") => Some(CeValueType::Bytes8),
+        Some("Float") => Some(CeValueType::Float),
+        Some("Double") => Some(CeValueType::Double),
+        Some("String") => Some(CeValueType::String {
+            len: number("Length")?.unwrap_or(0),
+            unicode: number("Unicode")?.unwrap_or(0) != 0,
+        }),
+        Some("Array of byte") => Some(CeValueType::ByteArray {
+            len: number("ByteLength")?.unwrap_or(0),
+        }),
+        // 脚本、二进制等暂不支持的类型
+        _ => None,
+    };
+
+    // 符号、`[ptr]+10` 等暂不支持的地址不影响其他条目，该条目没有地址
+    let address = text("Address").filter(|address| !address.is_empty());
+    let path = address.and_then(|address| match parse_path(element, address, parent) {
+        Ok(path) => Some(path),
+        Err(reason) => {
+            log::warn!(
+                "ignored address `{}` of cheat entry `{}`: {}",
+                address,
+                description,
+                reason
+            );
+            None
+        }
+    });
+
+    // 地址不支持时，子条目的相对地址同样无法解析
+    let children_parent = match address {
+        Some(_) => path.as_ref(),
+        None => parent,
+    };
+    let children = match element.child("CheatEntries") {
+        Some(children) => parse_entries(children, children_parent)?,
+        None => Vec::new(),
+    };
+
+    Ok(CheatEntry {
+        id: text("ID").and_then(|id| id.parse().ok()),
+        description,
+        value_type,
+        signed: text("ShowAsSigned") == Some("1"),
+        path,
+        children,
+    })
+}
+
+/// 解析条目地址与偏移
+fn parse_path(
+    element: &XmlElement,
+    address: &str,
+    parent: Option<&PointerPath>,
+) -> Result<PointerPath, String> {
+    let mut path = parse_address(address, parent)?;
+    // CE按从后往前的顺序保存偏移
+    if let Some(offsets) = element.child("Offsets") {
+        let mut offsets = offsets
+            .children
+            .iter()
+            .filter(|e| e.name == "Offset")
+            .map(|e| eval_offset(e.text.trim()))
+            .collect::<Result<Vec<_>, _>>()?;
+        offsets.reverse();
+        for offset in offsets {
+            path = path.join(offset);
+        }
+    }
+    Ok(path)
+}
+
+/// 解析条目地址，支持绝对地址、`模块名+偏移` 与相对父条目的 `+偏移`
+///
+/// CE中模块名可以带引号，例如 `"MonsterHunterWorld.exe"+050139A0`
+fn parse_address(address: &str, parent: Option<&PointerPath>) -> Result<PointerPath, String> {
+    if let Some(relative) = address.strip_prefix(['+', '-']) {
+        let parent =
+            parent.ok_or_else(|| format!("relative address `{}` has no parent", address))?;
+        let mut offset = eval_offset(relative)?;
+        if address.starts_with('-') {
+            offset = -offset;
+        }
+        // 父条目的最终地址加偏移：偏移加到最后一级
+        let mut offsets = parent.offsets().to_vec();
+        let base = match offsets.last_mut() {
+            Some(last) => {
+                *last += offset;
+                parent.base().clone()
+            }
+            None => match parent.base() {
+                PathBase::Absolute(addr) => PathBase::Absolute(addr.wrapping_add_signed(offset)),
+                PathBase::Module { name, offset: base } => PathBase::Module {
+                    name: name.clone(),
+                    offset: base.wrapping_add_signed(offset),
+                },
+            },
+        };
+        return Ok(PointerPath::new(base, &offsets));
+    }
+    let address = match address.rsplit_once('+') {
+        Some((module, offset)) => {
+            let module = module.trim().trim_matches('"');
+            // 模块名为文件名，其他名称为CE中注册的符号或表达式
+            if !module.contains('.') || module.contains(['[', ']', '+']) {
+                return Err(format!("unsupported address `{}`", address));
+            }
+            format!("{}+{}", module, offset)
+        }
+        None => address.to_string(),
+    };
+    address.parse().map_err(|e: PointerPathError| e.to_string())
+}
+
+/// 计算CE的偏移表达式，数值按十六进制解析，支持 `+`、`-`、`*`
+///
+/// 例如 `0x1AB0 + 0x58 * 3`
+fn eval_offset(expr: &str) -> Result<isize, String> {
+    let invalid = || format!("invalid offset `{}`", expr);
+    let mut sum = 0_isize;
+    for (sign, term) in split_terms(expr) {
+        let mut product = 1_isize;
+        for factor in term.split('*') {
+            let factor = factor.trim();
+            let digits = factor
+                .strip_prefix("0x")
+                .or_else(|| factor.strip_prefix("0X"))
+                .unwrap_or(factor);
+            let value = isize::from_str_radix(digits, 16).map_err(|_| invalid())?;
+            product = product.checked_mul(value).ok_or_else(invalid)?;
+        }
+        sum = sum.checked_add(sign * product).ok_or_else(invalid)?;
+    }
+    Ok(sum)
+}
+
+/// 按 `+`、`-` 拆分表达式，返回 (符号, 项)
+fn split_terms(expr: &str) -> Vec<(isize, &str)> {
+    let mut terms = Vec::new();
+    let mut sign = 1;
+    let mut start = 0;
+    for (i, c) in expr.char_indices() {
+        if c == '+' || c == '-' {
+            if !expr[start..i].trim().is_empty() {
+                terms.push((sign, &expr[start..i]));
+            }
+            sign = if c == '-' { -1 } else { 1 };
+            start = i + 1;
+        }
+    }
+    terms.push((sign, &expr[start..]));
+    terms
+}
+
+fn write_entries(xml: &mut String, entries: &[CheatEntry], depth: usize, next_id: &mut u32) {
+    let indent = "  ".repeat(depth);
+    let _ = writeln!(xml, "{}<CheatEntries>", indent);
+    for entry in entries {
+        let id = entry.id.unwrap_or_else(|| {
+            *next_id += 1;
+            *next_id - 1
+        });
+        let inner = "  ".repeat(depth + 2);
+        let _ = writeln!(xml, "{}  <CheatEntry>", indent);
+        let _ = writeln!(xml, "{}<ID>{}</ID>", inner, id);
+        let _ = writeln!(
+            xml,
+            "{}<Description>\"{}\"</Description>",
+            inner,
+            escape_xml(&entry.description)
+        );
+        if let Some(value_type) = entry.value_type {
+            let _ = writeln!(
+                xml,
+                "{}<ShowAsSigned>{}</ShowAsSigned>",
+                inner, entry.signed as u8
+            );
+            let _ = writeln!(
+                xml,
+                "{}<VariableType>{}</VariableType>",
+                inner,
+                value_type.ce_name()
+            );
+            match value_type {
+                CeValueType::String { len, unicode } => {
+                    let _ = writeln!(xml, "{}<Length>{}</Length>", inner, len);
+                    let _ = writeln!(xml, "{}<Unicode>{}</Unicode>", inner, unicode as u8);
+                    let _ = writeln!(xml, "{}<CodePage>0</CodePage>", inner);
+                    let _ = writeln!(xml, "{}<ZeroTerminate>1</ZeroTerminate>", inner);
+                }
+                CeValueType::ByteArray { len } => {
+                    let _ = writeln!(xml, "{}<ByteLength>{}</ByteLength>", inner, len);
+                }
+                _ => {}
+            }
+        }
+        if let Some(path) = &entry.path {
+            let address = match path.base() {
+                PathBase::Absolute(addr) => format!("0x{:X}", addr),
+                PathBase::Module { name, offset } => format!("{}+{:X}", name, offset),
+            };
+            let _ = writeln!(xml, "{}<Address>{}</Address>", inner, escape_xml(&address));
+            if !path.offsets().is_empty() {
+                let _ = writeln!(xml, "{}<Offsets>", inner);
+                for &offset in path.offsets().iter().rev() {
+                    let offset = if offset < 0 {
+                        format!("-{:X}", offset.unsigned_abs())
+                    } else {
+                        format!("{:X}", offset)
+                    };
+                    let _ = writeln!(xml, "{}  <Offset>{}</Offset>", inner, offset);
+                }
+                let _ = writeln!(xml, "{}</Offsets>", inner);
+            }
+        }
+        if !entry.children.is_empty() {
+            write_entries(xml, &entry.children, depth + 2, next_id);
+        }
+        let _ = writeln!(xml, "{}  </CheatEntry>", indent);
+    }
+    let _ = writeln!(xml, "{}</CheatEntries>", indent);
+}
+
+fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn unescape_xml(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// XML元素，仅保留名称、子元素与文本，忽略属性
+#[derive(Debug, Default)]
+struct XmlElement {
+    name: String,
+    children: Vec<XmlElement>,
+    text: String,
+}
+
+impl XmlElement {
+    fn child(&self, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|e| e.name == name)
+    }
+}
+
+/// 仅支持CE表格所需子集的XML解析器
+struct XmlParser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> XmlParser<'a> {
+    fn new(input: &'a str) -> Self {
+        Self { input, pos: 0 }
+    }
+
+    fn parse_document(&mut self) -> Result<XmlElement, CheatTableError> {
+        self.skip_misc()?;
+        let root = self.parse_element()?;
+        self.skip_misc()?;
+        if self.pos != self.input.len() {
+            return Err(self.error("unexpected content after root element"));
+        }
+        Ok(root)
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn error(&self, reason: &str) -> CheatTableError {
+        CheatTableError::Xml {
+            position: self.pos,
+            reason: reason.to_string(),
+        }
+    }
+
+    /// 跳过空白、XML声明、注释与DOCTYPE
+    fn skip_misc(&mut self) -> Result<(), CheatTableError> {
+        loop {
+            let trimmed = self.rest().trim_start();
+            self.pos = self.input.len() - trimmed.len();
+            if trimmed.starts_with("<?") {
+                self.skip_past("?>")?;
+            } else if trimmed.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if trimmed.starts_with("<!") {
+                self.skip_past(">")?;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    fn skip_past(&mut self, end: &str) -> Result<&'a str, CheatTableError> {
+        let rest = self.rest();
+        let index = rest
+            .find(end)
+            .ok_or_else(|| self.error(&format!("missing `{}`", end)))?;
+        self.pos += index + end.len();
+        Ok(&rest[..index])
+    }
+
+    fn parse_element(&mut self) -> Result<XmlElement, CheatTableError> {
+        if !self.rest().starts_with('<') {
+            return Err(self.error("expected `<`"));
+        }
+        self.pos += 1;
+        let name_len = self
+            .rest()
+            .find(|c: char| c.is_whitespace() || c == '>' || c == '/')
+            .ok_or_else(|| self.error("unterminated tag"))?;
+        let name = self.rest()[..name_len].to_string();
+        if name.is_empty() {
+            return Err(self.error("empty tag name"));
+        }
+        self.pos += name_len;
+        let tag = self.skip_tag_rest()?;
+        let mut element = XmlElement {
+            name,
+            ..Default::default()
+        };
+        if tag.ends_with('/') {
+            return Ok(element);
+        }
+
+        loop {
+            let rest = self.rest();
+            if rest.starts_with("</") {
+                let close = self.skip_past(">")?;
+                if close[2..].trim() != element.name {
+                    return Err(
+                        self.error(&format!("mismatched closing tag for <{}>", element.name))
+                    );
+                }
+                return Ok(element);
+            } else if rest.starts_with("<![CDATA[") {
+                self.pos += "<![CDATA[".len();
+                let text = self.skip_past("]]>")?;
+                element.text.push_str(text);
+            } else if rest.starts_with("<!--") {
+                self.skip_past("-->")?;
+            } else if rest.starts_with('<') {
+                element.children.push(self.parse_element()?);
+            } else if rest.is_empty() {
+                return Err(self.error(&format!("unclosed element <{}>", element.name)));
+            } else {
+                let len = rest.find('<').unwrap_or(rest.len());
+                element.text.push_str(&unescape_xml(&rest[..len]));
+                self.pos += len;
+            }
+        }
+    }
+
+    /// 跳过标签的属性部分，返回 `>` 之前的内容
+    fn skip_tag_rest(&mut self) -> Result<&'a str, CheatTableError> {
+        let rest = self.rest();
+        let mut quote = None;
+        for (i, c) in rest.char_indices() {
+            match (quote, c) {
+                (None, '"' | '\'') => quote = Some(c),
+                (Some(q), _) if q == c => quote = None,
+                (None, '>') => {
+                    self.pos += i + 1;
+                    return Ok(rest[..i].trim_end());
+                }
+                _ => {}
+            }
+        }
+        Err(self.error("unterminated tag"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::utils::{set_thread_memory_source, SparseMemory};
+
+    const PARTY_TABLE: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<CheatTable>
+  <CheatEntries>
+    <CheatEntry>
+      <ID>395</ID>
+      <Description>"PlayerShortInfo01"</Description>
+      <ShowAsSigned>0</ShowAsSigned>
+      <VariableType>4 Bytes</VariableType>
+      <Address>0x1450112F0</Address>
+      <Offsets>
+        <Offset>0</Offset>
+        <Offset>0x1AB0 + 0x58 * 1</Offset>
+      </Offsets>
+      <CheatEntries>
+        <CheatEntry>
+          <ID>434</ID>
+          <Description>"名字"</Description>
+          <VariableType>String</VariableType>
+          <Length>32</Length>
+          <Unicode>0</Unicode>
+          <Address>+49</Address>
+        </CheatEntry>
+        <CheatEntry>
+          <ID>436</ID>
+          <Description>"脚本"</Description>
+          <VariableType>Auto Assembler Script</VariableType>
+          <AssemblerScript><![CDATA[[ENABLE]
+// <not xml>
+]]></AssemblerScript>
+        </CheatEntry>
+      </CheatEntries>
+    </CheatEntry>
+    <CheatEntry>
+      <ID>20</ID>
+      <Description>"Symbol"</Description>
+      <VariableType>4 Bytes</VariableType>
+      <Address>playerBase+10</Address>
+      <CheatEntries>
+        <CheatEntry>
+          <ID>22</ID>
+          <Description>"Pointer"</Description>
+          <VariableType>4 Bytes</VariableType>
+          <Address>[MonsterHunterWorld.exe+50139A0]+10</Address>
+        </CheatEntry>
+        <CheatEntry>
+          <ID>21</ID>
+          <Description>"Child"</Description>
+          <VariableType>4 Bytes</VariableType>
+          <Address>+8</Address>
+        </CheatEntry>
+      </CheatEntries>
+    </CheatEntry>
+    <CheatEntry>
+      <ID>12</ID>
+      <Description>"HP"</Description>
+      <VariableType>Float</VariableType>
+      <Address>"MonsterHunterWorld.exe"+050139A0</Address>
+      <Offsets>
+        <Offset>64</Offset>
+        <Offset>7630</Offset>
+        <Offset>50</Offset>
+      </Offsets>
+    </CheatEntry>
+  </CheatEntries>
+</CheatTable>
+"#;
+
+    #[test]
+    fn test_cheat_table() {
+        let table = CheatTable::parse(PARTY_TABLE).unwrap();
+        let definitions = table.definitions();
+        assert_eq!(definitions.len(), 3);
+        assert_eq!(
+            definitions[0].path.to_string(),
+            "0x1450112F0 -> 0x1B08 -> 0x0"
+        );
+        assert_eq!(definitions[0].value_type, CeValueType::Bytes4);
+        let name = table.definition("PlayerShortInfo01/名字").unwrap();
+        assert_eq!(name.path.to_string(), "0x1450112F0 -> 0x1B08 -> 0x49");
+        assert_eq!(
+            name.value_type,
+            CeValueType::String {
+                len: 32,
+                unicode: false
+            }
+        );
+        assert!(table.entries[0].children[1].value_type.is_none());
+        // 不支持的地址只影响该条目与其相对地址的子条目
+        assert!(table.entries[1].path.is_none());
+        assert!(table.entries[1].children[0].path.is_none());
+        assert!(table.entries[1].children[1].path.is_none());
+        let hp = table.definition("HP").unwrap();
+        assert_eq!(
+            hp.path.to_string(),
+            "MonsterHunterWorld.exe+0x50139A0 -> 0x50 -> 0x7630 -> 0x64"
+        );
+
+        // 导出后重新解析得到相同的定义
+        let exported = CheatTable::from_definitions(&definitions).to_xml();
+        assert!(exported.contains("<Offset>1B08</Offset>"));
+        let reparsed = CheatTable::parse(&exported).unwrap();
+        assert_eq!(reparsed.definitions()[1].path, name.path);
+
+        let mut memory = SparseMemory::new();
+        memory.insert_zeroed(0x1450112F0, 0x2000);
+        memory.insert_zeroed(0x20000, 0x100);
+        memory.write_value(0x1450112F0_usize, 0x1450112F0_usize);
+        memory.write_value(0x1450112F0_usize + 0x1B08, 0x20000_usize);
+        memory.write_value(0x20049_usize, *b"Hunter\0\0");
+        let _guard = set_thread_memory_source(Arc::new(memory));
+        assert_eq!(name.read().unwrap(), CeValue::String("Hunter".to_string()));
+
+        let known = crate::game_export::known_pointers();
+        let exported = CheatTable::from_definitions(&known).to_xml();
+        assert_eq!(CheatTable::parse(&exported).unwrap().definitions(), known);
+
+        assert!(CheatTable::parse("<CheatTable><CheatEntries></CheatTable>").is_err());
+        assert_eq!(eval_offset("-0x10 + 4"), Ok(-0xC));
+    }
+}
@@ -0,0 +1,174 @@
+use std::cmp::Ordering;
+
+use serde::{Deserialize, Serialize};
+
+/// 字段的数值类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum FieldType {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    I64,
+    F32,
+    F64,
+}
+
+impl FieldType {
+    pub fn size(&self) -> usize {
+        match self {
+            FieldType::U8 | FieldType::I8 => 1,
+            FieldType::U16 | FieldType::I16 => 2,
+            FieldType::U32 | FieldType::I32 | FieldType::F32 => 4,
+            FieldType::U64 | FieldType::I64 | FieldType::F64 => 8,
+        }
+    }
+
+    pub fn is_float(&self) -> bool {
+        matches!(self, FieldType::F32 | FieldType::F64)
+    }
+
+    /// 将小端字节解释为该类型的值，`bytes` 长度必须等于 [`size`](Self::size)
+    pub fn decode(&self, bytes: &[u8]) -> FieldValue {
+        let mut raw = [0_u8; 8];
+        raw[..bytes.len()].copy_from_slice(bytes);
+        let unsigned = u64::from_le_bytes(raw);
+        match self {
+            FieldType::U8 | FieldType::U16 | FieldType::U32 | FieldType::U64 => {
+                FieldValue::UInt(unsigned)
+            }
+            FieldType::I8 => FieldValue::Int(unsigned as u8 as i8 as i64),
+            FieldType::I16 => FieldValue::Int(unsigned as u16 as i16 as i64),
+            FieldType::I32 => FieldValue::Int(unsigned as u32 as i32 as i64),
+            FieldType::I64 => FieldValue::Int(unsigned as i64),
+            FieldType::F32 => FieldValue::Float(f32::from_bits(unsigned as u32) as f64),
+            FieldType::F64 => FieldValue::Float(f64::from_bits(unsigned)),
+        }
+    }
+}
+
+/// 按 [`FieldType`] 解释得到的值
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FieldValue {
+    UInt(u64),
+    Int(i64),
+    Float(f64),
+}
+
+impl FieldValue {
+    pub fn as_f64(&self) -> f64 {
+        match *self {
+            FieldValue::UInt(v) => v as f64,
+            FieldValue::Int(v) => v as f64,
+            FieldValue::Float(v) => v,
+        }
+    }
+
+    /// 整数的原值，浮点数返回None
+    fn as_i128(&self) -> Option<i128> {
+        match *self {
+            FieldValue::UInt(v) => Some(v as i128),
+            FieldValue::Int(v) => Some(v as i128),
+            FieldValue::Float(_) => None,
+        }
+    }
+
+    /// 比较两个值，整数之间按原值比较，含浮点数时按f64比较，NaN无法比较
+    pub fn compare(&self, other: &FieldValue) -> Option<Ordering> {
+        match (self.as_i128(), other.as_i128()) {
+            (Some(a), Some(b)) => Some(a.cmp(&b)),
+            _ => self.as_f64().partial_cmp(&other.as_f64()),
+        }
+    }
+
+    /// 按原始位模式判断是否相同，NaN与自身相同，与其他值不同
+    fn same_bits(&self, other: &FieldValue) -> bool {
+        match (*self, *other) {
+            (FieldValue::Float(a), FieldValue::Float(b)) => a.to_bits() == b.to_bits(),
+            _ => self.compare(other) == Some(Ordering::Equal),
+        }
+    }
+
+    /// `self - base` 是否等于 `delta`
+    fn differs_by(&self, base: &FieldValue, delta: &FieldValue) -> bool {
+        match (self.as_i128(), base.as_i128(), delta.as_i128()) {
+            (Some(new), Some(old), Some(delta)) => new - old == delta,
+            _ => self.as_f64() - base.as_f64() == delta.as_f64(),
+        }
+    }
+}
+
+impl From<u64> for FieldValue {
+    fn from(value: u64) -> Self {
+        FieldValue::UInt(value)
+    }
+}
+
+impl From<i64> for FieldValue {
+    fn from(value: i64) -> Self {
+        FieldValue::Int(value)
+    }
+}
+
+impl From<f64> for FieldValue {
+    fn from(value: f64) -> Self {
+        FieldValue::Float(value)
+    }
+}
+
+/// 字段的旧值与新值之间的比较条件
+///
+/// 整数按原值比较，避免大整数转换为浮点数后丢失精度
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    /// 原始位模式改变，包括变为或不再是NaN
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+    /// 新值等于给定值
+    Equals(FieldValue),
+    /// 新值位于 `[min, max]` 之间
+    Between(FieldValue, FieldValue),
+    /// 增加了给定值
+    IncreasedBy(FieldValue),
+    /// 减少了给定值
+    DecreasedBy(FieldValue),
+}
+
+impl Comparison {
+    pub fn matches(&self, old: FieldValue, new: FieldValue) -> bool {
+        match *self {
+            Comparison::Changed => !new.same_bits(&old),
+            Comparison::Unchanged => new.same_bits(&old),
+            Comparison::Increased => new.compare(&old) == Some(Ordering::Greater),
+            Comparison::Decreased => new.compare(&old) == Some(Ordering::Less),
+            Comparison::Equals(value) => new.compare(&value) == Some(Ordering::Equal),
+            Comparison::Between(min, max) => {
+                new.compare(&min).is_some_and(|o| o != Ordering::Less)
+                    && new.compare(&max).is_some_and(|o| o != Ordering::Greater)
+            }
+            Comparison::IncreasedBy(delta) => new.differs_by(&old, &delta),
+            Comparison::DecreasedBy(delta) => old.differs_by(&new, &delta),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changed_nan() {
+        let nan = FieldValue::Float(f64::NAN);
+        let one = FieldValue::Float(1.0);
+        assert!(Comparison::Changed.matches(one, nan));
+        assert!(Comparison::Changed.matches(nan, one));
+        assert!(Comparison::Unchanged.matches(nan, nan));
+        assert!(!Comparison::Unchanged.matches(one, nan));
+        assert!(Comparison::Unchanged.matches(FieldValue::Int(-1), FieldValue::Int(-1)));
+    }
+}
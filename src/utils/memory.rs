@@ -1,13 +1,20 @@
-use std::{ffi::c_void, slice};
+use std::ffi::c_void;
 
 use thiserror::Error;
 use windows::Win32::System::Memory::{
     VirtualProtect, PAGE_EXECUTE_READWRITE, PAGE_PROTECTION_FLAGS,
 };
 
+use super::{
+    decode_rip_operand, read_span_covering, Capture, DecodeError, Pattern, PatternHit, PeError,
+    PeImage, RipOperand, SectionFilter, MAX_INSTRUCTION_LEN,
+};
+
 const PATTERN_WILDCARD: u8 = 0xFF;
+/// 分块扫描时每块的大小
+const SCAN_CHUNK_SIZE: usize = 0x1000000;
 
-#[derive(Debug, Error)]
+#[derive(Debug, Error, Clone)]
 pub enum PatternScanError {
     #[error("pattern not found")]
     NotFound,
@@ -15,6 +22,58 @@ pub enum PatternScanError {
     MultipleMatchesFound,
     #[error("invalid pattern format: {0}")]
     Format(String),
+    #[error("failed to read module: {0}")]
+    Module(#[from] PeError),
+    #[error("failed to decode instruction: {0}")]
+    Decode(#[from] DecodeError),
+}
+
+/// 特征码匹配结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternMatch {
+    /// 所在模块名
+    pub module: String,
+    /// 相对模块基址的偏移
+    pub rva: usize,
+    /// 虚拟地址
+    pub va: usize,
+    /// 特征码中捕获的操作数
+    pub captures: Vec<Capture>,
+}
+
+impl PatternMatch {
+    /// 由节内的匹配构造，`section_rva` 为节的RVA
+    pub(crate) fn from_hit(image: &PeImage, section_rva: usize, hit: PatternHit) -> Self {
+        let rva = section_rva + hit.start;
+        Self {
+            module: image.name().to_string(),
+            rva,
+            va: image.rva_to_va(rva),
+            captures: hit.captures,
+        }
+    }
+
+    pub fn capture(&self, name: &str) -> Option<&Capture> {
+        self.captures.iter().find(|c| c.name == name)
+    }
+
+    /// 捕获的相对偏移指向的虚拟地址
+    pub fn capture_target(&self, name: &str) -> Option<usize> {
+        self.capture(name)?.target(self.va)
+    }
+
+    /// 解码匹配位置偏移 `offset` 处指令的相对寻址操作数
+    ///
+    /// `image` 为匹配所在的模块镜像
+    pub fn rip_operand(&self, image: &PeImage, offset: isize) -> Result<RipOperand, DecodeError> {
+        let va = self.va.wrapping_add_signed(offset);
+        let rva = image.va_to_rva(va).ok_or(DecodeError::Unreadable(va))?;
+        let len = MAX_INSTRUCTION_LEN.min(image.size() - rva);
+        let code = image
+            .bytes_at(rva, len)
+            .ok_or(DecodeError::Unreadable(va))?;
+        decode_rip_operand(code, va)
+    }
 }
 
 pub struct PatternScan;
@@ -24,32 +83,115 @@ impl PatternScan {
         boyer_moore_search_all(text, pattern, wildcard)
     }
 
-    /// 扫描内存，查找匹配的第一个地址
-    pub fn scan_first(pattern: &[u8]) -> Result<u64, PatternScanError> {
-        for now_ptr in (0x140000000_u64..0x143000000_u64).step_by(0x1000000) {
-            let part = unsafe { slice::from_raw_parts(now_ptr as *const u8, 0x1000100) };
-            let matches: Option<usize> = boyer_moore_search_first(part, pattern, PATTERN_WILDCARD);
-            if let Some(matches) = matches {
-                let real_ptr = now_ptr + matches as u64;
-                return Ok(real_ptr);
+    /// 分块搜索所有匹配位置
+    ///
+    /// 相邻块之间重叠 `pattern.len() - 1` 字节，跨越块边界的匹配只会被记录一次。
+    pub fn search_chunked(
+        text: &[u8],
+        pattern: &[u8],
+        wildcard: u8,
+        chunk_size: usize,
+    ) -> Vec<usize> {
+        if pattern.is_empty() || chunk_size == 0 {
+            return Vec::new();
+        }
+        let overlap = pattern.len() - 1;
+        let mut result = Vec::new();
+        for chunk_start in (0..text.len()).step_by(chunk_size) {
+            let chunk_end = (chunk_start + chunk_size + overlap).min(text.len());
+            boyer_moore_search_all(&text[chunk_start..chunk_end], pattern, wildcard)
+                .into_iter()
+                // 起点落在下一块的匹配由下一块负责
+                .filter(|&pos| pos < chunk_size)
+                .for_each(|pos| result.push(chunk_start + pos));
+        }
+        result
+    }
+
+    /// 扫描模块中满足条件的节，查找匹配的所有地址
+    ///
+    /// 按块扫描，跨越块边界的匹配只会被记录一次。
+    pub fn scan_module(
+        image: &PeImage,
+        filter: SectionFilter,
+        pattern: &Pattern,
+    ) -> Vec<PatternMatch> {
+        let mut result = Vec::new();
+        for section in image.sections().iter().filter(|s| filter.matches(s)) {
+            let bytes = image.section_bytes(section);
+            for chunk_start in (0..bytes.len()).step_by(SCAN_CHUNK_SIZE) {
+                let chunk_end = (chunk_start + SCAN_CHUNK_SIZE).min(bytes.len());
+                for hit in pattern.find_in_range(bytes, chunk_start, chunk_end) {
+                    result.push(PatternMatch::from_hit(
+                        image,
+                        section.virtual_address as usize,
+                        hit,
+                    ));
+                }
             }
         }
+        result
+    }
 
-        Err(PatternScanError::NotFound)
+    /// 扫描模块中满足条件的节，如果有且仅有一个匹配，则返回匹配结果，否则返回错误
+    pub fn safe_scan_module(
+        image: &PeImage,
+        filter: SectionFilter,
+        pattern: &Pattern,
+    ) -> Result<PatternMatch, PatternScanError> {
+        let mut result = Self::scan_module(image, filter, pattern);
+        match result.len() {
+            0 => Err(PatternScanError::NotFound),
+            1 => Ok(result.remove(0)),
+            _ => Err(PatternScanError::MultipleMatchesFound),
+        }
     }
 
-    /// 扫描内存，查找匹配的所有地址
-    pub fn scan_all(pattern: &[u8]) -> Result<Vec<u64>, PatternScanError> {
-        let mut result = Vec::new();
-        for now_ptr in (0x140000000_u64..0x143000000_u64).step_by(0x1000000) {
-            let part = unsafe { slice::from_raw_parts(now_ptr as *const u8, 0x1000100) };
-            let matches = boyer_moore_search_all(part, pattern, PATTERN_WILDCARD);
-            if !matches.is_empty() {
-                matches
+    /// 扫描模块中满足条件的节，返回第一个匹配
+    ///
+    /// 与 [`PatternScan::scan_module`] 使用相同的分块扫描，找到匹配后立即返回。
+    pub fn scan_module_first(
+        image: &PeImage,
+        filter: SectionFilter,
+        pattern: &Pattern,
+    ) -> Option<PatternMatch> {
+        for section in image.sections().iter().filter(|s| filter.matches(s)) {
+            let bytes = image.section_bytes(section);
+            for chunk_start in (0..bytes.len()).step_by(SCAN_CHUNK_SIZE) {
+                let chunk_end = (chunk_start + SCAN_CHUNK_SIZE).min(bytes.len());
+                if let Some(hit) = pattern
+                    .find_in_range(bytes, chunk_start, chunk_end)
                     .into_iter()
-                    .for_each(|x| result.push(x as u64 + now_ptr));
+                    .next()
+                {
+                    return Some(PatternMatch::from_hit(
+                        image,
+                        section.virtual_address as usize,
+                        hit,
+                    ));
+                }
             }
         }
+        None
+    }
+
+    /// 扫描主模块的可执行节，查找匹配的第一个地址
+    pub fn scan_first(pattern: &[u8]) -> Result<u64, PatternScanError> {
+        let image = PeImage::main_module()?;
+        let pattern = Pattern::from_wildcard_bytes(pattern, PATTERN_WILDCARD);
+        Self::scan_module_first(&image, SectionFilter::Executable, &pattern)
+            .map(|m| m.va as u64)
+            .ok_or(PatternScanError::NotFound)
+    }
+
+    /// 扫描主模块的可执行节，查找匹配的所有地址
+    pub fn scan_all(pattern: &[u8]) -> Result<Vec<u64>, PatternScanError> {
+        let image = PeImage::main_module()?;
+        let pattern = Pattern::from_wildcard_bytes(pattern, PATTERN_WILDCARD);
+        let result: Vec<u64> = Self::scan_module(&image, SectionFilter::Executable, &pattern)
+            .into_iter()
+            .map(|m| m.va as u64)
+            .collect();
         if result.is_empty() {
             Err(PatternScanError::NotFound)
         } else {
@@ -57,26 +199,19 @@ impl PatternScan {
         }
     }
 
-    /// 扫描内存，查找匹配的地址，如果有且仅有一个，则返回地址，否则返回错误
+    /// 扫描主模块的可执行节，查找匹配的地址，如果有且仅有一个，则返回地址，否则返回错误
     pub fn safe_scan(pattern: &[u8]) -> Result<u64, PatternScanError> {
-        let mut result = Vec::new();
-        for now_ptr in (0x140000000_u64..0x143000000_u64).step_by(0x1000000) {
-            let part = unsafe { slice::from_raw_parts(now_ptr as *const u8, 0x1000100) };
-            let matches = boyer_moore_search_all(part, pattern, PATTERN_WILDCARD);
-            if !matches.is_empty() {
-                matches
-                    .into_iter()
-                    .for_each(|x| result.push(x as u64 + now_ptr));
-            }
-        }
+        let result = Self::scan_all(pattern)?;
         match result.len() {
-            0 => Err(PatternScanError::NotFound),
             1 => Ok(result[0]),
             _ => Err(PatternScanError::MultipleMatchesFound),
         }
     }
 }
 
+/// 直接覆盖目标地址的字节
+///
+/// 不校验、不记录原始字节，需要可撤销的补丁请使用 [`Patch`](super::Patch)。
 pub unsafe fn patch(position: *const c_void, bytes: &[u8]) -> Result<(), String> {
     let patch_len = bytes.len();
     let dwsize = (patch_len / 4096 + 1) * 4096;
@@ -98,71 +233,111 @@ pub unsafe fn patch(position: *const c_void, bytes: &[u8]) -> Result<(), String>
     Ok(())
 }
 
+/// 将 `position` 处的 `length` 字节替换为 `nop`
+///
+/// 替换范围必须恰好覆盖若干条完整指令，否则返回错误且不写入。
 pub unsafe fn patch_nop(position: *const c_void, length: usize) -> Result<(), String> {
+    let span = read_span_covering(position as usize, length).map_err(|e| e.to_string())?;
+    if span.len != length {
+        return Err(format!(
+            "patching {} bytes at {:p} would split an instruction, the nearest boundary is {} bytes",
+            length, position, span.len
+        ));
+    }
     let nop_bytes = vec![0x90; length];
     patch(position, &nop_bytes)?;
 
     Ok(())
 }
 
-fn build_bad_character_table(pattern: &[u8], wildcard: u8) -> Vec<isize> {
+/// 坏字符表：每个字节值在特征码中能匹配的最后位置
+fn build_bad_character_table(pattern: &[u8], mask: &[u8]) -> Vec<isize> {
     let mut table = vec![-1; 256];
-    for (i, &byte) in pattern.iter().enumerate() {
-        if byte != wildcard {
-            // 忽略通配符
+    for (i, (&byte, &m)) in pattern.iter().zip(mask.iter()).enumerate() {
+        if m == 0xFF {
             table[byte as usize] = i as isize;
+        } else {
+            // 通配位置能匹配任意满足掩码的字节
+            for (value, entry) in table.iter_mut().enumerate() {
+                if value as u8 & m == byte & m {
+                    *entry = i as isize;
+                }
+            }
         }
     }
     table
 }
 
-pub fn boyer_moore_search_first(text: &[u8], pattern: &[u8], wildcard: u8) -> Option<usize> {
-    let bct = build_bad_character_table(pattern, wildcard);
-    let m = pattern.len();
-    let n = text.len();
-    let mut i = 0;
+fn wildcard_mask(pattern: &[u8], wildcard: u8) -> Vec<u8> {
+    pattern
+        .iter()
+        .map(|&b| if b == wildcard { 0x00 } else { 0xFF })
+        .collect()
+}
 
-    while i <= n - m {
-        let mut j = (m - 1) as isize;
-        while j >= 0
-            && (pattern[j as usize] == wildcard || pattern[j as usize] == text[i + j as usize])
-        {
-            j -= 1;
-        }
-        if j < 0 {
-            return Some(i);
-        } else {
-            let bad_char_shift = bct[text[i + j as usize] as usize];
-            i += std::cmp::max(1, j - bad_char_shift) as usize;
-        }
-    }
-    None
+pub fn boyer_moore_search_first(text: &[u8], pattern: &[u8], wildcard: u8) -> Option<usize> {
+    masked_search_first(text, pattern, &wildcard_mask(pattern, wildcard))
 }
 
 pub fn boyer_moore_search_all(text: &[u8], pattern: &[u8], wildcard: u8) -> Vec<usize> {
-    let bct = build_bad_character_table(pattern, wildcard);
+    masked_search_all(text, pattern, &wildcard_mask(pattern, wildcard))
+}
+
+/// 带掩码的Boyer-Moore搜索，返回第一个匹配位置
+///
+/// `mask` 与 `pattern` 等长，为1的位参与匹配
+pub fn masked_search_first(text: &[u8], pattern: &[u8], mask: &[u8]) -> Option<usize> {
+    let mut first = None;
+    masked_search(text, pattern, mask, |pos| {
+        first = Some(pos);
+        false
+    });
+    first
+}
+
+/// 带掩码的Boyer-Moore搜索，返回所有匹配位置
+pub fn masked_search_all(text: &[u8], pattern: &[u8], mask: &[u8]) -> Vec<usize> {
     let mut matches = Vec::new();
+    masked_search(text, pattern, mask, |pos| {
+        matches.push(pos);
+        true
+    });
+    matches
+}
+
+/// `on_match` 返回false时停止搜索
+fn masked_search(
+    text: &[u8],
+    pattern: &[u8],
+    mask: &[u8],
+    mut on_match: impl FnMut(usize) -> bool,
+) {
     let m = pattern.len();
     let n = text.len();
+    if m == 0 || n < m || mask.len() != m {
+        return;
+    }
+    let bct = build_bad_character_table(pattern, mask);
     let mut i = 0;
 
     while i <= n - m {
         let mut j = (m - 1) as isize;
-        while j >= 0
-            && (pattern[j as usize] == wildcard || pattern[j as usize] == text[i + j as usize])
-        {
+        while j >= 0 && {
+            let k = j as usize;
+            text[i + k] & mask[k] == pattern[k] & mask[k]
+        } {
             j -= 1;
         }
         if j < 0 {
-            matches.push(i);
+            if !on_match(i) {
+                return;
+            }
             i += 1;
         } else {
             let bad_char_shift = bct[text[i + j as usize] as usize];
             i += std::cmp::max(1, j - bad_char_shift) as usize;
         }
     }
-
-    matches
 }
 
 /// 将特征码模板字符串转换为字节数组
@@ -185,6 +360,8 @@ pub fn boyer_moore_search_all(text: &[u8], pattern: &[u8], wildcard: u8) -> Vec<
 /// ```
 ///
 /// 通配符：支持 `**`, `??`, `?`，通配符转换为 `0xFF`
+///
+/// 注意：特征码中字面量 `FF` 也会被视为通配符，需要精确匹配时请使用 [`Pattern::parse`]。
 pub fn space_hex_to_bytes(text_hex: &str) -> Result<Vec<u8>, PatternScanError> {
     text_hex
         .split_whitespace()
@@ -219,6 +396,17 @@ pub fn relative_address(src: *const c_void, dst: *const c_void, cmd_length: usiz
     dst_addr as isize - (src_addr as isize + cmd_length as isize)
 }
 
+/// 由相对地址计算目标地址，是 [`relative_address`] 的逆运算
+///
+/// src: 调用者的原地址\
+/// offset: 指令中的相对偏移\
+/// cmd_length: 调用者指令长度
+pub fn relative_target(src: *const c_void, offset: isize, cmd_length: usize) -> *const c_void {
+    (src as usize)
+        .wrapping_add(cmd_length)
+        .wrapping_add_signed(offset) as *const c_void
+}
+
 #[cfg(test)]
 mod tests {
     use address_scanner::hex_str_to_bytes;
@@ -260,9 +448,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_search_chunked() {
+        let mut text = vec![0_u8; 64];
+        // 跨越第一个块边界（16）的匹配
+        text[14..18].copy_from_slice(&[0x48, 0x8B, 0x05, 0x11]);
+        // 恰好位于第二个块起点的匹配
+        text[32..36].copy_from_slice(&[0x48, 0x8B, 0x05, 0x22]);
+        let pattern = hex_str_to_bytes!("48 8B 05 **");
+
+        let matches = PatternScan::search_chunked(&text, &pattern, 0xFF, 16);
+        assert_eq!(matches, vec![14, 32]);
+        assert_eq!(matches, PatternScan::search(&text, &pattern, 0xFF));
+        assert!(PatternScan::search_chunked(&text[..3], &pattern, 0xFF, 16).is_empty());
+    }
+
     #[test]
     fn test_relative_address() {
         let addr = relative_address(0x109DA7FF as *const c_void, 0x073C99D0 as *const c_void, 5);
         eprintln!("{:X?}", addr.to_le_bytes());
     }
+
+    #[test]
+    fn test_relative_target() {
+        let addr = relative_address(0x109DA7FF as *const c_void, 0x073C99D0 as *const c_void, 5);
+        assert_eq!(
+            relative_target(0x109DA7FF as *const c_void, addr, 5),
+            0x073C99D0 as *const c_void
+        );
+    }
 }
@@ -0,0 +1,615 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    ffi::c_void,
+    mem::{self, MaybeUninit},
+    ops::Range,
+    sync::{Arc, RwLock},
+};
+
+use thiserror::Error;
+
+use super::{MemoryRegion, RegionMap, VirtualQueryRegions};
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum MemoryError {
+    #[error("hop {index} reads unreadable address 0x{address:X}")]
+    InvalidHop { index: usize, address: usize },
+    #[error("hop {index} yields a null pointer")]
+    NullPointer { index: usize },
+    #[error("failed to read {len} bytes at 0x{address:X}")]
+    Unreadable { address: usize, len: usize },
+}
+
+/// 内存数据源
+///
+/// 指针链取值与 `Resource` 的字段访问都通过该接口进行，
+/// 默认使用当前进程的内存（[`LiveMemory`]），测试时可替换为内存快照。
+pub trait MemorySource: Send + Sync {
+    /// 将游戏地址映射为当前进程可直接访问的指针
+    ///
+    /// 若 `[addr, addr + len)` 不可访问，返回None。
+    /// 内存快照的数据由锁保护，不提供指针，只能通过 `read_bytes` 与 `write_bytes` 访问。
+    fn host_ptr(&self, addr: usize, len: usize) -> Option<*mut u8>;
+
+    /// 读取 `[addr, addr + buf.len())` 的字节，成功返回true
+    fn read_bytes(&self, addr: usize, buf: &mut [u8]) -> bool {
+        match self.host_ptr(addr, buf.len()) {
+            Some(ptr) => {
+                unsafe { std::ptr::copy_nonoverlapping(ptr, buf.as_mut_ptr(), buf.len()) };
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// `[addr, addr + len)` 是否可以安全读取
+    fn is_readable(&self, addr: usize, len: usize) -> bool {
+        self.host_ptr(addr, len).is_some()
+    }
+
+    /// 数据源中所有可访问的内存区域，用于遍历整个数据源，例如数值搜索
+    fn committed_regions(&self) -> Vec<MemoryRegion> {
+        Vec::new()
+    }
+
+    /// 是否为当前进程的内存
+    ///
+    /// 只有当前进程的内存可以返回 `'static` 引用，内存快照中的引用会在快照释放后失效。
+    fn is_live(&self) -> bool {
+        false
+    }
+
+    /// 向 `[addr, addr + bytes.len())` 写入字节
+    fn write_bytes(&self, addr: usize, bytes: &[u8]) -> Result<(), String> {
+        let ptr = self
+            .host_ptr(addr, bytes.len())
+            .ok_or_else(|| format!("address 0x{:X} is not accessible", addr))?;
+        unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len()) };
+        Ok(())
+    }
+}
+
+impl dyn MemorySource + '_ {
+    /// 读取某个地址的值（的副本）
+    pub fn read_value<T: Copy>(&self, addr: usize) -> Option<T> {
+        let mut value = MaybeUninit::<T>::uninit();
+        let buf = unsafe {
+            std::slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, mem::size_of::<T>())
+        };
+        self.read_bytes(addr, buf)
+            .then(|| unsafe { value.assume_init() })
+    }
+
+    /// 向某个地址写入值
+    pub fn write_value<T: Copy>(&self, addr: usize, value: T) -> Result<(), String> {
+        let bytes = unsafe {
+            std::slice::from_raw_parts(&value as *const T as *const u8, mem::size_of::<T>())
+        };
+        self.write_bytes(addr, bytes)
+    }
+
+    /// 读取某个地址的值（的副本），读取前确认地址可读
+    pub fn try_read_value<T: Copy>(&self, addr: usize) -> Result<T, MemoryError> {
+        let len = mem::size_of::<T>();
+        let unreadable = MemoryError::Unreadable { address: addr, len };
+        if !self.is_readable(addr, len) {
+            return Err(unreadable);
+        }
+        self.read_value(addr).ok_or(unreadable)
+    }
+}
+
+/// 当前进程的内存
+///
+/// `host_ptr` 仅检查空指针，不检查地址是否可读；
+/// `is_readable` 通过 [`RegionMap`] 检查地址所在的内存区域，供带检查的读取使用。
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LiveMemory;
+
+impl MemorySource for LiveMemory {
+    fn host_ptr(&self, addr: usize, _len: usize) -> Option<*mut u8> {
+        if addr == 0 {
+            return None;
+        }
+        Some(addr as *mut u8)
+    }
+
+    fn is_readable(&self, addr: usize, len: usize) -> bool {
+        RegionMap::current().is_readable(addr, len)
+    }
+
+    fn committed_regions(&self) -> Vec<MemoryRegion> {
+        VirtualQueryRegions.enumerate()
+    }
+
+    fn is_live(&self) -> bool {
+        true
+    }
+
+    /// 写入前临时修改内存保护，可用于修改代码
+    fn write_bytes(&self, addr: usize, bytes: &[u8]) -> Result<(), String> {
+        if addr == 0 {
+            return Err("address is null".to_string());
+        }
+        unsafe { super::patch(addr as *const c_void, bytes) }
+    }
+}
+
+/// 连续的内存快照
+///
+/// 将一段字节映射到 `base` 起始的地址空间，数据由读写锁保护，可以在线程间共享。
+pub struct ByteSnapshot {
+    base: usize,
+    data: RwLock<Vec<u8>>,
+}
+
+impl ByteSnapshot {
+    pub fn new(base: usize, bytes: &[u8]) -> Self {
+        Self {
+            base,
+            data: RwLock::new(bytes.to_vec()),
+        }
+    }
+
+    /// 创建全零的快照
+    pub fn zeroed(base: usize, len: usize) -> Self {
+        Self::new(base, &vec![0; len])
+    }
+
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// 快照数据的副本
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.data.read().unwrap().clone()
+    }
+
+    pub fn as_bytes_mut(&mut self) -> &mut [u8] {
+        self.data.get_mut().unwrap()
+    }
+
+    /// 写入某个地址的值
+    ///
+    /// 地址超出快照范围时panic
+    pub fn write_value<T: Copy>(&mut self, addr: usize, value: T) {
+        let size = mem::size_of::<T>();
+        let range = self
+            .range(addr, size)
+            .unwrap_or_else(|| panic!("address 0x{:X} is out of snapshot range", addr));
+        let bytes = unsafe { std::slice::from_raw_parts(&value as *const T as *const u8, size) };
+        self.as_bytes_mut()[range].copy_from_slice(bytes);
+    }
+
+    /// `[addr, addr + len)` 在快照数据中的范围
+    fn range(&self, addr: usize, len: usize) -> Option<Range<usize>> {
+        let start = addr.checked_sub(self.base)?;
+        let end = start.checked_add(len)?;
+        (end <= self.len()).then_some(start..end)
+    }
+}
+
+impl MemorySource for ByteSnapshot {
+    fn host_ptr(&self, _addr: usize, _len: usize) -> Option<*mut u8> {
+        None
+    }
+
+    fn read_bytes(&self, addr: usize, buf: &mut [u8]) -> bool {
+        match self.range(addr, buf.len()) {
+            Some(range) => {
+                buf.copy_from_slice(&self.data.read().unwrap()[range]);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn is_readable(&self, addr: usize, len: usize) -> bool {
+        self.range(addr, len).is_some()
+    }
+
+    fn committed_regions(&self) -> Vec<MemoryRegion> {
+        vec![MemoryRegion::read_write(self.base, self.len())]
+    }
+
+    fn write_bytes(&self, addr: usize, bytes: &[u8]) -> Result<(), String> {
+        let range = self
+            .range(addr, bytes.len())
+            .ok_or_else(|| format!("address 0x{:X} is not accessible", addr))?;
+        self.data.write().unwrap()[range].copy_from_slice(bytes);
+        Ok(())
+    }
+}
+
+/// 稀疏内存映射
+///
+/// 由若干互不重叠的内存区域组成，适合构造多级指针的测试数据。
+#[derive(Default)]
+pub struct SparseMemory {
+    regions: BTreeMap<usize, ByteSnapshot>,
+}
+
+impl SparseMemory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 添加一段内存区域，若与已有区域重叠则panic
+    pub fn insert(&mut self, base: usize, bytes: &[u8]) {
+        let end = base + bytes.len();
+        let overlapped = self
+            .regions
+            .range(..end)
+            .next_back()
+            .is_some_and(|(_, region)| region.base() + region.len() > base);
+        if overlapped {
+            panic!(
+                "region 0x{:X}..0x{:X} overlaps an existing region",
+                base, end
+            );
+        }
+        self.regions.insert(base, ByteSnapshot::new(base, bytes));
+    }
+
+    /// 添加一段全零的内存区域
+    pub fn insert_zeroed(&mut self, base: usize, len: usize) {
+        self.insert(base, &vec![0; len]);
+    }
+
+    /// 写入某个地址的值，地址必须位于已有区域内
+    pub fn write_value<T: Copy>(&mut self, addr: usize, value: T) {
+        let region = self
+            .regions
+            .range_mut(..=addr)
+            .next_back()
+            .map(|(_, region)| region)
+            .unwrap_or_else(|| panic!("address 0x{:X} is not mapped", addr));
+        region.write_value(addr, value);
+    }
+
+    pub fn regions(&self) -> impl Iterator<Item = &ByteSnapshot> {
+        self.regions.values()
+    }
+
+    /// 复制数据源中的内存区域，跳过无法读取的部分
+    ///
+    /// 用于对游戏内存做离线分析，例如 [`PointerScanner`](super::PointerScanner)。
+    pub fn capture(source: &dyn MemorySource, regions: &[MemoryRegion]) -> Self {
+        const CHUNK_SIZE: usize = 0x100000;
+        let mut memory = Self::new();
+        let mut buf = Vec::new();
+        for region in regions {
+            for offset in (0..region.size).step_by(CHUNK_SIZE) {
+                let addr = region.base + offset;
+                buf.resize(CHUNK_SIZE.min(region.size - offset), 0);
+                if source.is_readable(addr, buf.len()) && source.read_bytes(addr, &mut buf) {
+                    memory.insert(addr, &buf);
+                }
+            }
+        }
+        memory
+    }
+}
+
+impl SparseMemory {
+    fn region(&self, addr: usize) -> Option<&ByteSnapshot> {
+        self.regions
+            .range(..=addr)
+            .next_back()
+            .map(|(_, region)| region)
+    }
+}
+
+impl MemorySource for SparseMemory {
+    fn host_ptr(&self, _addr: usize, _len: usize) -> Option<*mut u8> {
+        None
+    }
+
+    fn read_bytes(&self, addr: usize, buf: &mut [u8]) -> bool {
+        self.region(addr)
+            .is_some_and(|region| region.read_bytes(addr, buf))
+    }
+
+    fn is_readable(&self, addr: usize, len: usize) -> bool {
+        self.region(addr)
+            .is_some_and(|region| region.is_readable(addr, len))
+    }
+
+    fn write_bytes(&self, addr: usize, bytes: &[u8]) -> Result<(), String> {
+        self.region(addr)
+            .ok_or_else(|| format!("address 0x{:X} is not accessible", addr))?
+            .write_bytes(addr, bytes)
+    }
+
+    fn committed_regions(&self) -> Vec<MemoryRegion> {
+        self.regions
+            .values()
+            .flat_map(|r| r.committed_regions())
+            .collect()
+    }
+}
+
+thread_local! {
+    static THREAD_SOURCE: RefCell<Option<Arc<dyn MemorySource>>> = const { RefCell::new(None) };
+}
+
+/// 使用当前线程的内存数据源
+///
+/// 未设置时使用 [`LiveMemory`]
+pub fn with_memory_source<F, R>(f: F) -> R
+where
+    F: FnOnce(&dyn MemorySource) -> R,
+{
+    THREAD_SOURCE.with(|source| match source.borrow().as_ref() {
+        Some(source) => f(source.as_ref()),
+        None => f(&LiveMemory),
+    })
+}
+
+/// 为当前线程设置内存数据源
+///
+/// 返回的守卫被释放时恢复之前的数据源。
+pub fn set_thread_memory_source(source: Arc<dyn MemorySource>) -> MemorySourceGuard {
+    let previous = THREAD_SOURCE.with(|current| current.borrow_mut().replace(source));
+    MemorySourceGuard { previous }
+}
+
+pub struct MemorySourceGuard {
+    previous: Option<Arc<dyn MemorySource>>,
+}
+
+impl Drop for MemorySourceGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        THREAD_SOURCE.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+/// 在指定数据源上按多级偏移解析最终地址
+///
+/// 与CE中多级偏移取值算法一致：每一级先取值，再加偏移。
+/// 若任一级取值失败或为空指针，返回None
+pub fn resolve_offsets(source: &dyn MemorySource, base: usize, offsets: &[isize]) -> Option<usize> {
+    if base == 0 {
+        return None;
+    }
+    let mut addr = base;
+    for &offset in offsets.iter() {
+        let ptr: usize = source.read_value(addr)?;
+        if ptr == 0 {
+            return None;
+        }
+        addr = ptr.wrapping_add_signed(offset);
+    }
+    Some(addr)
+}
+
+/// 在指定数据源上按多级偏移解析最终地址，每一级取值前确认地址可读
+///
+/// 第 `i` 级取值（读取 `offsets[i]` 之前的指针）失败时，返回的错误包含 `index: i`。
+pub fn try_resolve_offsets(
+    source: &dyn MemorySource,
+    base: usize,
+    offsets: &[isize],
+) -> Result<usize, MemoryError> {
+    let mut addr = base;
+    for (index, &offset) in offsets.iter().enumerate() {
+        let ptr: usize = source
+            .try_read_value(addr)
+            .map_err(|_| MemoryError::InvalidHop {
+                index,
+                address: addr,
+            })?;
+        if ptr == 0 {
+            return Err(MemoryError::NullPointer { index });
+        }
+        addr = ptr.wrapping_add_signed(offset);
+    }
+    Ok(addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::game::{
+        prelude::{MtObject, Resource},
+        resources::{ActionController, ActionInfo, Health, Monster, Player, PlayerInfo, Quest},
+    };
+
+    use super::*;
+    use crate::utils::{set_thread_region_map, MemoryRegion, RegionQuery};
+
+    #[test]
+    fn test_resolve_offsets() {
+        let mut memory = SparseMemory::new();
+        memory.insert_zeroed(0x1000, 0x100);
+        memory.insert_zeroed(0x2000, 0x100);
+        memory.write_value(0x1000_usize, 0x2000_usize);
+        memory.write_value(0x2050_usize, 0x1080_usize);
+        memory.write_value(0x10C0_usize, 1234_i32);
+
+        let source: &dyn MemorySource = &memory;
+        assert_eq!(resolve_offsets(source, 0x1000, &[0x50, 0x40]), Some(0x10C0));
+        assert_eq!(source.read_value::<i32>(0x10C0), Some(1234));
+        // 空指针
+        assert_eq!(resolve_offsets(source, 0x2000, &[0x8]), None);
+        // 未映射地址
+        assert_eq!(resolve_offsets(source, 0x3000, &[0x0]), None);
+    }
+
+    #[test]
+    fn test_try_resolve_offsets() {
+        let mut chain = Box::new([0_usize; 4]);
+        let base = chain.as_ptr() as usize;
+        chain[0] = base + 0x10;
+        chain[1] = 0xDEAD_0000;
+        chain[3] = 1234;
+        let map = RegionMap::from_regions([MemoryRegion::read_write(base, 0x20)]);
+        let _guard = set_thread_region_map(Arc::new(map));
+
+        // 使用当前进程的内存，野指针不会被解引用
+        let source: &dyn MemorySource = &LiveMemory;
+        assert_eq!(try_resolve_offsets(source, base, &[0x8]), Ok(base + 0x18));
+        assert_eq!(source.try_read_value::<usize>(base + 0x18), Ok(1234));
+        assert_eq!(
+            try_resolve_offsets(source, base + 0x8, &[0x0, 0x0]),
+            Err(MemoryError::InvalidHop {
+                index: 1,
+                address: 0xDEAD_0000
+            })
+        );
+        assert_eq!(
+            try_resolve_offsets(source, base + 0x10, &[0x0]),
+            Err(MemoryError::NullPointer { index: 0 })
+        );
+        assert!(matches!(
+            source.try_read_value::<u64>(base + 0x1C),
+            Err(MemoryError::Unreadable { .. })
+        ));
+    }
+
+    #[test]
+    fn test_freed_region() {
+        struct Regions(Arc<RwLock<Vec<MemoryRegion>>>);
+
+        impl RegionQuery for Regions {
+            fn query(&self, addr: usize) -> Option<MemoryRegion> {
+                let regions = self.0.read().unwrap();
+                regions.iter().find(|r| r.contains(addr)).copied()
+            }
+        }
+
+        let mut chain = Box::new([0_usize; 4]);
+        let base = chain.as_ptr() as usize;
+        chain[0] = base + 0x10;
+        let regions = Arc::new(RwLock::new(vec![MemoryRegion::read_write(base, 0x20)]));
+        let map = RegionMap::new(Box::new(Regions(regions.clone())));
+        let _guard = set_thread_region_map(Arc::new(map));
+
+        let source: &dyn MemorySource = &LiveMemory;
+        assert_eq!(try_resolve_offsets(source, base, &[0x8]), Ok(base + 0x18));
+        // 缓存有效期内区域被释放
+        regions.write().unwrap().clear();
+        assert!(!RegionMap::current().is_readable(base, 8));
+        assert_eq!(
+            try_resolve_offsets(source, base, &[0x8]),
+            Err(MemoryError::InvalidHop {
+                index: 0,
+                address: base
+            })
+        );
+    }
+
+    #[test]
+    fn test_thread_memory_source() {
+        let mut memory = SparseMemory::new();
+        memory.insert_zeroed(0x20000, 0x18000);
+        memory.write_value(0x20038_usize, 2_i32);
+        memory.write_value(0x20000_usize + 0x17384, 1_i8);
+        memory.write_value(0x20100_usize, *b"Hunter\0");
+        // 区域末尾没有空字节的字符串
+        memory.write_value(0x37FFD_usize, *b"abc");
+
+        let _guard = set_thread_memory_source(Arc::new(memory));
+        let quest = Quest::from_instance(0x20000);
+        assert_eq!(quest.quest_state(), 2);
+        assert_eq!(
+            quest.with_value_mut(0x17384, |state: &mut i8| *state),
+            Some(1)
+        );
+        quest.with_value_mut(0x38, |state: &mut i32| *state = 3);
+        assert_eq!(quest.quest_state(), 3);
+        // 快照中的引用会在快照释放后失效，不返回 'static 引用
+        assert!(crate::utils::get_ref_with_offset(0x20038 as *const i32, &[]).is_none());
+        assert_eq!(quest.get_c_str(0x100), "Hunter");
+        assert_eq!(quest.get_c_str(0x17FFD), "");
+        assert_eq!(
+            PlayerInfo::from_instance(0x20100 - 0x78).try_name(),
+            Some("Hunter".to_string())
+        );
+        assert_eq!(quest.try_get_c_str(0x17FFD), None);
+    }
+
+    #[test]
+    fn test_shared_snapshot() {
+        let snapshot: Arc<dyn MemorySource> = Arc::new(ByteSnapshot::zeroed(0x1000, 0x100));
+        let threads: Vec<_> = (0..4_usize)
+            .map(|i| {
+                let snapshot = snapshot.clone();
+                std::thread::spawn(move || {
+                    for n in 0..1000_u64 {
+                        snapshot.write_value(0x1000 + i * 8, n).unwrap();
+                        assert!(snapshot.read_value::<u64>(0x1000 + i * 8).unwrap() <= n);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(snapshot.read_value::<u64>(0x1018), Some(999));
+        assert!(snapshot.host_ptr(0x1000, 8).is_none());
+        assert!(snapshot.write_value(0x10FC, 0_u64).is_err());
+    }
+
+    #[test]
+    fn test_resources_on_snapshot() {
+        let mut snapshot = ByteSnapshot::zeroed(0x100000, 0x20000);
+        snapshot.write_value(0x100000_usize + 0x7670, 0x110000_usize);
+        snapshot.write_value(0x110060_usize, 300.0_f32);
+        snapshot.write_value(0x110064_usize, 120.0_f32);
+        snapshot.write_value(0x100000_usize + 0x1D8A8, 1.0_f32);
+        snapshot.write_value(0x100000_usize + 0x13198 + 0x08, 50.0_f32);
+
+        let _guard = set_thread_memory_source(Arc::new(snapshot));
+        let monster = Monster::from_instance(0x100000);
+        assert_eq!(monster.health_value(), 120.0);
+        assert_eq!(monster.max_health_value(), 300.0);
+        let health = Health::from_instance(0x110000);
+        health.set_current(80.0);
+        health.set_max(310.0);
+        assert_eq!(
+            (monster.health_value(), monster.max_health_value()),
+            (80.0, 310.0)
+        );
+
+        assert_eq!(monster.speed_value(), 1.0);
+        monster.set_speed(1.5);
+        assert_eq!(monster.speed_value(), 1.5);
+
+        let quest = Quest::from_instance(0x100000);
+        quest.set_quest_state(2);
+        quest.set_quest_timer(40.0);
+        quest.set_ensurance_state(1);
+        assert_eq!(quest.quest_state(), 2);
+        assert_eq!(quest.quest_timer(), 40.0);
+        assert_eq!(quest.ensurance_state(), 1);
+
+        let controller = ActionController::from_instance(0x100000);
+        controller.force_derive(ActionInfo { set: 1, id: 42 });
+        assert_eq!(controller.next_action(), ActionInfo { set: 1, id: 42 });
+    }
+
+    #[test]
+    fn test_player_health() {
+        let mut memory = SparseMemory::new();
+        memory.insert_zeroed(0x10000, 0x8000);
+        memory.insert_zeroed(0x30000, 0x100);
+        memory.write_value(0x10000_usize + 0x7630, 0x30000_usize);
+        memory.write_value(0x30060_usize, 150.0_f32);
+        memory.write_value(0x30064_usize, 42.5_f32);
+
+        let _guard = set_thread_memory_source(Arc::new(memory));
+        let health = Player::from_instance(0x10000).health();
+        assert_eq!(health.max(), 150.0);
+        assert_eq!(health.current(), 42.5);
+    }
+}
@@ -1,5 +1,36 @@
+mod batch_scan;
+mod cheat_table;
+mod field;
 mod memory;
+mod memory_source;
+mod patch;
+mod pattern;
+mod pe;
+mod pointer_path;
+mod pointer_scan;
+mod region;
+mod trampoline;
 mod util;
+mod value_scan;
+mod watch;
+mod x86;
 
+pub use batch_scan::*;
+pub use cheat_table::*;
+pub use field::*;
 pub use memory::*;
+pub use memory_source::*;
+pub use patch::*;
+pub use pattern::*;
+pub use pe::*;
+pub use pointer_path::*;
+pub use pointer_scan::*;
+pub use region::*;
+pub use trampoline::*;
 pub use util::*;
+pub use value_scan::*;
+pub use watch::*;
+pub use x86::*;
+
+#[cfg(test)]
+pub(crate) use pe::tests::build_headers;
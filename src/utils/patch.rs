@@ -0,0 +1,354 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use once_cell::sync::Lazy;
+use thiserror::Error;
+
+use super::{bytes_to_space_hex, is_instruction_boundary, with_memory_source, DecodeError};
+
+pub type SharedPatchRegistry = Arc<Mutex<PatchRegistry>>;
+
+static PATCH_REGISTRY: Lazy<SharedPatchRegistry> =
+    Lazy::new(|| Arc::new(Mutex::new(PatchRegistry::new())));
+
+#[derive(Debug, Error)]
+pub enum PatchError {
+    #[error("failed to read memory at 0x{0:X}")]
+    Read(usize),
+    #[error("failed to write memory at 0x{address:X}: {reason}")]
+    Write { address: usize, reason: String },
+    #[error("unexpected bytes at 0x{address:X}: expected [{expected}], found [{found}]")]
+    Mismatch {
+        address: usize,
+        expected: String,
+        found: String,
+    },
+    #[error("replacement length {replacement} does not match original length {original}")]
+    LengthMismatch { original: usize, replacement: usize },
+    #[error("patch `{name}` overlaps existing patch `{existing}`")]
+    Overlap { name: String, existing: String },
+    #[error("patch `{0}` already exists")]
+    AlreadyExists(String),
+    #[error("patch `{0}` not found")]
+    NotFound(String),
+    #[error("patching {len} bytes at 0x{address:X} would split an instruction")]
+    SplitsInstruction { address: usize, len: usize },
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+}
+
+/// 可撤销的字节补丁
+///
+/// 创建时校验目标地址的原始字节，启用时写入新字节，禁用或释放时恢复原始字节。
+/// 启用与禁用前都会检查当前字节，避免覆盖其他代码对同一位置的修改。
+///
+/// ```ignore
+/// let addr = AddressRepository::get_instance()
+///     .lock()
+///     .unwrap()
+///     .get_record_address(steamwork::FailureJnzPatch)?;
+/// let mut patch = Patch::new("steamwork_failure", addr, &[0x75, 0x0D], &[0xEB, 0x0D])?;
+/// patch.enable()?;
+/// // ...
+/// patch.disable()?;
+/// ```
+#[derive(Debug)]
+pub struct Patch {
+    name: String,
+    address: usize,
+    original: Vec<u8>,
+    replacement: Vec<u8>,
+    enabled: bool,
+}
+
+impl Patch {
+    /// 创建补丁，`expected` 必须与目标地址当前的字节一致
+    pub fn new(
+        name: &str,
+        address: usize,
+        expected: &[u8],
+        replacement: &[u8],
+    ) -> Result<Self, PatchError> {
+        if expected.len() != replacement.len() {
+            return Err(PatchError::LengthMismatch {
+                original: expected.len(),
+                replacement: replacement.len(),
+            });
+        }
+        check_bytes(address, expected)?;
+
+        Ok(Self {
+            name: name.to_string(),
+            address,
+            original: expected.to_vec(),
+            replacement: replacement.to_vec(),
+            enabled: false,
+        })
+    }
+
+    /// 创建补丁，不校验原始字节，以目标地址当前的字节作为原始字节
+    pub fn unchecked(name: &str, address: usize, replacement: &[u8]) -> Result<Self, PatchError> {
+        let original = read_bytes(address, replacement.len())?;
+        Ok(Self {
+            name: name.to_string(),
+            address,
+            original,
+            replacement: replacement.to_vec(),
+            enabled: false,
+        })
+    }
+
+    /// 创建将原始字节替换为 `nop` 的补丁
+    ///
+    /// `expected` 必须恰好为若干条完整指令，否则会留下被截断的指令。
+    pub fn nop(name: &str, address: usize, expected: &[u8]) -> Result<Self, PatchError> {
+        if !is_instruction_boundary(expected, address, expected.len())? {
+            return Err(PatchError::SplitsInstruction {
+                address,
+                len: expected.len(),
+            });
+        }
+        Self::new(name, address, expected, &vec![0x90; expected.len()])
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn address(&self) -> usize {
+        self.address
+    }
+
+    pub fn len(&self) -> usize {
+        self.original.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.original.is_empty()
+    }
+
+    pub fn original(&self) -> &[u8] {
+        &self.original
+    }
+
+    pub fn replacement(&self) -> &[u8] {
+        &self.replacement
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// 补丁范围是否与另一个补丁重叠
+    pub fn overlaps(&self, other: &Patch) -> bool {
+        self.address < other.address + other.len() && other.address < self.address + self.len()
+    }
+
+    /// 写入新字节，已启用时不做任何操作
+    pub fn enable(&mut self) -> Result<(), PatchError> {
+        if self.enabled {
+            return Ok(());
+        }
+        check_bytes(self.address, &self.original)?;
+        write_bytes(self.address, &self.replacement)?;
+        self.enabled = true;
+        Ok(())
+    }
+
+    /// 恢复原始字节，未启用时不做任何操作
+    pub fn disable(&mut self) -> Result<(), PatchError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        check_bytes(self.address, &self.replacement)?;
+        write_bytes(self.address, &self.original)?;
+        self.enabled = false;
+        Ok(())
+    }
+
+    /// 切换启用状态，返回切换后的状态
+    pub fn toggle(&mut self) -> Result<bool, PatchError> {
+        if self.enabled {
+            self.disable()?;
+        } else {
+            self.enable()?;
+        }
+        Ok(self.enabled)
+    }
+}
+
+impl Drop for Patch {
+    fn drop(&mut self) {
+        if let Err(e) = self.disable() {
+            log::error!("failed to restore patch `{}`: {}", self.name, e);
+        }
+    }
+}
+
+/// 补丁注册表
+///
+/// 按名称管理补丁，拒绝与已有补丁重叠的补丁。移除补丁时恢复原始字节。
+#[derive(Debug, Default)]
+pub struct PatchRegistry {
+    patches: HashMap<String, Patch>,
+}
+
+impl PatchRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_instance() -> SharedPatchRegistry {
+        PATCH_REGISTRY.clone()
+    }
+
+    /// 注册补丁，不改变其启用状态
+    pub fn add(&mut self, patch: Patch) -> Result<(), PatchError> {
+        if self.patches.contains_key(patch.name()) {
+            return Err(PatchError::AlreadyExists(patch.name().to_string()));
+        }
+        if let Some(existing) = self.patches.values().find(|p| p.overlaps(&patch)) {
+            return Err(PatchError::Overlap {
+                name: patch.name().to_string(),
+                existing: existing.name().to_string(),
+            });
+        }
+        self.patches.insert(patch.name().to_string(), patch);
+        Ok(())
+    }
+
+    /// 移除补丁并返回，补丁被释放时恢复原始字节
+    pub fn remove(&mut self, name: &str) -> Option<Patch> {
+        self.patches.remove(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Patch> {
+        self.patches.get(name)
+    }
+
+    pub fn is_enabled(&self, name: &str) -> bool {
+        self.get(name).is_some_and(|p| p.is_enabled())
+    }
+
+    pub fn enable(&mut self, name: &str) -> Result<(), PatchError> {
+        self.get_mut(name)?.enable()
+    }
+
+    pub fn disable(&mut self, name: &str) -> Result<(), PatchError> {
+        self.get_mut(name)?.disable()
+    }
+
+    pub fn toggle(&mut self, name: &str) -> Result<bool, PatchError> {
+        self.get_mut(name)?.toggle()
+    }
+
+    pub fn patches(&self) -> impl Iterator<Item = &Patch> {
+        self.patches.values()
+    }
+
+    fn get_mut(&mut self, name: &str) -> Result<&mut Patch, PatchError> {
+        self.patches
+            .get_mut(name)
+            .ok_or_else(|| PatchError::NotFound(name.to_string()))
+    }
+}
+
+/// 读取前确认地址可读，无效地址返回错误而不是访问异常
+fn read_bytes(address: usize, len: usize) -> Result<Vec<u8>, PatchError> {
+    let mut buf = vec![0; len];
+    if with_memory_source(|source| {
+        source.is_readable(address, len) && source.read_bytes(address, &mut buf)
+    }) {
+        Ok(buf)
+    } else {
+        Err(PatchError::Read(address))
+    }
+}
+
+fn write_bytes(address: usize, bytes: &[u8]) -> Result<(), PatchError> {
+    with_memory_source(|source| source.write_bytes(address, bytes))
+        .map_err(|reason| PatchError::Write { address, reason })
+}
+
+fn check_bytes(address: usize, expected: &[u8]) -> Result<(), PatchError> {
+    let found = read_bytes(address, expected.len())?;
+    if found != expected {
+        return Err(PatchError::Mismatch {
+            address,
+            expected: bytes_to_space_hex(expected),
+            found: bytes_to_space_hex(&found),
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::utils::{
+        set_thread_memory_source, set_thread_region_map, MemorySource, RegionMap, SparseMemory,
+    };
+
+    #[test]
+    fn test_patch_restore() {
+        let mut memory = SparseMemory::new();
+        memory.insert(0x1000, &[0x75, 0x0D, 0x48, 0x8B, 0xC3, 0xCC, 0xCC, 0xCC]);
+        let memory = Arc::new(memory);
+        let _guard = set_thread_memory_source(memory.clone());
+        let bytes_at = |addr: usize, len: usize| {
+            let mut buf = vec![0; len];
+            assert!(memory.read_bytes(addr, &mut buf));
+            buf
+        };
+
+        assert!(matches!(
+            Patch::new("wrong", 0x1000, &[0x74, 0x0D], &[0xEB, 0x0D]),
+            Err(PatchError::Mismatch { .. })
+        ));
+
+        let mut registry = PatchRegistry::new();
+        let patch = Patch::new("jnz", 0x1000, &[0x75, 0x0D], &[0xEB, 0x0D]).unwrap();
+        registry.add(patch).unwrap();
+        assert_eq!(bytes_at(0x1000, 2), [0x75, 0x0D]);
+
+        registry.enable("jnz").unwrap();
+        assert_eq!(bytes_at(0x1000, 2), [0xEB, 0x0D]);
+        assert!(!registry.toggle("jnz").unwrap());
+        assert_eq!(bytes_at(0x1000, 2), [0x75, 0x0D]);
+
+        let overlapped = Patch::new("or", 0x1001, &[0x0D, 0x48], &[0x90, 0x90]).unwrap();
+        assert!(matches!(
+            registry.add(overlapped),
+            Err(PatchError::Overlap { .. })
+        ));
+
+        // mov rax, rbx 的前两个字节
+        assert!(matches!(
+            Patch::nop("nop", 0x1002, &[0x48, 0x8B]),
+            Err(PatchError::SplitsInstruction { len: 2, .. })
+        ));
+
+        // 释放时恢复原始字节
+        registry.enable("jnz").unwrap();
+        let mut nop = Patch::nop("nop", 0x1002, &[0x48, 0x8B, 0xC3]).unwrap();
+        nop.enable().unwrap();
+        assert_eq!(bytes_at(0x1000, 5), [0xEB, 0x0D, 0x90, 0x90, 0x90]);
+        drop(nop);
+        drop(registry.remove("jnz"));
+        assert_eq!(bytes_at(0x1000, 5), [0x75, 0x0D, 0x48, 0x8B, 0xC3]);
+    }
+
+    #[test]
+    fn test_patch_unreadable() {
+        // 使用当前进程的内存，无效地址不会被读取
+        let _guard = set_thread_region_map(Arc::new(RegionMap::from_regions([])));
+        assert!(matches!(
+            Patch::new("bad", 0xDEAD_0000, &[0x75], &[0xEB]),
+            Err(PatchError::Read(0xDEAD_0000))
+        ));
+    }
+}
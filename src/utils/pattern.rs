@@ -0,0 +1,675 @@
+use std::str::FromStr;
+
+use super::{masked_search_all, PatternScanError};
+
+/// 捕获的操作数类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CaptureKind {
+    U8,
+    I8,
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+    /// 8位相对跳转偏移
+    Rel8,
+    /// 32位相对跳转/调用偏移
+    Rel32,
+    /// 32位RIP相对寻址偏移
+    Disp32,
+}
+
+impl CaptureKind {
+    pub fn size(&self) -> usize {
+        match self {
+            CaptureKind::U8 | CaptureKind::I8 | CaptureKind::Rel8 => 1,
+            CaptureKind::U16 | CaptureKind::I16 => 2,
+            CaptureKind::U32 | CaptureKind::I32 | CaptureKind::Rel32 | CaptureKind::Disp32 => 4,
+            CaptureKind::U64 => 8,
+        }
+    }
+
+    /// 是否为相对于下一条指令地址的偏移
+    pub fn is_relative(&self) -> bool {
+        matches!(
+            self,
+            CaptureKind::Rel8 | CaptureKind::Rel32 | CaptureKind::Disp32
+        )
+    }
+
+    fn is_signed(&self) -> bool {
+        matches!(
+            self,
+            CaptureKind::I8
+                | CaptureKind::I16
+                | CaptureKind::I32
+                | CaptureKind::Rel8
+                | CaptureKind::Rel32
+                | CaptureKind::Disp32
+        )
+    }
+
+    fn read(&self, bytes: &[u8]) -> i64 {
+        let mut buf = [0_u8; 8];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        let value = u64::from_le_bytes(buf);
+        if self.is_signed() {
+            let shift = 64 - self.size() * 8;
+            ((value << shift) as i64) >> shift
+        } else {
+            value as i64
+        }
+    }
+}
+
+impl FromStr for CaptureKind {
+    type Err = PatternScanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "u8" => CaptureKind::U8,
+            "i8" => CaptureKind::I8,
+            "u16" => CaptureKind::U16,
+            "i16" => CaptureKind::I16,
+            "u32" => CaptureKind::U32,
+            "i32" => CaptureKind::I32,
+            "u64" => CaptureKind::U64,
+            "rel8" => CaptureKind::Rel8,
+            "rel32" => CaptureKind::Rel32,
+            "disp32" => CaptureKind::Disp32,
+            _ => {
+                return Err(PatternScanError::Format(format!(
+                    "unknown capture kind `{}`",
+                    s
+                )))
+            }
+        })
+    }
+}
+
+/// 匹配时捕获的操作数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capture {
+    pub name: String,
+    pub kind: CaptureKind,
+    /// 操作数相对匹配起点的偏移
+    pub offset: usize,
+    /// 所在指令结束位置相对匹配起点的偏移，即相对偏移的计算基准
+    pub end: usize,
+    /// 操作数的值，有符号类型已做符号扩展
+    pub value: i64,
+}
+
+impl Capture {
+    /// 相对偏移指向的目标地址
+    ///
+    /// `match_addr` 为匹配起点的地址，非相对偏移类型返回None
+    pub fn target(&self, match_addr: usize) -> Option<usize> {
+        if !self.kind.is_relative() {
+            return None;
+        }
+        Some(
+            match_addr
+                .wrapping_add(self.end)
+                .wrapping_add_signed(self.value as isize),
+        )
+    }
+}
+
+/// 字节切片中的一次匹配
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternHit {
+    /// 匹配起点
+    pub start: usize,
+    /// 匹配长度（包含跳过的字节）
+    pub len: usize,
+    pub captures: Vec<Capture>,
+}
+
+/// 不完全匹配中与特征码不一致的字节
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mismatch {
+    /// 相对匹配起点的偏移
+    pub offset: usize,
+    pub expected: u8,
+    /// 参与比较的位
+    pub mask: u8,
+    pub actual: u8,
+}
+
+/// 字节切片中的一次不完全匹配
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartialHit {
+    /// 匹配起点
+    pub start: usize,
+    pub mismatches: Vec<Mismatch>,
+}
+
+/// 连续的一段特征码
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Segment {
+    /// 与上一段之间跳过的字节数范围
+    gap: (usize, usize),
+    bytes: Vec<u8>,
+    mask: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CaptureSpec {
+    name: String,
+    kind: CaptureKind,
+    segment: usize,
+    /// 相对所在段起点的偏移
+    offset: usize,
+    /// 操作数之后同一条指令剩余的字节数
+    trailing: usize,
+}
+
+/// 特征码
+///
+/// 由若干段带掩码的字节组成，段与段之间可以跳过不定长度的字节。
+///
+/// 文本格式以空白分隔：
+///
+/// - `48`：字节
+/// - `??`, `**`, `?`：任意字节
+/// - `4?`, `?8`：半字节通配
+/// - `<name:kind>`：捕获操作数，按 `kind` 的长度匹配任意字节，
+///   `kind` 可选 `u8`, `i8`, `u16`, `i16`, `u32`, `i32`, `u64`, `rel8`, `rel32`, `disp32`。
+///   相对偏移默认以操作数结束处为下一条指令地址，若操作数后还有立即数，
+///   可写作 `<name:disp32+1>` 指明剩余字节数
+/// - `[n]`：跳过n个字节
+/// - `[n-m]`：跳过n到m个字节，取第一个能匹配的长度
+///
+/// ```ignore
+/// let pattern = Pattern::parse("48 8B 0D <base:disp32> E8 <call:rel32> [0-8] C6 05 <flag:disp32+1> 01")?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    segments: Vec<Segment>,
+    captures: Vec<CaptureSpec>,
+}
+
+impl Pattern {
+    /// 解析特征码文本
+    pub fn parse(text: &str) -> Result<Self, PatternScanError> {
+        let mut builder = PatternBuilder::default();
+        for token in text.split_whitespace() {
+            builder.push_token(token)?;
+        }
+        builder.finish()
+    }
+
+    /// 由字节与逐位掩码构造，掩码为1的位参与匹配
+    pub fn from_masked_bytes(bytes: &[u8], mask: &[u8]) -> Result<Self, PatternScanError> {
+        if bytes.len() != mask.len() {
+            return Err(PatternScanError::Format(format!(
+                "mask length {} does not match pattern length {}",
+                mask.len(),
+                bytes.len()
+            )));
+        }
+        Ok(Self::from_segment(bytes.to_vec(), mask.to_vec()))
+    }
+
+    /// 由字节与字符掩码构造，`x` 表示匹配，`?` 或 `.` 表示任意字节
+    ///
+    /// 例如 `("\x48\x8B\x05\x00\x00\x00\x00", "xxx????")`
+    pub fn from_code_mask(bytes: &[u8], mask: &str) -> Result<Self, PatternScanError> {
+        let mask = mask
+            .chars()
+            .map(|c| match c {
+                'x' | 'X' => Ok(0xFF),
+                '?' | '.' => Ok(0x00),
+                _ => Err(PatternScanError::Format(format!(
+                    "invalid mask character `{}`",
+                    c
+                ))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_masked_bytes(bytes, &mask)
+    }
+
+    /// 由使用通配字节的特征码构造，与 `space_hex_to_bytes` 的结果兼容
+    pub fn from_wildcard_bytes(bytes: &[u8], wildcard: u8) -> Self {
+        let mask = bytes
+            .iter()
+            .map(|&b| if b == wildcard { 0x00 } else { 0xFF })
+            .collect();
+        Self::from_segment(bytes.to_vec(), mask)
+    }
+
+    fn from_segment(bytes: Vec<u8>, mask: Vec<u8>) -> Self {
+        let segments = if bytes.is_empty() {
+            Vec::new()
+        } else {
+            vec![Segment {
+                gap: (0, 0),
+                bytes,
+                mask,
+            }]
+        };
+        Self {
+            segments,
+            captures: Vec::new(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    /// 匹配的最短长度
+    pub fn min_len(&self) -> usize {
+        self.segments.iter().map(|s| s.gap.0 + s.bytes.len()).sum()
+    }
+
+    /// 匹配的最长长度
+    pub fn max_len(&self) -> usize {
+        self.segments.iter().map(|s| s.gap.1 + s.bytes.len()).sum()
+    }
+
+    /// 所有捕获的名称
+    pub fn capture_names(&self) -> impl Iterator<Item = &str> {
+        self.captures.iter().map(|c| c.name.as_str())
+    }
+
+    /// 第一段的字节与掩码，可用于预筛选
+    pub(crate) fn prefix(&self) -> (&[u8], &[u8]) {
+        match self.segments.first() {
+            Some(segment) => (&segment.bytes, &segment.mask),
+            None => (&[], &[]),
+        }
+    }
+
+    /// 检查 `pos` 处是否匹配
+    pub fn match_at(&self, text: &[u8], pos: usize) -> Option<PatternHit> {
+        if self.segments.is_empty() {
+            return None;
+        }
+        let mut starts = Vec::with_capacity(self.segments.len());
+        let end = self.match_segments(text, 0, pos, &mut starts)?;
+        let captures = self
+            .captures
+            .iter()
+            .map(|spec| {
+                let offset = starts[spec.segment] + spec.offset - pos;
+                let bytes = &text[pos + offset..pos + offset + spec.kind.size()];
+                Capture {
+                    name: spec.name.clone(),
+                    kind: spec.kind,
+                    offset,
+                    end: offset + spec.kind.size() + spec.trailing,
+                    value: spec.kind.read(bytes),
+                }
+            })
+            .collect();
+
+        Some(PatternHit {
+            start: pos,
+            len: end - pos,
+            captures,
+        })
+    }
+
+    /// 查找所有匹配
+    pub fn find_all(&self, text: &[u8]) -> Vec<PatternHit> {
+        self.find_in_range(text, 0, text.len())
+    }
+
+    /// 查找第一个匹配
+    pub fn find_first(&self, text: &[u8]) -> Option<PatternHit> {
+        let (bytes, mask) = self.prefix();
+        let mut pos = 0;
+        while pos < text.len() {
+            let found = super::masked_search_first(&text[pos..], bytes, mask)?;
+            if let Some(hit) = self.match_at(text, pos + found) {
+                return Some(hit);
+            }
+            pos += found + 1;
+        }
+        None
+    }
+
+    /// 查找起点位于 `[start, end)` 的所有匹配，匹配可以延伸到 `end` 之后
+    pub fn find_in_range(&self, text: &[u8], start: usize, end: usize) -> Vec<PatternHit> {
+        let (bytes, mask) = self.prefix();
+        if bytes.is_empty() || start >= end {
+            return Vec::new();
+        }
+        let search_end = (end + bytes.len() - 1).min(text.len());
+        masked_search_all(&text[start..search_end], bytes, mask)
+            .into_iter()
+            .filter_map(|pos| self.match_at(text, start + pos))
+            .collect()
+    }
+
+    /// `pos` 处与特征码不一致的字节，超过 `max_mismatches` 个或越界时返回None
+    ///
+    /// 不定长跳过按最短长度计算。
+    pub fn mismatches_at(
+        &self,
+        text: &[u8],
+        pos: usize,
+        max_mismatches: usize,
+    ) -> Option<Vec<Mismatch>> {
+        if self.segments.is_empty() || pos + self.min_len() > text.len() {
+            return None;
+        }
+        let mut mismatches = Vec::new();
+        let mut offset = 0;
+        for segment in self.segments.iter() {
+            offset += segment.gap.0;
+            for (i, (&b, &m)) in segment.bytes.iter().zip(segment.mask.iter()).enumerate() {
+                let actual = text[pos + offset + i];
+                if actual & m != b & m {
+                    if mismatches.len() == max_mismatches {
+                        return None;
+                    }
+                    mismatches.push(Mismatch {
+                        offset: offset + i,
+                        expected: b,
+                        mask: m,
+                        actual,
+                    });
+                }
+            }
+            offset += segment.bytes.len();
+        }
+        Some(mismatches)
+    }
+
+    /// 查找不一致字节数不超过 `max_mismatches` 的位置，用于定位失效的特征码
+    ///
+    /// 按不一致字节数从少到多排列，最多返回 `limit` 个。
+    pub fn find_partial(
+        &self,
+        text: &[u8],
+        max_mismatches: usize,
+        limit: usize,
+    ) -> Vec<PartialHit> {
+        let mut result: Vec<PartialHit> = Vec::new();
+        if limit == 0 || text.len() < self.min_len() {
+            return result;
+        }
+        let mut max_mismatches = max_mismatches;
+        for pos in 0..=text.len() - self.min_len() {
+            let Some(mismatches) = self.mismatches_at(text, pos, max_mismatches) else {
+                continue;
+            };
+            let index = result.partition_point(|h| h.mismatches.len() <= mismatches.len());
+            result.insert(
+                index,
+                PartialHit {
+                    start: pos,
+                    mismatches,
+                },
+            );
+            if result.len() > limit {
+                result.pop();
+            }
+            // 已满时只需要查找更接近的位置
+            if result.len() == limit {
+                match result.last().unwrap().mismatches.len() {
+                    0 => break,
+                    worst => max_mismatches = max_mismatches.min(worst - 1),
+                }
+            }
+        }
+        result
+    }
+
+    /// 从第 `index` 段开始匹配，返回匹配结束位置
+    ///
+    /// 对不定长跳过，按长度从小到大尝试，返回第一个使后续所有段都能匹配的结果。
+    fn match_segments(
+        &self,
+        text: &[u8],
+        index: usize,
+        pos: usize,
+        starts: &mut Vec<usize>,
+    ) -> Option<usize> {
+        let Some(segment) = self.segments.get(index) else {
+            return Some(pos);
+        };
+        for gap in segment.gap.0..=segment.gap.1 {
+            let start = pos + gap;
+            if start + segment.bytes.len() > text.len() {
+                break;
+            }
+            if !segment_matches(segment, &text[start..start + segment.bytes.len()]) {
+                continue;
+            }
+            starts.push(start);
+            if let Some(end) =
+                self.match_segments(text, index + 1, start + segment.bytes.len(), starts)
+            {
+                return Some(end);
+            }
+            starts.pop();
+        }
+        None
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = PatternScanError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+fn segment_matches(segment: &Segment, text: &[u8]) -> bool {
+    segment
+        .bytes
+        .iter()
+        .zip(segment.mask.iter())
+        .zip(text.iter())
+        .all(|((&b, &m), &t)| t & m == b & m)
+}
+
+#[derive(Default)]
+struct PatternBuilder {
+    segments: Vec<Segment>,
+    captures: Vec<CaptureSpec>,
+    /// 尚未归入任何段的跳过范围
+    pending_gap: Option<(usize, usize)>,
+}
+
+impl PatternBuilder {
+    fn push_token(&mut self, token: &str) -> Result<(), PatternScanError> {
+        if let Some(body) = token.strip_prefix('<') {
+            let body = body
+                .strip_suffix('>')
+                .ok_or_else(|| format_error(token, "unclosed capture"))?;
+            return self.push_capture(token, body);
+        }
+        if let Some(body) = token.strip_prefix('[') {
+            let body = body
+                .strip_suffix(']')
+                .ok_or_else(|| format_error(token, "unclosed skip"))?;
+            let (min, max) = match body.split_once('-') {
+                Some((min, max)) => (parse_count(token, min)?, parse_count(token, max)?),
+                None => {
+                    let n = parse_count(token, body)?;
+                    (n, n)
+                }
+            };
+            if min > max {
+                return Err(format_error(token, "invalid skip range"));
+            }
+            if min == max {
+                self.push_bytes(&vec![0; min], &vec![0; min]);
+            } else {
+                let gap = self.pending_gap.get_or_insert((0, 0));
+                gap.0 += min;
+                gap.1 += max;
+            }
+            return Ok(());
+        }
+
+        let (byte, mask) = parse_byte(token)?;
+        self.push_bytes(&[byte], &[mask]);
+        Ok(())
+    }
+
+    fn push_capture(&mut self, token: &str, body: &str) -> Result<(), PatternScanError> {
+        let (name, kind) = body
+            .split_once(':')
+            .ok_or_else(|| format_error(token, "expected `<name:kind>`"))?;
+        if name.is_empty() {
+            return Err(format_error(token, "empty capture name"));
+        }
+        if self.captures.iter().any(|c| c.name == name) {
+            return Err(format_error(token, "duplicate capture name"));
+        }
+        let (kind, trailing) = match kind.split_once('+') {
+            Some((kind, trailing)) => (kind, parse_count(token, trailing)?),
+            None => (kind, 0),
+        };
+        let kind: CaptureKind = kind.parse()?;
+        if trailing != 0 && !kind.is_relative() {
+            return Err(format_error(token, "only relative captures accept `+n`"));
+        }
+
+        let size = kind.size();
+        self.push_bytes(&vec![0; size], &vec![0; size]);
+        let segment = self.segments.len() - 1;
+        self.captures.push(CaptureSpec {
+            name: name.to_string(),
+            kind,
+            segment,
+            offset: self.segments[segment].bytes.len() - size,
+            trailing,
+        });
+        Ok(())
+    }
+
+    fn push_bytes(&mut self, bytes: &[u8], mask: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+        if self.segments.is_empty() || self.pending_gap.is_some() {
+            self.segments.push(Segment {
+                gap: self.pending_gap.take().unwrap_or((0, 0)),
+                bytes: Vec::new(),
+                mask: Vec::new(),
+            });
+        }
+        let segment = self.segments.last_mut().unwrap();
+        segment.bytes.extend_from_slice(bytes);
+        segment.mask.extend_from_slice(mask);
+    }
+
+    fn finish(self) -> Result<Pattern, PatternScanError> {
+        if self.pending_gap.is_some() {
+            return Err(PatternScanError::Format(
+                "pattern cannot end with a variable skip".to_string(),
+            ));
+        }
+        if self.segments.first().is_some_and(|s| s.gap != (0, 0)) {
+            return Err(PatternScanError::Format(
+                "pattern cannot start with a variable skip".to_string(),
+            ));
+        }
+        Ok(Pattern {
+            segments: self.segments,
+            captures: self.captures,
+        })
+    }
+}
+
+fn parse_byte(token: &str) -> Result<(u8, u8), PatternScanError> {
+    if token == "??" || token == "**" || token == "?" {
+        return Ok((0x00, 0x00));
+    }
+    let chars: Vec<char> = token.chars().collect();
+    if chars.len() != 2 {
+        return Err(format_error(token, "expected a hex byte"));
+    }
+    let mut byte = 0;
+    let mut mask = 0;
+    for c in chars {
+        byte <<= 4;
+        mask <<= 4;
+        match c {
+            '?' | '*' => {}
+            _ => {
+                let nibble = c
+                    .to_digit(16)
+                    .ok_or_else(|| format_error(token, "expected a hex byte"))?;
+                byte |= nibble as u8;
+                mask |= 0xF;
+            }
+        }
+    }
+    Ok((byte, mask))
+}
+
+fn parse_count(token: &str, text: &str) -> Result<usize, PatternScanError> {
+    text.parse()
+        .map_err(|_| format_error(token, "expected a decimal number"))
+}
+
+fn format_error(token: &str, reason: &str) -> PatternScanError {
+    PatternScanError::Format(format!("{} in `{}`", reason, token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_pattern() {
+        let pattern = Pattern::parse("48 8B 4? ?5 FF [2] E8 <call:rel32> [1-4] C3").unwrap();
+        assert_eq!(pattern.min_len(), 14);
+        assert_eq!(pattern.max_len(), 17);
+        assert_eq!(pattern.capture_names().collect::<Vec<_>>(), ["call"]);
+        let (bytes, mask) = pattern.prefix();
+        assert_eq!(
+            bytes,
+            [0x48, 0x8B, 0x40, 0x05, 0xFF, 0, 0, 0xE8, 0, 0, 0, 0]
+        );
+        assert_eq!(mask, [0xFF, 0xFF, 0xF0, 0x0F, 0xFF, 0, 0, 0xFF, 0, 0, 0, 0]);
+
+        assert!(Pattern::parse("").unwrap().is_empty());
+        assert!(Pattern::parse("48 GG").is_err());
+        assert!(Pattern::parse("[1-2] 48").is_err());
+        assert!(Pattern::parse("48 [1-2]").is_err());
+        assert!(Pattern::parse("<a:u8> <a:u8>").is_err());
+        assert!(Pattern::parse("<a:u32+1>").is_err());
+    }
+
+    #[test]
+    fn test_match_captures() {
+        #[rustfmt::skip]
+        let text = [
+            0x90, 0x90,
+            0x48, 0x8B, 0x0D, 0x10, 0x00, 0x00, 0x00, // mov rcx, [rip+0x10]
+            0x90,
+            0xC6, 0x05, 0xF0, 0xFF, 0xFF, 0xFF, 0x01, // mov byte ptr [rip-0x10], 1
+            0xFF,
+        ];
+        let pattern =
+            Pattern::parse("48 8B 0D <base:disp32> [0-3] C6 05 <flag:disp32+1> 01 FF").unwrap();
+
+        let hits = pattern.find_all(&text);
+        assert_eq!(hits.len(), 1);
+        let hit = &hits[0];
+        assert_eq!((hit.start, hit.len), (2, 16));
+        assert_eq!(hit.captures[0].value, 0x10);
+        assert_eq!(hit.captures[0].target(0x1000), Some(0x1000 + 7 + 0x10));
+        assert_eq!(hit.captures[1].offset, 10);
+        assert_eq!(hit.captures[1].value, -0x10);
+        assert_eq!(hit.captures[1].target(0x1000), Some(0x1000 + 15 - 0x10));
+        assert_eq!(pattern.find_first(&text), Some(hit.clone()));
+
+        // 字面量0xFF不再被当作通配符
+        let literal = Pattern::parse("90 FF").unwrap();
+        assert!(literal.find_all(&text).is_empty());
+        let masked = Pattern::from_code_mask(&[0x90, 0xC6], "x?").unwrap();
+        assert_eq!(masked.find_all(&text).len(), 3);
+    }
+}
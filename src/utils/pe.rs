@@ -0,0 +1,518 @@
+use std::{fs, ops::Range, path::Path, slice};
+
+use thiserror::Error;
+use windows::{
+    core::PCWSTR,
+    Win32::{
+        Foundation::HMODULE,
+        System::LibraryLoader::{GetModuleFileNameW, GetModuleHandleW},
+    },
+};
+
+const IMAGE_DOS_SIGNATURE: u16 = 0x5A4D; // MZ
+const IMAGE_NT_SIGNATURE: u32 = 0x00004550; // PE\0\0
+const IMAGE_NT_OPTIONAL_HDR32_MAGIC: u16 = 0x10B;
+const IMAGE_NT_OPTIONAL_HDR64_MAGIC: u16 = 0x20B;
+const IMAGE_SIZEOF_FILE_HEADER: usize = 20;
+const IMAGE_SIZEOF_SECTION_HEADER: usize = 40;
+
+pub const IMAGE_SCN_CNT_CODE: u32 = 0x0000_0020;
+pub const IMAGE_SCN_MEM_EXECUTE: u32 = 0x2000_0000;
+
+/// 已加载模块的头部页大小
+const HEADER_PAGE_SIZE: usize = 0x1000;
+
+#[derive(Debug, Error)]
+pub enum PeError {
+    #[error("invalid DOS header")]
+    InvalidDosHeader,
+    #[error("invalid NT header")]
+    InvalidNtHeader,
+    #[error("unsupported optional header magic 0x{0:X}")]
+    UnsupportedMagic(u16),
+    #[error("section table out of bounds")]
+    SectionTableOutOfBounds,
+    #[error("module not found: {0}")]
+    ModuleNotFound(String),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// `std::io::Error` 不能克隆，复制时保留错误类型与信息
+impl Clone for PeError {
+    fn clone(&self) -> Self {
+        match self {
+            PeError::InvalidDosHeader => PeError::InvalidDosHeader,
+            PeError::InvalidNtHeader => PeError::InvalidNtHeader,
+            PeError::UnsupportedMagic(magic) => PeError::UnsupportedMagic(*magic),
+            PeError::SectionTableOutOfBounds => PeError::SectionTableOutOfBounds,
+            PeError::ModuleNotFound(name) => PeError::ModuleNotFound(name.clone()),
+            PeError::Io(e) => PeError::Io(std::io::Error::new(e.kind(), e.to_string())),
+        }
+    }
+}
+
+/// PE节信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    pub name: String,
+    pub virtual_address: u32,
+    pub virtual_size: u32,
+    pub raw_offset: u32,
+    pub raw_size: u32,
+    pub characteristics: u32,
+}
+
+impl Section {
+    /// 节是否可执行
+    pub fn is_executable(&self) -> bool {
+        self.characteristics & (IMAGE_SCN_MEM_EXECUTE | IMAGE_SCN_CNT_CODE) != 0
+    }
+
+    /// 节在内存中的大小
+    pub fn mapped_size(&self) -> u32 {
+        if self.virtual_size == 0 {
+            self.raw_size
+        } else {
+            self.virtual_size
+        }
+    }
+
+    pub fn contains_rva(&self, rva: u32) -> bool {
+        (self.virtual_address..self.virtual_address + self.mapped_size()).contains(&rva)
+    }
+}
+
+/// PE头部中扫描需要的信息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeHeaders {
+    pub image_base: u64,
+    pub size_of_image: u32,
+    pub size_of_headers: u32,
+    pub time_date_stamp: u32,
+    pub check_sum: u32,
+    pub sections: Vec<Section>,
+}
+
+impl PeHeaders {
+    /// 从字节数组解析PE头部
+    ///
+    /// 字节数组需从DOS头开始，且至少包含完整的节表。
+    pub fn parse(bytes: &[u8]) -> Result<Self, PeError> {
+        if read_u16(bytes, 0) != Some(IMAGE_DOS_SIGNATURE) {
+            return Err(PeError::InvalidDosHeader);
+        }
+        let nt_offset = read_u32(bytes, 0x3C).ok_or(PeError::InvalidDosHeader)? as usize;
+        if read_u32(bytes, nt_offset) != Some(IMAGE_NT_SIGNATURE) {
+            return Err(PeError::InvalidNtHeader);
+        }
+
+        let file_header = nt_offset + 4;
+        let section_count = read_u16(bytes, file_header + 2).ok_or(PeError::InvalidNtHeader)?;
+        let time_date_stamp = read_u32(bytes, file_header + 4).ok_or(PeError::InvalidNtHeader)?;
+        let optional_size = read_u16(bytes, file_header + 16).ok_or(PeError::InvalidNtHeader)?;
+
+        let optional_header = file_header + IMAGE_SIZEOF_FILE_HEADER;
+        let magic = read_u16(bytes, optional_header).ok_or(PeError::InvalidNtHeader)?;
+        let image_base = match magic {
+            IMAGE_NT_OPTIONAL_HDR64_MAGIC => read_u64(bytes, optional_header + 24),
+            IMAGE_NT_OPTIONAL_HDR32_MAGIC => {
+                read_u32(bytes, optional_header + 28).map(|base| base as u64)
+            }
+            other => return Err(PeError::UnsupportedMagic(other)),
+        }
+        .ok_or(PeError::InvalidNtHeader)?;
+        let size_of_image =
+            read_u32(bytes, optional_header + 56).ok_or(PeError::InvalidNtHeader)?;
+        let size_of_headers =
+            read_u32(bytes, optional_header + 60).ok_or(PeError::InvalidNtHeader)?;
+        let check_sum = read_u32(bytes, optional_header + 64).ok_or(PeError::InvalidNtHeader)?;
+
+        let section_table = optional_header + optional_size as usize;
+        let sections = (0..section_count as usize)
+            .map(|index| {
+                let header = section_table + index * IMAGE_SIZEOF_SECTION_HEADER;
+                parse_section(bytes, header).ok_or(PeError::SectionTableOutOfBounds)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            image_base,
+            size_of_image,
+            size_of_headers,
+            time_date_stamp,
+            check_sum,
+            sections,
+        })
+    }
+}
+
+/// `OptionalHeader.ImageBase` 字段在PE头部中的位置
+///
+/// 模块无法加载到首选基址时，加载器会改写该字段为实际基址。
+pub(crate) fn image_base_field(bytes: &[u8]) -> Option<Range<usize>> {
+    let nt_offset = read_u32(bytes, 0x3C)? as usize;
+    let optional_header = nt_offset + 4 + IMAGE_SIZEOF_FILE_HEADER;
+    match read_u16(bytes, optional_header)? {
+        IMAGE_NT_OPTIONAL_HDR64_MAGIC => Some(optional_header + 24..optional_header + 32),
+        IMAGE_NT_OPTIONAL_HDR32_MAGIC => Some(optional_header + 28..optional_header + 32),
+        _ => None,
+    }
+}
+
+/// 扫描时选择的节
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionFilter<'a> {
+    /// 所有可执行节
+    Executable,
+    /// 指定名称的节，例如 `.text`
+    Named(&'a str),
+    /// 所有节
+    All,
+}
+
+impl SectionFilter<'_> {
+    pub fn matches(&self, section: &Section) -> bool {
+        match self {
+            SectionFilter::Executable => section.is_executable(),
+            SectionFilter::Named(name) => section.name == *name,
+            SectionFilter::All => true,
+        }
+    }
+}
+
+/// PE模块镜像
+///
+/// 可以是当前进程中已加载的模块，也可以是从磁盘读取并按节映射的可执行文件。
+#[derive(Debug, Clone)]
+pub struct PeImage {
+    name: String,
+    base: usize,
+    headers: PeHeaders,
+    data: ImageData,
+}
+
+#[derive(Debug, Clone)]
+enum ImageData {
+    /// 已加载到当前进程，直接访问 `base` 处的内存
+    Loaded,
+    /// 从文件映射的镜像，按虚拟地址布局存放
+    Mapped(Vec<u8>),
+}
+
+impl PeImage {
+    /// 获取游戏主模块
+    pub fn main_module() -> Result<Self, PeError> {
+        let module = unsafe { GetModuleHandleW(PCWSTR::null()) }
+            .map_err(|_| PeError::ModuleNotFound("<main>".to_string()))?;
+        let name = module_file_name(module).unwrap_or_default();
+        unsafe { Self::from_base(&name, module.0 as usize) }
+    }
+
+    /// 按名称获取已加载的模块，例如 `MonsterHunterWorld.exe`
+    pub fn find_module(name: &str) -> Result<Self, PeError> {
+        unsafe { Self::from_base(name, module_base(name)?) }
+    }
+
+    /// 从模块基址解析已加载的模块
+    ///
+    /// # Safety
+    ///
+    /// `base` 必须指向当前进程中已加载模块的起始地址。
+    pub unsafe fn from_base(name: &str, base: usize) -> Result<Self, PeError> {
+        let header_page = slice::from_raw_parts(base as *const u8, HEADER_PAGE_SIZE);
+        let headers = PeHeaders::parse(header_page)?;
+
+        Ok(Self {
+            name: name.to_string(),
+            base,
+            headers,
+            data: ImageData::Loaded,
+        })
+    }
+
+    /// 从磁盘读取可执行文件，并按节映射为内存布局
+    ///
+    /// 基址使用PE头中的首选基址（ImageBase）
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, PeError> {
+        let path = path.as_ref();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let bytes = fs::read(path)?;
+        Self::from_file_bytes(&name, &bytes)
+    }
+
+    /// 将可执行文件的内容按节映射为内存布局
+    pub fn from_file_bytes(name: &str, bytes: &[u8]) -> Result<Self, PeError> {
+        let headers = PeHeaders::parse(bytes)?;
+        let mut mapped = vec![0_u8; headers.size_of_image as usize];
+
+        let header_len = (headers.size_of_headers as usize)
+            .min(bytes.len())
+            .min(mapped.len());
+        mapped[..header_len].copy_from_slice(&bytes[..header_len]);
+        for section in headers.sections.iter() {
+            let raw_start = section.raw_offset as usize;
+            let raw_len = section.raw_size.min(section.mapped_size()) as usize;
+            let raw_end = (raw_start + raw_len).min(bytes.len());
+            let dst_start = section.virtual_address as usize;
+            if raw_start >= raw_end || dst_start >= mapped.len() {
+                continue;
+            }
+            let len = (raw_end - raw_start).min(mapped.len() - dst_start);
+            mapped[dst_start..dst_start + len].copy_from_slice(&bytes[raw_start..raw_start + len]);
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            base: headers.image_base as usize,
+            headers,
+            data: ImageData::Mapped(mapped),
+        })
+    }
+
+    /// 镜像是否从文件映射
+    pub fn is_mapped(&self) -> bool {
+        matches!(self.data, ImageData::Mapped(_))
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    pub fn size(&self) -> usize {
+        self.headers.size_of_image as usize
+    }
+
+    pub fn headers(&self) -> &PeHeaders {
+        &self.headers
+    }
+
+    pub fn sections(&self) -> &[Section] {
+        &self.headers.sections
+    }
+
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections().iter().find(|section| section.name == name)
+    }
+
+    /// 节在内存中的字节
+    pub fn section_bytes(&self, section: &Section) -> &[u8] {
+        let start = section.virtual_address as usize;
+        let len = section.mapped_size() as usize;
+        match &self.data {
+            ImageData::Loaded => unsafe {
+                slice::from_raw_parts((self.base + start) as *const u8, len)
+            },
+            ImageData::Mapped(mapped) => {
+                let start = start.min(mapped.len());
+                let end = (start + len).min(mapped.len());
+                &mapped[start..end]
+            }
+        }
+    }
+
+    /// 镜像中 `[rva, rva + len)` 的字节，超出镜像范围时返回None
+    pub fn bytes_at(&self, rva: usize, len: usize) -> Option<&[u8]> {
+        if rva.checked_add(len)? > self.size() {
+            return None;
+        }
+        match &self.data {
+            ImageData::Loaded => unsafe {
+                Some(slice::from_raw_parts((self.base + rva) as *const u8, len))
+            },
+            ImageData::Mapped(mapped) => mapped.get(rva..rva + len),
+        }
+    }
+
+    pub fn rva_to_va(&self, rva: usize) -> usize {
+        self.base + rva
+    }
+
+    pub fn va_to_rva(&self, va: usize) -> Option<usize> {
+        va.checked_sub(self.base).filter(|rva| *rva < self.size())
+    }
+
+    /// 文件偏移转换为RVA，偏移不属于任何节时返回None
+    pub fn file_offset_to_rva(&self, offset: usize) -> Option<usize> {
+        if offset < self.headers.size_of_headers as usize {
+            return Some(offset);
+        }
+        self.sections().iter().find_map(|section| {
+            let raw_start = section.raw_offset as usize;
+            let raw_len = section.raw_size.min(section.mapped_size()) as usize;
+            (raw_start..raw_start + raw_len)
+                .contains(&offset)
+                .then(|| section.virtual_address as usize + (offset - raw_start))
+        })
+    }
+
+    /// RVA转换为文件偏移，RVA位于未初始化数据中时返回None
+    pub fn rva_to_file_offset(&self, rva: usize) -> Option<usize> {
+        if rva < self.headers.size_of_headers as usize {
+            return Some(rva);
+        }
+        self.sections().iter().find_map(|section| {
+            let start = section.virtual_address as usize;
+            let raw_len = section.raw_size.min(section.mapped_size()) as usize;
+            (start..start + raw_len)
+                .contains(&rva)
+                .then(|| section.raw_offset as usize + (rva - start))
+        })
+    }
+
+    /// 文件偏移转换为虚拟地址
+    pub fn file_offset_to_va(&self, offset: usize) -> Option<usize> {
+        self.file_offset_to_rva(offset)
+            .map(|rva| self.rva_to_va(rva))
+    }
+}
+
+/// 按名称获取已加载模块的基址，例如 `MonsterHunterWorld.exe`
+pub fn module_base(name: &str) -> Result<usize, PeError> {
+    let wide_name: Vec<u16> = name.encode_utf16().chain(std::iter::once(0)).collect();
+    let module = unsafe { GetModuleHandleW(PCWSTR(wide_name.as_ptr())) }
+        .map_err(|_| PeError::ModuleNotFound(name.to_string()))?;
+    Ok(module.0 as usize)
+}
+
+fn module_file_name(module: HMODULE) -> Option<String> {
+    let mut buffer = [0_u16; 260];
+    let len = unsafe { GetModuleFileNameW(module, &mut buffer) } as usize;
+    if len == 0 {
+        return None;
+    }
+    let path = String::from_utf16_lossy(&buffer[..len]);
+    path.rsplit(['\\', '/']).next().map(|name| name.to_string())
+}
+
+fn parse_section(bytes: &[u8], header: usize) -> Option<Section> {
+    let raw_name = bytes.get(header..header + 8)?;
+    let name_len = raw_name.iter().position(|&b| b == 0).unwrap_or(8);
+
+    Some(Section {
+        name: String::from_utf8_lossy(&raw_name[..name_len]).to_string(),
+        virtual_size: read_u32(bytes, header + 8)?,
+        virtual_address: read_u32(bytes, header + 12)?,
+        raw_size: read_u32(bytes, header + 16)?,
+        raw_offset: read_u32(bytes, header + 20)?,
+        characteristics: read_u32(bytes, header + 36)?,
+    })
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u64(bytes: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_le_bytes(
+        bytes.get(offset..offset + 8)?.try_into().ok()?,
+    ))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// 构造一个最小的PE32+头部
+    pub(crate) fn build_headers(sections: &[(&str, u32, u32, u32, u32, u32)]) -> Vec<u8> {
+        let mut bytes = vec![0_u8; 0x400];
+        bytes[0..2].copy_from_slice(&IMAGE_DOS_SIGNATURE.to_le_bytes());
+        bytes[0x3C..0x40].copy_from_slice(&0x80_u32.to_le_bytes());
+        bytes[0x80..0x84].copy_from_slice(&IMAGE_NT_SIGNATURE.to_le_bytes());
+        // FileHeader
+        bytes[0x86..0x88].copy_from_slice(&(sections.len() as u16).to_le_bytes());
+        bytes[0x94..0x96].copy_from_slice(&0xF0_u16.to_le_bytes());
+        // OptionalHeader
+        let opt = 0x98;
+        bytes[opt..opt + 2].copy_from_slice(&IMAGE_NT_OPTIONAL_HDR64_MAGIC.to_le_bytes());
+        bytes[opt + 24..opt + 32].copy_from_slice(&0x140000000_u64.to_le_bytes());
+        let size_of_image = sections
+            .iter()
+            .map(|(_, rva, vsize, ..)| rva + vsize)
+            .max()
+            .unwrap_or(0x1000);
+        bytes[opt + 56..opt + 60].copy_from_slice(&size_of_image.to_le_bytes());
+        bytes[opt + 60..opt + 64].copy_from_slice(&0x400_u32.to_le_bytes());
+        // Sections
+        for (index, (name, rva, vsize, raw_offset, raw_size, flags)) in sections.iter().enumerate()
+        {
+            let header = opt + 0xF0 + index * IMAGE_SIZEOF_SECTION_HEADER;
+            bytes[header..header + name.len()].copy_from_slice(name.as_bytes());
+            bytes[header + 8..header + 12].copy_from_slice(&vsize.to_le_bytes());
+            bytes[header + 12..header + 16].copy_from_slice(&rva.to_le_bytes());
+            bytes[header + 16..header + 20].copy_from_slice(&raw_size.to_le_bytes());
+            bytes[header + 20..header + 24].copy_from_slice(&raw_offset.to_le_bytes());
+            bytes[header + 36..header + 40].copy_from_slice(&flags.to_le_bytes());
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_parse_headers() {
+        let bytes = build_headers(&[
+            (".text", 0x1000, 0x2000, 0x400, 0x2000, 0x6000_0020),
+            (".rdata", 0x3000, 0x800, 0x2400, 0x800, 0x4000_0040),
+        ]);
+        let headers = PeHeaders::parse(&bytes).unwrap();
+        assert_eq!(headers.image_base, 0x140000000);
+        assert_eq!(headers.size_of_image, 0x3800);
+        assert_eq!(headers.sections.len(), 2);
+        assert_eq!(headers.sections[0].name, ".text");
+        assert!(headers.sections[0].is_executable());
+        assert!(!headers.sections[1].is_executable());
+        assert!(headers.sections[1].contains_rva(0x37FF));
+        assert!(SectionFilter::Named(".rdata").matches(&headers.sections[1]));
+
+        assert!(matches!(
+            PeHeaders::parse(&bytes[0x40..]),
+            Err(PeError::InvalidDosHeader)
+        ));
+    }
+
+    #[test]
+    fn test_map_file() {
+        let mut file = build_headers(&[
+            (".text", 0x1000, 0x300, 0x400, 0x200, 0x6000_0020),
+            (".data", 0x2000, 0x100, 0x600, 0x100, 0xC000_0040),
+        ]);
+        file.resize(0x700, 0);
+        file[0x410..0x414].copy_from_slice(&[0xE8, 0x11, 0x22, 0x33]);
+        file[0x600] = 0xAB;
+
+        let image = PeImage::from_file_bytes("test.exe", &file).unwrap();
+        assert!(image.is_mapped());
+        assert_eq!(image.base(), 0x140000000);
+
+        let text = image.section(".text").unwrap();
+        let text_bytes = image.section_bytes(text);
+        // 虚拟大小大于文件大小的部分补零
+        assert_eq!(text_bytes.len(), 0x300);
+        assert_eq!(&text_bytes[0x10..0x14], &[0xE8, 0x11, 0x22, 0x33]);
+        assert_eq!(text_bytes[0x250], 0);
+        assert_eq!(
+            image.section_bytes(image.section(".data").unwrap())[0],
+            0xAB
+        );
+
+        assert_eq!(image.file_offset_to_rva(0x410), Some(0x1010));
+        assert_eq!(image.file_offset_to_va(0x600), Some(0x140002000));
+        assert_eq!(image.rva_to_file_offset(0x1010), Some(0x410));
+        // 未初始化数据没有对应的文件偏移
+        assert_eq!(image.rva_to_file_offset(0x1250), None);
+    }
+}
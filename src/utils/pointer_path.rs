@@ -0,0 +1,259 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+use super::{
+    module_base, resolve_offsets, try_resolve_offsets, with_memory_source, MemoryError, PeError,
+};
+
+/// 路径各部分之间的分隔符
+const PATH_SEPARATOR: &str = "->";
+
+#[derive(Debug, Error)]
+pub enum PointerPathError {
+    #[error("invalid pointer path `{path}`: {reason}")]
+    Parse { path: String, reason: String },
+    #[error(transparent)]
+    Module(#[from] PeError),
+    #[error(transparent)]
+    Memory(#[from] MemoryError),
+}
+
+/// 指针路径的起点
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum PathBase {
+    /// 绝对地址，例如 `0x1450139A0`
+    Absolute(usize),
+    /// 模块基址加偏移，例如 `MonsterHunterWorld.exe+0x50139A0`
+    Module { name: String, offset: usize },
+}
+
+impl PathBase {
+    /// 起点的绝对地址，模块起点需要查询模块基址
+    pub fn address(&self) -> Result<usize, PeError> {
+        match self {
+            PathBase::Absolute(addr) => Ok(*addr),
+            PathBase::Module { name, offset } => Ok(module_base(name)? + offset),
+        }
+    }
+}
+
+/// 多级指针路径
+///
+/// 与CE中的指针记录一致：从起点开始，每一级先取值，再加偏移。
+/// 可以从字符串解析，并以相同的格式输出与序列化，便于将偏移写入配置文件。
+///
+/// ```ignore
+/// let path: PointerPath = "MonsterHunterWorld.exe+0x50139A0 -> 0x50 -> 0xC0".parse()?;
+/// let value: i32 = path.try_read()?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PointerPath {
+    base: PathBase,
+    offsets: Vec<isize>,
+}
+
+impl PointerPath {
+    pub fn new(base: PathBase, offsets: &[isize]) -> Self {
+        Self {
+            base,
+            offsets: offsets.to_vec(),
+        }
+    }
+
+    /// 由绝对地址与偏移创建，例如 `game_export` 中的常量
+    pub fn absolute<T>(base: *const T, offsets: &[isize]) -> Self {
+        Self::new(PathBase::Absolute(base as usize), offsets)
+    }
+
+    /// 由模块基址的偏移创建
+    pub fn module(name: &str, offset: usize, offsets: &[isize]) -> Self {
+        Self::new(
+            PathBase::Module {
+                name: name.to_string(),
+                offset,
+            },
+            offsets,
+        )
+    }
+
+    pub fn base(&self) -> &PathBase {
+        &self.base
+    }
+
+    pub fn offsets(&self) -> &[isize] {
+        &self.offsets
+    }
+
+    /// 在末尾追加一级偏移
+    pub fn join(mut self, offset: isize) -> Self {
+        self.offsets.push(offset);
+        self
+    }
+
+    /// 解析最终地址，任一级取值失败或为空指针时返回None
+    ///
+    /// 取值方式与 [`get_ptr_with_offset`](super::get_ptr_with_offset) 一致
+    pub fn resolve(&self) -> Option<usize> {
+        let base = self.base.address().ok()?;
+        with_memory_source(|source| resolve_offsets(source, base, &self.offsets))
+    }
+
+    /// 解析最终地址，每一级取值前确认地址可读
+    pub fn try_resolve(&self) -> Result<usize, PointerPathError> {
+        let base = self.base.address()?;
+        Ok(with_memory_source(|source| {
+            try_resolve_offsets(source, base, &self.offsets)
+        })?)
+    }
+
+    /// 读取最终地址的值（的副本），每一级取值前确认地址可读
+    pub fn try_read<T: Copy>(&self) -> Result<T, PointerPathError> {
+        let addr = self.try_resolve()?;
+        Ok(with_memory_source(|source| source.try_read_value(addr))?)
+    }
+}
+
+impl fmt::Display for PointerPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.base {
+            PathBase::Absolute(addr) => write!(f, "0x{:X}", addr)?,
+            PathBase::Module { name, offset } => write!(f, "{}+0x{:X}", name, offset)?,
+        }
+        for &offset in self.offsets.iter() {
+            if offset < 0 {
+                write!(f, " {} -0x{:X}", PATH_SEPARATOR, offset.unsigned_abs())?;
+            } else {
+                write!(f, " {} 0x{:X}", PATH_SEPARATOR, offset)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PointerPath {
+    type Err = PointerPathError;
+
+    /// 解析 `0x1450139A0 -> 0x50 -> 0xC0` 或 `MonsterHunterWorld.exe+0x50139A0 -> 0x50` 格式的路径
+    ///
+    /// 数值均按十六进制解析，`0x` 前缀可省略，偏移可以为负数。
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |reason: String| PointerPathError::Parse {
+            path: s.to_string(),
+            reason,
+        };
+
+        let mut parts = s.split(PATH_SEPARATOR).map(str::trim);
+        let base = parts.next().unwrap_or_default();
+        if base.is_empty() {
+            return Err(error("base address is empty".to_string()));
+        }
+        let base = match base.rsplit_once('+') {
+            Some((name, offset)) if !name.trim().is_empty() => PathBase::Module {
+                name: name.trim().to_string(),
+                offset: parse_hex(offset.trim())
+                    .ok_or_else(|| error(format!("invalid module offset `{}`", offset)))?,
+            },
+            _ => PathBase::Absolute(
+                parse_hex(base).ok_or_else(|| error(format!("invalid address `{}`", base)))?,
+            ),
+        };
+
+        let offsets = parts
+            .map(|part| {
+                parse_offset(part).ok_or_else(|| error(format!("invalid offset `{}`", part)))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { base, offsets })
+    }
+}
+
+impl Serialize for PointerPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for PointerPath {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+fn parse_hex(s: &str) -> Option<usize> {
+    let digits = s
+        .strip_prefix("0x")
+        .or_else(|| s.strip_prefix("0X"))
+        .unwrap_or(s);
+    if digits.is_empty() {
+        return None;
+    }
+    usize::from_str_radix(digits, 16).ok()
+}
+
+fn parse_offset(s: &str) -> Option<isize> {
+    match s.strip_prefix('-') {
+        Some(rest) => isize::try_from(parse_hex(rest.trim())?).ok().map(|v| -v),
+        None => isize::try_from(parse_hex(s.strip_prefix('+').unwrap_or(s).trim())?).ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        game_export,
+        utils::{set_thread_memory_source, SparseMemory},
+    };
+
+    #[test]
+    fn test_pointer_path() {
+        let path: PointerPath = "0x1450139A0 -> 0x50 -> 0xC0".parse().unwrap();
+        assert_eq!(
+            path,
+            PointerPath::absolute(game_export::player_base(), &[0x50]).join(0xC0)
+        );
+        assert_eq!(path.to_string(), "0x1450139A0 -> 0x50 -> 0xC0");
+
+        let path: PointerPath = "MonsterHunterWorld.exe+50139A0->-0x10".parse().unwrap();
+        assert_eq!(
+            path,
+            PointerPath::module("MonsterHunterWorld.exe", 0x50139A0, &[-0x10])
+        );
+        assert_eq!(
+            path.to_string(),
+            "MonsterHunterWorld.exe+0x50139A0 -> -0x10"
+        );
+        let json = serde_json::to_string(&path).unwrap();
+        assert_eq!(json, r#""MonsterHunterWorld.exe+0x50139A0 -> -0x10""#);
+        assert_eq!(serde_json::from_str::<PointerPath>(&json).unwrap(), path);
+
+        for invalid in ["", "-> 0x50", "0x1000 -> ", "0x1000 -> 0xZZ", "game.exe+"] {
+            assert!(invalid.parse::<PointerPath>().is_err(), "{}", invalid);
+        }
+
+        let mut memory = SparseMemory::new();
+        memory.insert_zeroed(0x1000, 0x100);
+        memory.write_value(0x1000_usize, 0x1080_usize);
+        memory.write_value(0x10C0_usize, 77_i32);
+        let _guard = set_thread_memory_source(Arc::new(memory));
+        let path: PointerPath = "0x1000 -> 0x40".parse().unwrap();
+        assert_eq!(path.resolve(), Some(0x10C0));
+        assert_eq!(path.try_read::<i32>().unwrap(), 77);
+        assert!(matches!(
+            "0x2000 -> 0x40"
+                .parse::<PointerPath>()
+                .unwrap()
+                .try_read::<i32>(),
+            Err(PointerPathError::Memory(MemoryError::InvalidHop {
+                index: 0,
+                ..
+            }))
+        ));
+    }
+}
@@ -0,0 +1,412 @@
+use std::{ops::Range, sync::Arc};
+
+use thiserror::Error;
+
+use super::{resolve_offsets, MemoryRegion, MemorySource, PathBase, PeImage, PointerPath};
+
+/// 指针按8字节对齐存放
+const POINTER_SIZE: usize = 8;
+/// 逐块读取内存区域时每块的大小
+const READ_CHUNK_SIZE: usize = 0x100000;
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum PointerScanError {
+    #[error("at least one snapshot is required")]
+    NoSnapshot,
+    #[error("snapshot {0} has no static module")]
+    NoStaticModule(usize),
+    #[error("snapshot {0} reads live memory, capture it first")]
+    LiveSource(usize),
+    #[error("more than {0} pointers found, narrow the scanned regions")]
+    TooManyPointers(usize),
+}
+
+/// 静态模块，位于其中的指针作为路径的起点
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaticModule {
+    /// 模块名称，为空时路径使用绝对地址
+    pub name: String,
+    pub base: usize,
+    pub size: usize,
+}
+
+impl StaticModule {
+    pub fn new(name: &str, base: usize, size: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            base,
+            size,
+        }
+    }
+
+    pub fn from_image(image: &PeImage) -> Self {
+        Self::new(image.name(), image.base(), image.size())
+    }
+
+    pub fn range(&self) -> Range<usize> {
+        self.base..self.base + self.size
+    }
+}
+
+/// 一次搜索所用的内存快照，以及目标在该快照中的地址
+///
+/// 搜索期间内存不能变化，数据源不能是 [`LiveMemory`](super::LiveMemory)，
+/// 需要先通过 [`SparseMemory::capture`](super::SparseMemory::capture) 复制。
+#[derive(Clone)]
+pub struct PointerScanSnapshot {
+    pub source: Arc<dyn MemorySource>,
+    pub target: usize,
+    pub modules: Vec<StaticModule>,
+}
+
+impl PointerScanSnapshot {
+    pub fn new(source: Arc<dyn MemorySource>, target: usize, modules: Vec<StaticModule>) -> Self {
+        Self {
+            source,
+            target,
+            modules,
+        }
+    }
+
+    /// 在该快照中解析路径，模块起点使用快照中的模块基址
+    fn resolve(&self, path: &PointerPath) -> Option<usize> {
+        let base = match path.base() {
+            PathBase::Absolute(addr) => *addr,
+            PathBase::Module { name, offset } => {
+                self.modules.iter().find(|m| &m.name == name)?.base + offset
+            }
+        };
+        resolve_offsets(self.source.as_ref(), base, path.offsets())
+    }
+
+    fn static_base(&self, addr: usize) -> Option<PathBase> {
+        let module = self.modules.iter().find(|m| m.range().contains(&addr))?;
+        Some(if module.name.is_empty() {
+            PathBase::Absolute(addr)
+        } else {
+            PathBase::Module {
+                name: module.name.clone(),
+                offset: addr - module.base,
+            }
+        })
+    }
+}
+
+/// 搜索得到的候选路径
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PointerCandidate {
+    pub path: PointerPath,
+    /// 能解析到目标地址的快照数量
+    pub matched: usize,
+    /// 快照总数
+    pub total: usize,
+}
+
+impl PointerCandidate {
+    /// 在所有快照中均能解析到目标地址
+    pub fn is_stable(&self) -> bool {
+        self.matched == self.total
+    }
+}
+
+/// 反向搜索指向目标地址的多级指针路径
+///
+/// 在第一个快照中从目标地址开始逐级查找指向它（加上不超过 `max_offset` 的偏移）的指针，
+/// 直到指针位于静态模块中。随后在其余快照中验证每条路径，按能解析到目标的快照数量排序。
+///
+/// 搜索按层数从少到多进行，最多找到 [`with_max_paths`](Self::with_max_paths) 条路径，
+/// 排序后返回前 [`with_max_results`](Self::with_max_results) 条。
+///
+/// 第一个快照中的所有指针会被读入内存，数量超过 [`with_max_pointers`](Self::with_max_pointers)
+/// 时返回错误，此时应只复制需要的区域（例如可写区域）。
+///
+/// ```ignore
+/// let regions: Vec<MemoryRegion> = LiveMemory
+///     .committed_regions()
+///     .into_iter()
+///     .filter(|r| r.readable && r.writable)
+///     .collect();
+/// let snapshot = PointerScanSnapshot::new(
+///     Arc::new(SparseMemory::capture(&LiveMemory, &regions)),
+///     player.get_instance(),
+///     vec![StaticModule::from_image(&PeImage::main_module()?)],
+/// );
+/// let candidates = PointerScanner::new(3, 0x1000).scan(&[snapshot])?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct PointerScanner {
+    max_depth: usize,
+    max_offset: usize,
+    max_results: usize,
+    max_paths: usize,
+    max_pointers: usize,
+}
+
+impl PointerScanner {
+    /// `max_depth` 为最大层数，`max_offset` 为每一级的最大偏移
+    pub fn new(max_depth: usize, max_offset: usize) -> Self {
+        Self {
+            max_depth,
+            max_offset,
+            max_results: 10000,
+            max_paths: 1000000,
+            max_pointers: 0x2000000,
+        }
+    }
+
+    /// 排序后最多返回的候选数量
+    pub fn with_max_results(mut self, max_results: usize) -> Self {
+        self.max_results = max_results;
+        self
+    }
+
+    /// 搜索的路径数量上限，达到后不再搜索更多层数的路径
+    pub fn with_max_paths(mut self, max_paths: usize) -> Self {
+        self.max_paths = max_paths;
+        self
+    }
+
+    /// 第一个快照中最多记录的指针数量，每个指针占16字节
+    pub fn with_max_pointers(mut self, max_pointers: usize) -> Self {
+        self.max_pointers = max_pointers;
+        self
+    }
+
+    pub fn scan(
+        &self,
+        snapshots: &[PointerScanSnapshot],
+    ) -> Result<Vec<PointerCandidate>, PointerScanError> {
+        let first = snapshots.first().ok_or(PointerScanError::NoSnapshot)?;
+        if let Some(index) = snapshots.iter().position(|s| s.modules.is_empty()) {
+            return Err(PointerScanError::NoStaticModule(index));
+        }
+        if let Some(index) = snapshots.iter().position(|s| s.source.is_live()) {
+            return Err(PointerScanError::LiveSource(index));
+        }
+
+        let map = PointerMap::build(first.source.as_ref(), self.max_pointers)?;
+        let mut paths = Vec::new();
+        let mut chain = Vec::new();
+        // 逐层加深，路径数量达到上限时保留的是层数较少的路径
+        for depth in 1..=self.max_depth {
+            if paths.len() >= self.max_paths {
+                break;
+            }
+            self.search(first, &map, first.target, depth, &mut chain, &mut paths);
+        }
+
+        let mut candidates: Vec<PointerCandidate> = paths
+            .into_iter()
+            .map(|path| PointerCandidate {
+                matched: snapshots
+                    .iter()
+                    .filter(|s| s.resolve(&path) == Some(s.target))
+                    .count(),
+                total: snapshots.len(),
+                path,
+            })
+            .collect();
+        // 稳定性优先，其次层数少、偏移小的路径
+        candidates.sort_by_key(|c| {
+            (
+                std::cmp::Reverse(c.matched),
+                c.path.offsets().len(),
+                c.path
+                    .offsets()
+                    .iter()
+                    .map(|o| o.unsigned_abs())
+                    .sum::<usize>(),
+            )
+        });
+        candidates.truncate(self.max_results);
+        Ok(candidates)
+    }
+
+    /// 查找恰好 `depth` 层的路径
+    ///
+    /// `chain` 为当前已确定的偏移，按从目标往回的顺序排列
+    fn search(
+        &self,
+        snapshot: &PointerScanSnapshot,
+        map: &PointerMap,
+        target: usize,
+        depth: usize,
+        chain: &mut Vec<(usize, isize)>,
+        paths: &mut Vec<PointerPath>,
+    ) {
+        for &(value, slot) in map.pointing_near(target, self.max_offset) {
+            if paths.len() >= self.max_paths {
+                return;
+            }
+            // 跳过循环引用
+            if slot == target || chain.iter().any(|&(s, _)| s == slot) {
+                continue;
+            }
+            chain.push((slot, (target - value) as isize));
+            match snapshot.static_base(slot) {
+                Some(base) if chain.len() == depth => {
+                    let offsets: Vec<isize> = chain.iter().rev().map(|&(_, o)| o).collect();
+                    paths.push(PointerPath::new(base, &offsets));
+                }
+                // 较短的路径已在之前的层数中找到
+                Some(_) => {}
+                None if chain.len() < depth => {
+                    self.search(snapshot, map, slot, depth, chain, paths)
+                }
+                None => {}
+            }
+            chain.pop();
+        }
+    }
+}
+
+/// 按指针值排序的 (指针值, 指针所在地址) 列表
+struct PointerMap {
+    entries: Vec<(usize, usize)>,
+}
+
+impl PointerMap {
+    /// 逐块读取所有可读区域，区域可能在枚举后被释放，读取前逐块确认可读
+    fn build(source: &dyn MemorySource, max_pointers: usize) -> Result<Self, PointerScanError> {
+        let mut regions: Vec<MemoryRegion> = source
+            .committed_regions()
+            .into_iter()
+            .filter(|r| r.readable)
+            .collect();
+        regions.sort_by_key(|r| r.base);
+        let is_mapped = |value: usize| {
+            let index = regions.partition_point(|r| r.base <= value);
+            index > 0 && regions[index - 1].contains(value)
+        };
+
+        let mut entries = Vec::new();
+        let mut buf = Vec::new();
+        for region in regions.iter() {
+            let start = region.base.next_multiple_of(POINTER_SIZE);
+            let end = region.base + region.size;
+            for chunk in (start..end).step_by(READ_CHUNK_SIZE) {
+                buf.resize(READ_CHUNK_SIZE.min(end - chunk), 0);
+                if !source.is_readable(chunk, buf.len()) || !source.read_bytes(chunk, &mut buf) {
+                    continue;
+                }
+                for (i, bytes) in buf.chunks_exact(POINTER_SIZE).enumerate() {
+                    let value = usize::from_le_bytes(bytes.try_into().unwrap());
+                    if value != 0 && is_mapped(value) {
+                        if entries.len() >= max_pointers {
+                            return Err(PointerScanError::TooManyPointers(max_pointers));
+                        }
+                        entries.push((value, chunk + i * POINTER_SIZE));
+                    }
+                }
+            }
+        }
+        entries.sort_unstable();
+        Ok(Self { entries })
+    }
+
+    /// 指针值位于 `[target - max_offset, target]` 的条目
+    fn pointing_near(&self, target: usize, max_offset: usize) -> &[(usize, usize)] {
+        let low = target.saturating_sub(max_offset);
+        let start = self.entries.partition_point(|&(v, _)| v < low);
+        let end = self.entries.partition_point(|&(v, _)| v <= target);
+        &self.entries[start..end]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{LiveMemory, SparseMemory};
+
+    /// 模块位于 `module_base`，`0x100` 处为指向对象的指针，
+    /// 对象的 `0x50` 处为指向目标所在结构的指针，目标位于结构的 `0x18` 处
+    fn build_snapshot(module_base: usize, object: usize, info: usize) -> PointerScanSnapshot {
+        let mut memory = SparseMemory::new();
+        memory.insert_zeroed(module_base, 0x1000);
+        memory.insert_zeroed(object, 0x100);
+        memory.insert_zeroed(info, 0x100);
+        memory.write_value(module_base + 0x100, object);
+        memory.write_value(object + 0x50, info);
+        // 仅在部分快照中存在的路径
+        if module_base == 0x1_4000_0000 {
+            memory.write_value(module_base + 0x200, info);
+        }
+        PointerScanSnapshot::new(
+            Arc::new(memory),
+            info + 0x18,
+            vec![StaticModule::new("game.exe", module_base, 0x1000)],
+        )
+    }
+
+    #[test]
+    fn test_pointer_scan() {
+        let snapshots = [
+            build_snapshot(0x1_4000_0000, 0x2000_0000, 0x3000_0000),
+            build_snapshot(0x1_5000_0000, 0x2100_0000, 0x3100_0000),
+        ];
+        let candidates = PointerScanner::new(2, 0x100).scan(&snapshots).unwrap();
+        assert_eq!(candidates.len(), 2);
+        assert_eq!(
+            candidates[0].path.to_string(),
+            "game.exe+0x100 -> 0x50 -> 0x18"
+        );
+        assert!(candidates[0].is_stable());
+        assert_eq!(candidates[1].path.to_string(), "game.exe+0x200 -> 0x18");
+        assert_eq!((candidates[1].matched, candidates[1].total), (1, 2));
+
+        // 排序后截断，保留稳定的路径
+        let candidates = PointerScanner::new(2, 0x100)
+            .with_max_results(1)
+            .scan(&snapshots)
+            .unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert!(candidates[0].is_stable());
+        // 搜索上限先保留层数较少的路径
+        let candidates = PointerScanner::new(2, 0x100)
+            .with_max_paths(1)
+            .scan(&snapshots)
+            .unwrap();
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].path.to_string(), "game.exe+0x200 -> 0x18");
+
+        // 层数不足时找不到稳定路径
+        let candidates = PointerScanner::new(1, 0x100).scan(&snapshots).unwrap();
+        assert!(candidates.iter().all(|c| !c.is_stable()));
+        assert_eq!(
+            PointerScanner::new(2, 0x100).scan(&[]).unwrap_err(),
+            PointerScanError::NoSnapshot
+        );
+        assert_eq!(
+            PointerScanner::new(2, 0x100)
+                .with_max_pointers(2)
+                .scan(&snapshots)
+                .unwrap_err(),
+            PointerScanError::TooManyPointers(2)
+        );
+        let live = PointerScanSnapshot::new(Arc::new(LiveMemory), 0, snapshots[0].modules.clone());
+        assert_eq!(
+            PointerScanner::new(2, 0x100)
+                .scan(&[snapshots[0].clone(), live])
+                .unwrap_err(),
+            PointerScanError::LiveSource(1)
+        );
+
+        // 复制后的快照得到相同的结果
+        let regions = snapshots[0].source.committed_regions();
+        let captured = PointerScanSnapshot::new(
+            Arc::new(SparseMemory::capture(
+                snapshots[0].source.as_ref(),
+                &regions,
+            )),
+            snapshots[0].target,
+            snapshots[0].modules.clone(),
+        );
+        assert_eq!(
+            PointerScanner::new(2, 0x100)
+                .scan(&[captured])
+                .unwrap()
+                .len(),
+            2
+        );
+    }
+}
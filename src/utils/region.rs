@@ -0,0 +1,312 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    ffi::c_void,
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use once_cell::sync::Lazy;
+use windows::Win32::System::Memory::{
+    VirtualQuery, MEMORY_BASIC_INFORMATION, MEM_COMMIT, PAGE_EXECUTE_READ, PAGE_EXECUTE_READWRITE,
+    PAGE_EXECUTE_WRITECOPY, PAGE_GUARD, PAGE_NOACCESS, PAGE_READONLY, PAGE_READWRITE,
+    PAGE_WRITECOPY,
+};
+
+/// 缓存的内存区域信息的有效期
+///
+/// 游戏运行时会不断分配与释放内存，过期的区域需要重新查询。
+pub const REGION_CACHE_TTL: Duration = Duration::from_secs(1);
+
+static LIVE_REGION_MAP: Lazy<Arc<RegionMap>> =
+    Lazy::new(|| Arc::new(RegionMap::new(Box::new(VirtualQueryRegions))));
+
+/// 已提交的内存区域
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryRegion {
+    pub base: usize,
+    pub size: usize,
+    pub readable: bool,
+    pub writable: bool,
+    pub executable: bool,
+}
+
+impl MemoryRegion {
+    /// 可读写的区域
+    pub fn read_write(base: usize, size: usize) -> Self {
+        Self {
+            base,
+            size,
+            readable: true,
+            writable: true,
+            executable: false,
+        }
+    }
+
+    /// 只读的区域
+    pub fn read_only(base: usize, size: usize) -> Self {
+        Self {
+            writable: false,
+            ..Self::read_write(base, size)
+        }
+    }
+
+    pub fn end(&self) -> usize {
+        self.base + self.size
+    }
+
+    pub fn contains(&self, addr: usize) -> bool {
+        (self.base..self.end()).contains(&addr)
+    }
+}
+
+/// 内存区域查询
+pub trait RegionQuery: Send + Sync {
+    /// 查询包含 `addr` 的已提交区域，未提交或无法访问时返回None
+    fn query(&self, addr: usize) -> Option<MemoryRegion>;
+}
+
+/// 通过 `VirtualQuery` 查询当前进程的内存区域
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VirtualQueryRegions;
+
+impl VirtualQueryRegions {
+    /// 当前进程所有已提交且可访问的区域
+    pub fn enumerate(&self) -> Vec<MemoryRegion> {
+        let mut regions = Vec::new();
+        let mut addr = 0_usize;
+        while let Some(info) = virtual_query(addr) {
+            let next = (info.BaseAddress as usize).checked_add(info.RegionSize);
+            if let Some(region) = to_region(&info) {
+                regions.push(region);
+            }
+            match next {
+                Some(next) if next > addr => addr = next,
+                _ => break,
+            }
+        }
+        regions
+    }
+}
+
+impl RegionQuery for VirtualQueryRegions {
+    fn query(&self, addr: usize) -> Option<MemoryRegion> {
+        to_region(&virtual_query(addr)?)
+    }
+}
+
+fn virtual_query(addr: usize) -> Option<MEMORY_BASIC_INFORMATION> {
+    let mut info = MEMORY_BASIC_INFORMATION::default();
+    let len = unsafe {
+        VirtualQuery(
+            Some(addr as *const c_void),
+            &mut info,
+            std::mem::size_of::<MEMORY_BASIC_INFORMATION>(),
+        )
+    };
+    (len != 0).then_some(info)
+}
+
+fn to_region(info: &MEMORY_BASIC_INFORMATION) -> Option<MemoryRegion> {
+    if info.State != MEM_COMMIT {
+        return None;
+    }
+    let protect = info.Protect;
+    if protect.contains(PAGE_GUARD) || protect.contains(PAGE_NOACCESS) {
+        return None;
+    }
+    let any = |flags: &[_]| flags.iter().any(|&flag| protect.contains(flag));
+    Some(MemoryRegion {
+        base: info.BaseAddress as usize,
+        size: info.RegionSize,
+        readable: any(&[
+            PAGE_READONLY,
+            PAGE_READWRITE,
+            PAGE_WRITECOPY,
+            PAGE_EXECUTE_READ,
+            PAGE_EXECUTE_READWRITE,
+            PAGE_EXECUTE_WRITECOPY,
+        ]),
+        writable: any(&[
+            PAGE_READWRITE,
+            PAGE_WRITECOPY,
+            PAGE_EXECUTE_READWRITE,
+            PAGE_EXECUTE_WRITECOPY,
+        ]),
+        executable: any(&[
+            PAGE_EXECUTE_READ,
+            PAGE_EXECUTE_READWRITE,
+            PAGE_EXECUTE_WRITECOPY,
+        ]),
+    })
+}
+
+/// 固定的内存区域列表，用于测试
+#[derive(Debug, Clone, Default)]
+pub struct FixedRegions {
+    regions: BTreeMap<usize, MemoryRegion>,
+}
+
+impl FixedRegions {
+    pub fn new(regions: impl IntoIterator<Item = MemoryRegion>) -> Self {
+        Self {
+            regions: regions.into_iter().map(|r| (r.base, r)).collect(),
+        }
+    }
+}
+
+impl RegionQuery for FixedRegions {
+    fn query(&self, addr: usize) -> Option<MemoryRegion> {
+        let (_, region) = self.regions.range(..=addr).next_back()?;
+        region.contains(addr).then_some(*region)
+    }
+}
+
+/// 已提交内存区域的缓存
+///
+/// 用于在解引用前确认地址可读，避免野指针导致游戏崩溃。
+/// 只缓存查询到的区域，未命中时重新查询，缓存超过 [`REGION_CACHE_TTL`] 后失效。
+/// 可读写检查总是重新查询，区域在有效期内被释放时也不会误判为可读。
+pub struct RegionMap {
+    query: Box<dyn RegionQuery>,
+    ttl: Duration,
+    cache: RwLock<BTreeMap<usize, (MemoryRegion, Instant)>>,
+}
+
+impl RegionMap {
+    pub fn new(query: Box<dyn RegionQuery>) -> Self {
+        Self {
+            query,
+            ttl: REGION_CACHE_TTL,
+            cache: RwLock::new(BTreeMap::new()),
+        }
+    }
+
+    /// 由固定的区域列表创建，用于测试
+    pub fn from_regions(regions: impl IntoIterator<Item = MemoryRegion>) -> Self {
+        Self::new(Box::new(FixedRegions::new(regions)))
+    }
+
+    /// 当前线程使用的区域缓存
+    ///
+    /// 未通过 [`set_thread_region_map`] 设置时，使用当前进程的区域缓存。
+    pub fn current() -> Arc<RegionMap> {
+        THREAD_REGION_MAP
+            .with(|map| map.borrow().clone())
+            .unwrap_or_else(|| LIVE_REGION_MAP.clone())
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// 包含 `addr` 的已提交区域
+    pub fn region_of(&self, addr: usize) -> Option<MemoryRegion> {
+        let now = Instant::now();
+        let cached = self
+            .cache
+            .read()
+            .unwrap()
+            .range(..=addr)
+            .next_back()
+            .map(|(_, &entry)| entry)
+            .filter(|(region, time)| region.contains(addr) && now - *time < self.ttl);
+        if let Some((region, _)) = cached {
+            return Some(region);
+        }
+        self.refresh(addr)
+    }
+
+    /// 重新查询包含 `addr` 的区域并更新缓存
+    ///
+    /// 查询失败时移除缓存中包含 `addr` 的记录。
+    pub fn refresh(&self, addr: usize) -> Option<MemoryRegion> {
+        let now = Instant::now();
+        let Some(region) = self.query.query(addr) else {
+            let mut cache = self.cache.write().unwrap();
+            let stale = cache
+                .range(..=addr)
+                .next_back()
+                .filter(|(_, (region, _))| region.contains(addr))
+                .map(|(&base, _)| base);
+            if let Some(base) = stale {
+                cache.remove(&base);
+            }
+            return None;
+        };
+        let mut cache = self.cache.write().unwrap();
+        // 移除与新区域重叠的过期记录
+        let stale: Vec<usize> = cache
+            .range(..region.end())
+            .filter(|(_, (r, _))| r.end() > region.base)
+            .map(|(&base, _)| base)
+            .collect();
+        for base in stale {
+            cache.remove(&base);
+        }
+        cache.insert(region.base, (region, now));
+        Some(region)
+    }
+
+    /// `[addr, addr + len)` 是否全部可读
+    pub fn is_readable(&self, addr: usize, len: usize) -> bool {
+        self.check_range(addr, len, |region| region.readable)
+    }
+
+    /// `[addr, addr + len)` 是否全部可写
+    pub fn is_writable(&self, addr: usize, len: usize) -> bool {
+        self.check_range(addr, len, |region| region.writable)
+    }
+
+    /// 清空缓存
+    pub fn invalidate(&self) {
+        self.cache.write().unwrap().clear();
+    }
+
+    fn check_range(
+        &self,
+        addr: usize,
+        len: usize,
+        allowed: impl Fn(&MemoryRegion) -> bool,
+    ) -> bool {
+        let Some(end) = addr.checked_add(len) else {
+            return false;
+        };
+        if addr == 0 {
+            return false;
+        }
+        // 范围可能跨越多个相邻区域
+        let mut cursor = addr;
+        while cursor < end.max(addr + 1) {
+            match self.refresh(cursor) {
+                Some(region) if allowed(&region) => cursor = region.end(),
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+thread_local! {
+    static THREAD_REGION_MAP: RefCell<Option<Arc<RegionMap>>> = const { RefCell::new(None) };
+}
+
+/// 为当前线程设置区域缓存，用于在测试中模拟内存布局
+///
+/// 返回的守卫被释放时恢复之前的区域缓存。
+pub fn set_thread_region_map(map: Arc<RegionMap>) -> RegionMapGuard {
+    let previous = THREAD_REGION_MAP.with(|current| current.borrow_mut().replace(map));
+    RegionMapGuard { previous }
+}
+
+pub struct RegionMapGuard {
+    previous: Option<Arc<RegionMap>>,
+}
+
+impl Drop for RegionMapGuard {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        THREAD_REGION_MAP.with(|current| *current.borrow_mut() = previous);
+    }
+}
@@ -0,0 +1,472 @@
+use std::ffi::c_void;
+
+use thiserror::Error;
+use windows::Win32::System::{
+    Diagnostics::Debug::FlushInstructionCache,
+    Memory::{
+        VirtualAlloc, VirtualFree, VirtualQuery, MEMORY_BASIC_INFORMATION, MEM_COMMIT, MEM_FREE,
+        MEM_RELEASE, MEM_RESERVE, PAGE_EXECUTE_READWRITE,
+    },
+    SystemInformation::{GetSystemInfo, SYSTEM_INFO},
+    Threading::GetCurrentProcess,
+};
+
+use super::{
+    decode_instruction, read_span_covering, with_memory_source, DecodeError, Patch, PatchError,
+    RipOperandKind,
+};
+
+/// `jmp rel32` 的长度
+pub const JMP_REL32_LEN: usize = 5;
+/// `jmp [rip+0]; dq target` 的长度
+pub const JMP_ABS_LEN: usize = 14;
+/// `call [rip+2]; jmp +8; dq target` 的长度
+pub const CALL_ABS_LEN: usize = 16;
+/// rel32 可达的范围
+const REL32_RANGE: usize = 0x7FFF_0000;
+
+#[derive(Debug, Error)]
+pub enum TrampolineError {
+    #[error("0x{to:X} is out of rel32 range from 0x{from:X}")]
+    OutOfRange { from: usize, to: usize },
+    #[error("no free memory within ±2GB of 0x{0:X}")]
+    NoNearbyMemory(usize),
+    #[error("code cave is full")]
+    CaveFull,
+    #[error("displaced instructions are {0} bytes, at least 5 bytes are required")]
+    TooShort(usize),
+    #[error("instruction at offset {offset} cannot be relocated: {reason}")]
+    Relocation { offset: usize, reason: String },
+    #[error("failed to read memory at 0x{0:X}")]
+    Read(usize),
+    #[error(transparent)]
+    Decode(#[from] DecodeError),
+    #[error(transparent)]
+    Patch(#[from] PatchError),
+}
+
+/// 计算从 `from`（下一条指令地址）到 `to` 的rel32偏移，超出范围时返回None
+pub fn rel32(from: usize, to: usize) -> Option<i32> {
+    i32::try_from(to.wrapping_sub(from) as isize).ok()
+}
+
+/// `jmp rel32`
+pub fn emit_jmp_rel32(from: usize, to: usize) -> Result<[u8; JMP_REL32_LEN], TrampolineError> {
+    emit_rel32(0xE9, from, to)
+}
+
+/// `call rel32`
+pub fn emit_call_rel32(from: usize, to: usize) -> Result<[u8; JMP_REL32_LEN], TrampolineError> {
+    emit_rel32(0xE8, from, to)
+}
+
+fn emit_rel32(opcode: u8, from: usize, to: usize) -> Result<[u8; JMP_REL32_LEN], TrampolineError> {
+    let disp = rel32(from + JMP_REL32_LEN, to).ok_or(TrampolineError::OutOfRange { from, to })?;
+    let mut code = [opcode, 0, 0, 0, 0];
+    code[1..].copy_from_slice(&disp.to_le_bytes());
+    Ok(code)
+}
+
+/// 不受距离限制的跳转：`jmp [rip+0]; dq to`
+pub fn emit_jmp_abs(to: usize) -> [u8; JMP_ABS_LEN] {
+    let mut code = [0xFF, 0x25, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    code[6..].copy_from_slice(&(to as u64).to_le_bytes());
+    code
+}
+
+/// 不受距离限制的调用：`call [rip+2]; jmp +8; dq to`
+pub fn emit_call_abs(to: usize) -> [u8; CALL_ABS_LEN] {
+    let mut code = [
+        0xFF, 0x15, 0x02, 0, 0, 0, 0xEB, 0x08, 0, 0, 0, 0, 0, 0, 0, 0,
+    ];
+    code[8..].copy_from_slice(&(to as u64).to_le_bytes());
+    code
+}
+
+/// 跳转到 `to`，距离允许时使用 `jmp rel32`，否则使用绝对跳转
+pub fn emit_jmp(from: usize, to: usize) -> Vec<u8> {
+    match emit_jmp_rel32(from, to) {
+        Ok(code) => code.to_vec(),
+        Err(_) => emit_jmp_abs(to).to_vec(),
+    }
+}
+
+/// 将 `old_ip` 处的指令搬移到 `new_ip`
+///
+/// `instruction_lens` 为每条指令的长度，总和必须等于 `code.len()`。
+/// RIP相对寻址的偏移会被修正；相对跳转与调用在超出范围时改写为绝对跳转，
+/// 因此输出可能比输入更长。
+pub fn relocate(
+    code: &[u8],
+    instruction_lens: &[usize],
+    old_ip: usize,
+    new_ip: usize,
+) -> Result<Vec<u8>, TrampolineError> {
+    let total: usize = instruction_lens.iter().sum();
+    if total != code.len() {
+        return Err(TrampolineError::Relocation {
+            offset: 0,
+            reason: format!(
+                "instruction lengths cover {} bytes, code is {} bytes",
+                total,
+                code.len()
+            ),
+        });
+    }
+
+    let mut output = Vec::with_capacity(code.len() * 2);
+    let mut offset = 0;
+    for &len in instruction_lens {
+        let instruction = &code[offset..offset + len];
+        let new_addr = new_ip + output.len();
+        let relocated = relocate_instruction(
+            instruction,
+            old_ip + offset,
+            new_addr,
+            old_ip..old_ip + total,
+        )
+        .map_err(|reason| TrampolineError::Relocation { offset, reason })?;
+        output.extend_from_slice(&relocated);
+        offset += len;
+    }
+    Ok(output)
+}
+
+fn relocate_instruction(
+    instruction: &[u8],
+    old_addr: usize,
+    new_addr: usize,
+    displaced: std::ops::Range<usize>,
+) -> Result<Vec<u8>, String> {
+    let decoded = decode_instruction(instruction, old_addr).map_err(|e| e.to_string())?;
+    if decoded.len != instruction.len() {
+        return Err(format!(
+            "decoded length {} does not match {}",
+            decoded.len,
+            instruction.len()
+        ));
+    }
+    let Some(operand) = decoded.rip_operand else {
+        return Ok(instruction.to_vec());
+    };
+    if operand.kind != RipOperandKind::Memory && displaced.contains(&operand.target) {
+        return Err("branch target is inside the displaced instructions".to_string());
+    }
+
+    let target = operand.target;
+    match operand.kind {
+        RipOperandKind::Memory | RipOperandKind::IndirectCall | RipOperandKind::IndirectJmp => {
+            let disp = rel32(new_addr + instruction.len(), target)
+                .ok_or_else(|| format!("memory operand 0x{:X} is out of rel32 range", target))?;
+            let mut relocated = instruction.to_vec();
+            relocated[operand.disp_offset..operand.disp_offset + 4]
+                .copy_from_slice(&disp.to_le_bytes());
+            Ok(relocated)
+        }
+        RipOperandKind::Call => Ok(match emit_call_rel32(new_addr, target) {
+            Ok(code) => code.to_vec(),
+            Err(_) => emit_call_abs(target).to_vec(),
+        }),
+        RipOperandKind::Jmp => Ok(emit_jmp(new_addr, target)),
+        // loop/jrcxz 只有rel8形式，无法搬移
+        RipOperandKind::Loop => Err("loop/jrcxz cannot be relocated".to_string()),
+        RipOperandKind::Jcc => {
+            // rel8与rel32形式的条件码都位于偏移量前一个字节的低4位
+            let condition = instruction[operand.disp_offset - 1] & 0x0F;
+            if let Some(disp) = rel32(new_addr + 6, target) {
+                let mut code = vec![0x0F, 0x80 | condition];
+                code.extend_from_slice(&disp.to_le_bytes());
+                Ok(code)
+            } else {
+                // 反转条件跳过绝对跳转
+                let mut code = vec![0x70 | (condition ^ 1), JMP_ABS_LEN as u8];
+                code.extend_from_slice(&emit_jmp_abs(target));
+                Ok(code)
+            }
+        }
+    }
+}
+
+/// 注入所需的代码
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InjectionCode {
+    /// 写入代码洞的代码：自定义代码、被覆盖的原指令、跳回原位置
+    pub cave: Vec<u8>,
+    /// 写入目标地址的代码：跳转到代码洞，剩余字节以 `nop` 填充
+    pub patch: Vec<u8>,
+}
+
+/// 生成在 `target` 处注入 `custom` 代码所需的字节
+///
+/// `original` 为目标地址处将被覆盖的完整指令，`instruction_lens` 为其中每条指令的长度。
+/// 执行流程与CE的AOB注入模板一致：跳转到代码洞，执行自定义代码与原指令后跳回。
+pub fn build_injection(
+    target: usize,
+    original: &[u8],
+    instruction_lens: &[usize],
+    cave_addr: usize,
+    custom: &[u8],
+) -> Result<InjectionCode, TrampolineError> {
+    if original.len() < JMP_REL32_LEN {
+        return Err(TrampolineError::TooShort(original.len()));
+    }
+
+    let mut cave = custom.to_vec();
+    let relocated = relocate(original, instruction_lens, target, cave_addr + cave.len())?;
+    cave.extend_from_slice(&relocated);
+    let back = emit_jmp(cave_addr + cave.len(), target + original.len());
+    cave.extend_from_slice(&back);
+
+    let mut patch = emit_jmp_rel32(target, cave_addr)?.to_vec();
+    patch.resize(original.len(), 0x90);
+
+    Ok(InjectionCode { cave, patch })
+}
+
+/// 目标地址附近的可执行内存
+///
+/// 保证区域内任意地址与目标地址之间可以使用rel32跳转，释放时归还内存。
+pub struct CodeCave {
+    base: usize,
+    size: usize,
+    used: usize,
+}
+
+unsafe impl Send for CodeCave {}
+
+impl CodeCave {
+    /// 在 `target` 的±2GB范围内分配至少 `size` 字节的可执行内存
+    pub fn allocate_near(target: usize, size: usize) -> Result<Self, TrampolineError> {
+        let mut info = SYSTEM_INFO::default();
+        unsafe { GetSystemInfo(&mut info) };
+        let granularity = (info.dwAllocationGranularity as usize).max(0x10000);
+        let size = size.div_ceil(granularity) * granularity;
+        let min_addr = (target.saturating_sub(REL32_RANGE) + size)
+            .max(info.lpMinimumApplicationAddress as usize)
+            .next_multiple_of(granularity);
+        let max_addr = (target + REL32_RANGE - size).min(info.lpMaximumApplicationAddress as usize);
+
+        // 先向低地址搜索，再向高地址搜索
+        let start = target - target % granularity;
+        let mut addr = start;
+        while addr >= min_addr {
+            match query_region(addr) {
+                Some(region) if region.State == MEM_FREE => {
+                    if let Some(cave) = Self::try_alloc(addr, size) {
+                        return Ok(cave);
+                    }
+                }
+                Some(region) => {
+                    let base = region.AllocationBase as usize;
+                    addr = base - base % granularity;
+                }
+                None => break,
+            }
+            match addr.checked_sub(granularity) {
+                Some(below) => addr = below,
+                None => break,
+            }
+        }
+        let mut addr = start + granularity;
+        while addr <= max_addr {
+            match query_region(addr) {
+                Some(region) if region.State == MEM_FREE => {
+                    if let Some(cave) = Self::try_alloc(addr, size) {
+                        return Ok(cave);
+                    }
+                    addr += granularity;
+                }
+                Some(region) => {
+                    let next = region.BaseAddress as usize + region.RegionSize;
+                    addr = next.next_multiple_of(granularity);
+                }
+                None => break,
+            }
+        }
+
+        Err(TrampolineError::NoNearbyMemory(target))
+    }
+
+    fn try_alloc(addr: usize, size: usize) -> Option<Self> {
+        let ptr = unsafe {
+            VirtualAlloc(
+                Some(addr as *const c_void),
+                size,
+                MEM_COMMIT | MEM_RESERVE,
+                PAGE_EXECUTE_READWRITE,
+            )
+        };
+        (!ptr.is_null()).then_some(Self {
+            base: ptr as usize,
+            size,
+            used: 0,
+        })
+    }
+
+    pub fn base(&self) -> usize {
+        self.base
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// 剩余可用字节数
+    pub fn remaining(&self) -> usize {
+        self.size - self.used
+    }
+
+    /// 分配一段16字节对齐的空间并写入代码，返回其地址
+    pub fn push(&mut self, code: &[u8]) -> Result<usize, TrampolineError> {
+        let addr = self.reserve(code.len())?;
+        unsafe {
+            std::ptr::copy_nonoverlapping(code.as_ptr(), addr as *mut u8, code.len());
+            let _ =
+                FlushInstructionCache(GetCurrentProcess(), Some(addr as *const c_void), code.len());
+        }
+        Ok(addr)
+    }
+
+    /// 分配一段16字节对齐的空间，返回其地址
+    pub fn reserve(&mut self, len: usize) -> Result<usize, TrampolineError> {
+        let start = self.used.next_multiple_of(16);
+        if start + len > self.size {
+            return Err(TrampolineError::CaveFull);
+        }
+        self.used = start + len;
+        Ok(self.base + start)
+    }
+}
+
+impl Drop for CodeCave {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = VirtualFree(self.base as *mut c_void, 0, MEM_RELEASE);
+        }
+    }
+}
+
+fn query_region(addr: usize) -> Option<MEMORY_BASIC_INFORMATION> {
+    let mut info = MEMORY_BASIC_INFORMATION::default();
+    let len = unsafe {
+        VirtualQuery(
+            Some(addr as *const c_void),
+            &mut info,
+            std::mem::size_of::<MEMORY_BASIC_INFORMATION>(),
+        )
+    };
+    (len != 0).then_some(info)
+}
+
+/// 函数中间的代码注入
+///
+/// 将目标地址处的指令替换为跳转到代码洞，代码洞中依次执行自定义代码、
+/// 被覆盖的原指令，再跳回原位置。启用与禁用通过 [`Patch`] 完成，释放时恢复原指令并归还代码洞。
+pub struct Injection {
+    // 字段按声明顺序释放：先恢复原指令，再释放代码洞
+    patch: Patch,
+    cave: CodeCave,
+}
+
+impl Injection {
+    /// 创建注入，不会立即启用
+    ///
+    /// 目标地址处被覆盖的指令由解码器确定：覆盖至少5字节的最少完整指令，不会截断指令。
+    pub fn new(name: &str, target: usize, custom: &[u8]) -> Result<Self, TrampolineError> {
+        let span = read_span_covering(target, JMP_REL32_LEN)?;
+        let instruction_lens = span.lens();
+        let len = span.len;
+        let mut original = vec![0; len];
+        if !with_memory_source(|source| source.read_bytes(target, &mut original)) {
+            return Err(TrampolineError::Read(target));
+        }
+
+        // 最坏情况下每条指令都被改写为绝对跳转
+        let capacity = custom.len() + instruction_lens.len() * CALL_ABS_LEN + len + JMP_ABS_LEN;
+        let mut cave = CodeCave::allocate_near(target, capacity)?;
+        let code = build_injection(target, &original, &instruction_lens, cave.base(), custom)?;
+        cave.push(&code.cave)?;
+        let patch = Patch::new(name, target, &original, &code.patch)?;
+
+        Ok(Self { patch, cave })
+    }
+
+    pub fn patch(&self) -> &Patch {
+        &self.patch
+    }
+
+    pub fn patch_mut(&mut self) -> &mut Patch {
+        &mut self.patch
+    }
+
+    /// 代码洞中自定义代码的起始地址
+    pub fn cave_address(&self) -> usize {
+        self.cave.base()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use address_scanner::hex_str_to_bytes;
+
+    use super::*;
+    use crate::utils::decode_rip_operand;
+
+    #[test]
+    fn test_relocate() {
+        // mov rcx, [rip+0x100]; call +0x20; jz +0x10; sub rsp, 0x28
+        let code = hex_str_to_bytes!("48 8B 0D 00 01 00 00 E8 20 00 00 00 74 10 48 83 EC 28");
+        let lens = [7, 5, 2, 4];
+        let old_ip = 0x1_4000_1000;
+
+        // 近距离搬移：偏移量被修正
+        let new_ip = 0x1_4000_8000;
+        let relocated = relocate(&code, &lens, old_ip, new_ip).unwrap();
+        let operand = decode_rip_operand(&relocated, new_ip).unwrap();
+        assert_eq!(operand.target, old_ip + 7 + 0x100);
+        let call = decode_rip_operand(&relocated[7..], new_ip + 7).unwrap();
+        assert_eq!(call.target, old_ip + 12 + 0x20);
+        let jz = decode_rip_operand(&relocated[12..], new_ip + 12).unwrap();
+        assert_eq!((jz.kind, jz.len), (RipOperandKind::Jcc, 6));
+        assert_eq!(jz.target, old_ip + 14 + 0x10);
+        assert_eq!(&relocated[18..], &code[14..]);
+
+        // 远距离搬移：跳转改写为绝对跳转，内存操作数无法修正
+        let far_ip = 0x7FF0_0000_0000;
+        let err = relocate(&code, &lens, old_ip, far_ip).unwrap_err();
+        assert!(matches!(err, TrampolineError::Relocation { offset: 0, .. }));
+        let relocated = relocate(&code[7..], &lens[1..], old_ip + 7, far_ip).unwrap();
+        assert_eq!(relocated[..CALL_ABS_LEN], emit_call_abs(old_ip + 12 + 0x20));
+        // jnz +14; jmp [rip]
+        assert_eq!(relocated[CALL_ABS_LEN..CALL_ABS_LEN + 2], [0x75, 0x0E]);
+        assert_eq!(
+            relocated[CALL_ABS_LEN + 2..CALL_ABS_LEN + 2 + JMP_ABS_LEN],
+            emit_jmp_abs(old_ip + 14 + 0x10)
+        );
+
+        // loop -0x10
+        assert!(relocate(&[0xE2, 0xF0], &[2], old_ip, new_ip).is_err());
+    }
+
+    #[test]
+    fn test_build_injection() {
+        // sub rsp, 0x28; mov rbx, rcx
+        let original = hex_str_to_bytes!("48 83 EC 28 48 8B D9");
+        let custom = hex_str_to_bytes!("90 90");
+        let target = 0x1_4000_1000;
+        let cave_addr = 0x1_3FFF_0000;
+
+        let code = build_injection(target, &original, &[4, 3], cave_addr, &custom).unwrap();
+        assert_eq!(code.patch[..5], emit_jmp_rel32(target, cave_addr).unwrap());
+        assert_eq!(code.patch[5..], [0x90, 0x90]);
+        assert_eq!(code.cave[..2], custom);
+        assert_eq!(code.cave[2..9], original);
+        let back = decode_rip_operand(&code.cave[9..], cave_addr + 9).unwrap();
+        assert_eq!(back.target, target + original.len());
+
+        assert!(matches!(
+            build_injection(target, &original[..4], &[4], cave_addr, &custom),
+            Err(TrampolineError::TooShort(4))
+        ));
+    }
+}
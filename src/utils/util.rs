@@ -1,5 +1,6 @@
 use std::{
     collections::HashMap,
+    mem,
     sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
@@ -7,6 +8,8 @@ use std::{
 use windows::Win32::System::Threading::GetCurrentProcessId;
 use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
 
+use super::{resolve_offsets, try_resolve_offsets, with_memory_source, MemoryError, MemorySource};
+
 /// 设置指针所指向的值
 #[inline]
 pub unsafe fn set_value<T>(ptr: *mut T, value: T) {
@@ -21,7 +24,7 @@ where
     if base_addr.is_null() {
         return None;
     }
-    Some(*base_addr)
+    with_memory_source(|source| source.read_value(base_addr as usize))
 }
 
 /// 获取某个地址经过多级偏移后指向的值（的副本） \
@@ -32,28 +35,43 @@ where
 /// return: 若多级偏移时出现空指针，则返回None，否则返回Some(T)
 ///
 /// 注意：只能检查多次取值时出现的空指针问题。 \
-/// 若应用程序出现野指针可能触发异常
+/// 若应用程序出现野指针可能触发异常，此时应使用 [`try_get_value_with_offset`]
 pub fn get_value_with_offset<T>(base_addr: *const T, offsets: &[isize]) -> Option<T>
 where
     T: Copy,
 {
-    if base_addr.is_null() {
-        return None;
-    }
-    let mut addr = base_addr;
-    unsafe {
-        // 取值+偏移
-        // 取值后需要判断是否为空指针
-        for &offset in offsets.iter() {
-            let valptr = *(addr as *const *const T);
-            if valptr.is_null() {
-                return None;
-            }
-            addr = valptr.byte_offset(offset);
-        }
+    with_memory_source(|source| {
+        let addr = resolve_offsets(source, base_addr as usize, offsets)?;
         // 最后一级取值作为真实值返回
-        Some(*addr)
-    }
+        source.read_value(addr)
+    })
+}
+
+/// 获取某个地址经过多级偏移后指向的值（的副本），每一级取值前确认地址可读 \
+/// 取值算法与 [`get_value_with_offset`] 一致
+///
+/// 野指针不会导致游戏崩溃，失败时返回的错误指明出错的层级
+pub fn try_get_value_with_offset<T>(
+    base_addr: *const T,
+    offsets: &[isize],
+) -> Result<T, MemoryError>
+where
+    T: Copy,
+{
+    with_memory_source(|source| {
+        let addr = try_resolve_offsets(source, base_addr as usize, offsets)?;
+        source.try_read_value(addr)
+    })
+}
+
+/// 获取某个地址经过多级偏移后的地址，每一级取值前确认地址可读
+pub fn try_get_ptr_with_offset<T>(
+    base_addr: *const T,
+    offsets: &[isize],
+) -> Result<*const T, MemoryError> {
+    with_memory_source(|source| {
+        try_resolve_offsets(source, base_addr as usize, offsets).map(|addr| addr as *const T)
+    })
 }
 
 /// 获取某个地址经过多级偏移后指向的值的引用 \
@@ -62,42 +80,32 @@ where
 /// addr: 裸指针 \
 /// offsets: 多级偏移量（单位：byte） \
 /// return: 若多级偏移时出现空指针，则返回None，否则返回Some(T)
+///
+/// 仅当前进程的内存返回引用；数据源为内存快照或地址未按 `T` 对齐时返回None，
+/// 此时应使用 [`try_get_value_with_offset`] 读取副本。
 pub fn get_ref_with_offset<T>(base_addr: *const T, offsets: &[isize]) -> Option<&'static T> {
-    if base_addr.is_null() {
-        return None;
-    }
-    let mut addr = base_addr;
-    unsafe {
-        // 取值+偏移
-        // 取值后需要判断是否为空指针
-        for &offset in offsets.iter() {
-            let valptr = *(addr as *const *const T);
-            if valptr.is_null() {
-                return None;
-            }
-            addr = valptr.byte_offset(offset);
-        }
-        Some(addr.as_ref().unwrap())
-    }
+    with_memory_source(|source| {
+        let ptr = live_ptr::<T>(source, base_addr as usize, offsets)?;
+        unsafe { ptr.as_ref() }
+    })
 }
 
+/// 获取某个地址经过多级偏移后指向的值的可变引用，限制同 [`get_ref_with_offset`]
 pub fn get_mut_with_offset<T>(base_addr: *mut T, offsets: &[isize]) -> Option<&'static mut T> {
-    if base_addr.is_null() {
+    with_memory_source(|source| {
+        let ptr = live_ptr::<T>(source, base_addr as usize, offsets)?;
+        unsafe { ptr.as_mut() }
+    })
+}
+
+/// 当前进程内存中对齐的目标地址
+fn live_ptr<T>(source: &dyn MemorySource, base: usize, offsets: &[isize]) -> Option<*mut T> {
+    if !source.is_live() {
         return None;
     }
-    let mut addr = base_addr;
-    unsafe {
-        // 取值+偏移
-        // 取值后需要判断是否为空指针
-        for &offset in offsets.iter() {
-            let valptr = *(addr as *mut *mut T);
-            if valptr.is_null() {
-                return None;
-            }
-            addr = valptr.byte_offset(offset);
-        }
-        Some(addr.as_mut().unwrap())
-    }
+    let addr = resolve_offsets(source, base, offsets)?;
+    let ptr = source.host_ptr(addr, mem::size_of::<T>())? as *mut T;
+    ptr.is_aligned().then_some(ptr)
 }
 
 /// 获取某个地址经过多级偏移后的地址 \
@@ -110,23 +118,10 @@ pub fn get_mut_with_offset<T>(base_addr: *mut T, offsets: &[isize]) -> Option<&'
 /// 注意：只能检查多次取值时出现的空指针问题。 \
 /// 若应用程序出现野指针可能触发异常
 pub fn get_ptr_with_offset<T>(base_addr: *const T, offsets: &[isize]) -> Option<*const T> {
-    if base_addr.is_null() {
-        return None;
-    }
-    let mut addr = base_addr;
-    unsafe {
-        // 取值+偏移
-        // 取值后需要判断是否为空指针
-        for &offset in offsets.iter() {
-            let valptr = *(addr as *const *const T);
-            if valptr.is_null() {
-                return None;
-            }
-            addr = valptr.byte_offset(offset);
-        }
-        // 返回最后一级指针
-        Some(addr)
-    }
+    with_memory_source(|source| {
+        // 返回最后一级地址
+        resolve_offsets(source, base_addr as usize, offsets).map(|addr| addr as *const T)
+    })
 }
 
 /// 检查当前活动窗口是否为游戏窗口
@@ -0,0 +1,646 @@
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use thiserror::Error;
+
+use super::{with_memory_source, Comparison, FieldType, FieldValue, MemoryRegion, MemorySource};
+
+/// 逐块读取内存区域时每块的大小
+const READ_CHUNK_SIZE: usize = 0x100000;
+
+#[derive(Debug, Error, Clone, PartialEq)]
+pub enum ValueScanError {
+    #[error("no first scan has been performed")]
+    NotStarted,
+    #[error("condition {0} is not supported here")]
+    InvalidCondition(String),
+    #[error("failed to store unknown scan data: {0}")]
+    Storage(String),
+}
+
+impl From<std::io::Error> for ValueScanError {
+    fn from(e: std::io::Error) -> Self {
+        ValueScanError::Storage(e.to_string())
+    }
+}
+
+/// 搜索的数值类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ScanType {
+    Field(FieldType),
+    /// 字符串，`utf16` 为true时按UTF-16LE编码搜索
+    String {
+        utf16: bool,
+    },
+}
+
+/// 搜索条件
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScanCondition {
+    /// 等于给定值
+    Exact(FieldValue),
+    /// 位于 `[min, max]` 之间
+    Range(FieldValue, FieldValue),
+    /// 等于给定字符串
+    Text(String),
+    /// 未知初始值，仅用于首次搜索，记录所有地址的当前值
+    Unknown,
+    Changed,
+    Unchanged,
+    Increased,
+    Decreased,
+}
+
+impl ScanCondition {
+    /// 是否需要与上一次的值比较
+    pub fn is_relative(&self) -> bool {
+        matches!(
+            self,
+            ScanCondition::Changed
+                | ScanCondition::Unchanged
+                | ScanCondition::Increased
+                | ScanCondition::Decreased
+        )
+    }
+
+    fn comparison(&self) -> Option<Comparison> {
+        Some(match *self {
+            ScanCondition::Exact(value) => Comparison::Equals(value),
+            ScanCondition::Range(min, max) => Comparison::Between(min, max),
+            ScanCondition::Changed => Comparison::Changed,
+            ScanCondition::Unchanged => Comparison::Unchanged,
+            ScanCondition::Increased => Comparison::Increased,
+            ScanCondition::Decreased => Comparison::Decreased,
+            ScanCondition::Text(_) | ScanCondition::Unknown => return None,
+        })
+    }
+}
+
+/// 搜索结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanResult {
+    pub address: usize,
+    /// 最近一次搜索时的值
+    pub bytes: Vec<u8>,
+}
+
+impl ScanResult {
+    /// 按数值类型解释最近一次搜索时的值，字符串类型返回None
+    pub fn value(&self, scan_type: ScanType) -> Option<FieldValue> {
+        match scan_type {
+            ScanType::Field(field_type) => Some(field_type.decode(&self.bytes)),
+            ScanType::String { .. } => None,
+        }
+    }
+
+    /// 按字符串解释最近一次搜索时的值
+    pub fn text(&self, scan_type: ScanType) -> Option<String> {
+        match scan_type {
+            ScanType::String { utf16: false } => {
+                Some(String::from_utf8_lossy(&self.bytes).to_string())
+            }
+            ScanType::String { utf16: true } => {
+                let wide: Vec<u16> = self
+                    .bytes
+                    .chunks_exact(2)
+                    .map(|c| u16::from_le_bytes([c[0], c[1]]))
+                    .collect();
+                Some(String::from_utf16_lossy(&wide))
+            }
+            ScanType::Field(_) => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum ScanState {
+    NotStarted,
+    /// 未知初始值：所有区域的副本保存在临时文件中，下一次搜索时再生成结果
+    Unknown(UnknownCopy),
+    Results(Vec<ScanResult>),
+}
+
+/// 逐块读取的内存
+///
+/// `data` 比 `len` 多出最多 `value_size - 1` 字节，用于匹配跨越块边界的值。
+struct Chunk {
+    address: usize,
+    /// 相对区域起点的偏移，用于计算对齐
+    region_offset: usize,
+    len: usize,
+    data: Vec<u8>,
+}
+
+/// 保存在临时文件中的块
+#[derive(Debug, Clone, Copy)]
+struct StoredChunk {
+    address: usize,
+    region_offset: usize,
+    len: usize,
+    data_len: usize,
+    file_offset: u64,
+}
+
+/// 未知初始值搜索保存的内存副本
+///
+/// 可写内存可能有数GB，副本写入临时文件而不是保留在内存中，搜索结束后删除。
+#[derive(Debug, Clone)]
+struct UnknownCopy {
+    file: Arc<TempFile>,
+    chunks: Vec<StoredChunk>,
+}
+
+#[derive(Debug)]
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn create() -> Result<(Self, File), ValueScanError> {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "mhw_toolkit_value_scan_{}_{}.bin",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = File::create(&path)?;
+        Ok((Self(path), file))
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+/// 与CE类似的数值搜索
+///
+/// 首次搜索遍历数据源中所有可写区域，之后的搜索只检查上一次的结果，逐步缩小范围。
+/// 数据源可以是游戏进程（[`LiveMemory`](super::LiveMemory)），也可以是测试用的内存快照。
+///
+/// ```ignore
+/// let mut scanner = ValueScanner::new(ScanType::Field(FieldType::F32));
+/// scanner.first_scan(ScanCondition::Unknown)?;
+/// // 怪物受到伤害后
+/// scanner.next_scan(ScanCondition::Decreased)?;
+/// for result in scanner.results() {
+///     println!("0x{:X}", result.address);
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ValueScanner {
+    scan_type: ScanType,
+    alignment: usize,
+    /// 字符串搜索时为首次搜索的字符串长度
+    value_size: usize,
+    state: ScanState,
+}
+
+impl ValueScanner {
+    /// 创建搜索，数值默认按自身大小对齐（最多4字节），字符串不对齐
+    pub fn new(scan_type: ScanType) -> Self {
+        let (value_size, alignment) = match scan_type {
+            ScanType::Field(field_type) => (field_type.size(), field_type.size().min(4)),
+            ScanType::String { .. } => (0, 1),
+        };
+        Self {
+            scan_type,
+            alignment,
+            value_size,
+            state: ScanState::NotStarted,
+        }
+    }
+
+    pub fn with_alignment(mut self, alignment: usize) -> Self {
+        self.alignment = alignment.max(1);
+        self
+    }
+
+    pub fn scan_type(&self) -> ScanType {
+        self.scan_type
+    }
+
+    /// 当前的结果，未知初始值搜索后、下一次搜索前为空
+    pub fn results(&self) -> &[ScanResult] {
+        match &self.state {
+            ScanState::Results(results) => results,
+            _ => &[],
+        }
+    }
+
+    /// 当前候选地址的数量
+    pub fn count(&self) -> usize {
+        match &self.state {
+            ScanState::NotStarted => 0,
+            ScanState::Unknown(copy) => copy
+                .chunks
+                .iter()
+                .map(|c| self.offsets(c.region_offset, c.len, c.data_len).count())
+                .sum(),
+            ScanState::Results(results) => results.len(),
+        }
+    }
+
+    /// 清空结果，重新开始搜索
+    pub fn reset(&mut self) {
+        self.state = ScanState::NotStarted;
+    }
+
+    /// 在当前线程的数据源上进行首次搜索，返回结果数量
+    pub fn first_scan(&mut self, condition: ScanCondition) -> Result<usize, ValueScanError> {
+        with_memory_source(|source| self.first_scan_in(source, condition))
+    }
+
+    /// 在当前线程的数据源上进行下一次搜索，返回剩余结果数量
+    pub fn next_scan(&mut self, condition: ScanCondition) -> Result<usize, ValueScanError> {
+        with_memory_source(|source| self.next_scan_in(source, condition))
+    }
+
+    /// 在指定数据源上进行首次搜索
+    pub fn first_scan_in(
+        &mut self,
+        source: &dyn MemorySource,
+        condition: ScanCondition,
+    ) -> Result<usize, ValueScanError> {
+        if condition.is_relative() {
+            return Err(ValueScanError::InvalidCondition(format!(
+                "{:?} in first scan",
+                condition
+            )));
+        }
+        let needle = self.prepare(&condition)?;
+
+        let regions: Vec<MemoryRegion> = source
+            .committed_regions()
+            .into_iter()
+            .filter(|r| r.readable && r.writable)
+            .collect();
+        if condition == ScanCondition::Unknown {
+            let copy = self.store_regions(source, &regions)?;
+            self.state = ScanState::Unknown(copy);
+            return Ok(self.count());
+        }
+
+        let mut results = Vec::new();
+        for region in &regions {
+            for chunk in read_chunks(source, region, self.value_size) {
+                for offset in self.offsets(chunk.region_offset, chunk.len, chunk.data.len()) {
+                    let value = &chunk.data[offset..offset + self.value_size];
+                    if self.matches(&condition, needle.as_deref(), None, value) {
+                        results.push(ScanResult {
+                            address: chunk.address + offset,
+                            bytes: value.to_vec(),
+                        });
+                    }
+                }
+            }
+        }
+        self.state = ScanState::Results(results);
+        Ok(self.count())
+    }
+
+    /// 在指定数据源上进行下一次搜索，无法读取的地址被移除
+    pub fn next_scan_in(
+        &mut self,
+        source: &dyn MemorySource,
+        condition: ScanCondition,
+    ) -> Result<usize, ValueScanError> {
+        if condition == ScanCondition::Unknown {
+            return Err(ValueScanError::InvalidCondition(
+                "Unknown in next scan".to_string(),
+            ));
+        }
+        let needle = match (&condition, self.scan_type) {
+            (ScanCondition::Text(_), ScanType::Field(_)) => {
+                return Err(ValueScanError::InvalidCondition(
+                    "Text for a numeric scan".to_string(),
+                ))
+            }
+            (ScanCondition::Text(text), ScanType::String { .. }) => {
+                let needle = self.encode_text(text);
+                if needle.len() != self.value_size {
+                    return Err(ValueScanError::InvalidCondition(
+                        "text length differs from the first scan".to_string(),
+                    ));
+                }
+                Some(needle)
+            }
+            (ScanCondition::Changed | ScanCondition::Unchanged, ScanType::String { .. }) => None,
+            (_, ScanType::String { .. }) => {
+                return Err(ValueScanError::InvalidCondition(format!(
+                    "{:?} in string scan",
+                    condition
+                )))
+            }
+            _ => None,
+        };
+
+        // 结果计算完成后才替换状态，读取临时文件失败时保留上一次的结果
+        let results = match &self.state {
+            ScanState::NotStarted => return Err(ValueScanError::NotStarted),
+            ScanState::Unknown(copy) => {
+                let mut file = File::open(&copy.file.0)?;
+                let mut results = Vec::new();
+                let mut old = Vec::new();
+                for stored in &copy.chunks {
+                    let Some(new) = read_checked(source, stored.address, stored.data_len) else {
+                        continue;
+                    };
+                    old.resize(stored.data_len, 0);
+                    file.seek(SeekFrom::Start(stored.file_offset))?;
+                    file.read_exact(&mut old)?;
+                    for offset in self.offsets(stored.region_offset, stored.len, stored.data_len) {
+                        let range = offset..offset + self.value_size;
+                        if self.matches(
+                            &condition,
+                            needle.as_deref(),
+                            Some(&old[range.clone()]),
+                            &new[range.clone()],
+                        ) {
+                            results.push(ScanResult {
+                                address: stored.address + offset,
+                                bytes: new[range].to_vec(),
+                            });
+                        }
+                    }
+                }
+                results
+            }
+            ScanState::Results(_) => {
+                let ScanState::Results(results) =
+                    std::mem::replace(&mut self.state, ScanState::NotStarted)
+                else {
+                    unreachable!()
+                };
+                let mut buf = vec![0_u8; self.value_size];
+                results
+                    .into_iter()
+                    .filter_map(|mut result| {
+                        // 上一次搜索后区域可能已被释放
+                        if !source.is_readable(result.address, buf.len())
+                            || !source.read_bytes(result.address, &mut buf)
+                        {
+                            return None;
+                        }
+                        if !self.matches(&condition, needle.as_deref(), Some(&result.bytes), &buf) {
+                            return None;
+                        }
+                        result.bytes.copy_from_slice(&buf);
+                        Some(result)
+                    })
+                    .collect()
+            }
+        };
+        self.state = ScanState::Results(results);
+        Ok(self.count())
+    }
+
+    /// 检查条件与类型是否匹配，字符串搜索时确定字符串长度并返回编码后的字符串
+    fn prepare(&mut self, condition: &ScanCondition) -> Result<Option<Vec<u8>>, ValueScanError> {
+        match (self.scan_type, condition) {
+            (ScanType::String { .. }, ScanCondition::Text(text)) => {
+                let needle = self.encode_text(text);
+                if needle.is_empty() {
+                    return Err(ValueScanError::InvalidCondition("empty text".to_string()));
+                }
+                self.value_size = needle.len();
+                Ok(Some(needle))
+            }
+            (ScanType::String { .. }, _) => Err(ValueScanError::InvalidCondition(format!(
+                "{:?} in first string scan",
+                condition
+            ))),
+            (ScanType::Field(_), ScanCondition::Text(_)) => Err(ValueScanError::InvalidCondition(
+                "Text for a numeric scan".to_string(),
+            )),
+            _ => Ok(None),
+        }
+    }
+
+    fn matches(
+        &self,
+        condition: &ScanCondition,
+        needle: Option<&[u8]>,
+        old: Option<&[u8]>,
+        new: &[u8],
+    ) -> bool {
+        match self.scan_type {
+            ScanType::String { .. } => match condition {
+                ScanCondition::Text(_) => needle == Some(new),
+                ScanCondition::Changed => old.is_some_and(|old| old != new),
+                ScanCondition::Unchanged => old.is_some_and(|old| old == new),
+                _ => false,
+            },
+            ScanType::Field(field_type) => {
+                let Some(comparison) = condition.comparison() else {
+                    return false;
+                };
+                let new = field_type.decode(new);
+                // 首次搜索与精确、范围搜索只使用新值
+                let old = old.map_or(new, |old| field_type.decode(old));
+                comparison.matches(old, new)
+            }
+        }
+    }
+
+    /// 将所有区域的可读部分写入临时文件
+    fn store_regions(
+        &self,
+        source: &dyn MemorySource,
+        regions: &[MemoryRegion],
+    ) -> Result<UnknownCopy, ValueScanError> {
+        let (temp, file) = TempFile::create()?;
+        let mut writer = BufWriter::new(file);
+        let mut chunks = Vec::new();
+        let mut file_offset = 0;
+        for region in regions {
+            for chunk in read_chunks(source, region, self.value_size) {
+                writer.write_all(&chunk.data)?;
+                chunks.push(StoredChunk {
+                    address: chunk.address,
+                    region_offset: chunk.region_offset,
+                    len: chunk.len,
+                    data_len: chunk.data.len(),
+                    file_offset,
+                });
+                file_offset += chunk.data.len() as u64;
+            }
+        }
+        writer.flush()?;
+        Ok(UnknownCopy {
+            file: Arc::new(temp),
+            chunks,
+        })
+    }
+
+    /// 块内按区域起点对齐的值的偏移，只包含起点位于块的前 `len` 字节内的值
+    fn offsets(
+        &self,
+        region_offset: usize,
+        len: usize,
+        data_len: usize,
+    ) -> impl Iterator<Item = usize> {
+        let first = (self.alignment - region_offset % self.alignment) % self.alignment;
+        let end = len.min((data_len + 1).saturating_sub(self.value_size.max(1)));
+        (first..end).step_by(self.alignment)
+    }
+
+    fn encode_text(&self, text: &str) -> Vec<u8> {
+        match self.scan_type {
+            ScanType::String { utf16: true } => {
+                text.encode_utf16().flat_map(|c| c.to_le_bytes()).collect()
+            }
+            _ => text.as_bytes().to_vec(),
+        }
+    }
+}
+
+/// 逐块读取区域，跳过不可读的块
+///
+/// 每块额外读取 `value_size - 1` 字节，区域可能在枚举后被释放，读取前逐块确认可读。
+fn read_chunks<'a>(
+    source: &'a dyn MemorySource,
+    region: &'a MemoryRegion,
+    value_size: usize,
+) -> impl Iterator<Item = Chunk> + 'a {
+    let overlap = value_size.saturating_sub(1);
+    (0..region.size)
+        .step_by(READ_CHUNK_SIZE)
+        .filter_map(move |region_offset| {
+            let address = region.base + region_offset;
+            let remaining = region.size - region_offset;
+            let len = READ_CHUNK_SIZE.min(remaining);
+            let data = read_checked(source, address, (len + overlap).min(remaining))
+                .or_else(|| read_checked(source, address, len))?;
+            Some(Chunk {
+                address,
+                region_offset,
+                len,
+                data,
+            })
+        })
+}
+
+/// 确认可读后读取 `[addr, addr + len)`
+fn read_checked(source: &dyn MemorySource, addr: usize, len: usize) -> Option<Vec<u8>> {
+    if !source.is_readable(addr, len) {
+        return None;
+    }
+    let mut bytes = vec![0_u8; len];
+    source.read_bytes(addr, &mut bytes).then_some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::SparseMemory;
+
+    #[test]
+    fn test_value_scan() {
+        let mut memory = SparseMemory::new();
+        memory.insert_zeroed(0x1000, 0x100);
+        memory.insert_zeroed(0x8000, 0x100);
+        memory.write_value(0x1010_usize, 150.0_f32);
+        memory.write_value(0x8020_usize, 150.0_f32);
+        memory.write_value(0x8040_usize, 120.0_f32);
+
+        let mut scanner = ValueScanner::new(ScanType::Field(FieldType::F32));
+        assert_eq!(
+            scanner.first_scan_in(&memory, ScanCondition::Exact(FieldValue::Float(150.0))),
+            Ok(2)
+        );
+        memory.write_value(0x8020_usize, 90.0_f32);
+        assert_eq!(
+            scanner.next_scan_in(&memory, ScanCondition::Decreased),
+            Ok(1)
+        );
+        assert_eq!(scanner.results()[0].address, 0x8020);
+        assert_eq!(
+            scanner.results()[0].value(scanner.scan_type()),
+            Some(FieldValue::Float(90.0))
+        );
+
+        // 未知初始值
+        let mut scanner = ValueScanner::new(ScanType::Field(FieldType::I32));
+        assert_eq!(
+            scanner.first_scan_in(&memory, ScanCondition::Unknown),
+            Ok(0x80)
+        );
+        // 读取临时文件失败时保留未知初始值的状态
+        let ScanState::Unknown(copy) = &scanner.state else {
+            unreachable!()
+        };
+        let path = copy.file.0.clone();
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(
+            scanner.next_scan_in(&memory, ScanCondition::Changed),
+            Err(ValueScanError::Storage(_))
+        ));
+        assert_eq!(scanner.count(), 0x80);
+        std::fs::write(&path, data).unwrap();
+        memory.write_value(0x1080_usize, 7_i32);
+        assert_eq!(scanner.next_scan_in(&memory, ScanCondition::Changed), Ok(1));
+        assert_eq!(
+            scanner.next_scan_in(
+                &memory,
+                ScanCondition::Range(FieldValue::Int(5), FieldValue::Int(10))
+            ),
+            Ok(1)
+        );
+        assert_eq!(
+            scanner.next_scan_in(&memory, ScanCondition::Increased),
+            Ok(0)
+        );
+
+        // 超出f64精度的整数按原值比较
+        memory.write_value(0x1090_usize, (1_i64 << 53) + 1);
+        let mut scanner = ValueScanner::new(ScanType::Field(FieldType::I64));
+        assert_eq!(
+            scanner.first_scan_in(&memory, ScanCondition::Exact(FieldValue::Int(1 << 53))),
+            Ok(0)
+        );
+        assert_eq!(
+            scanner.first_scan_in(
+                &memory,
+                ScanCondition::Exact(FieldValue::Int((1 << 53) + 1))
+            ),
+            Ok(1)
+        );
+
+        memory.write_value(0x80A1_usize, *b"Hunter");
+        let mut scanner = ValueScanner::new(ScanType::String { utf16: false });
+        assert_eq!(
+            scanner.first_scan_in(&memory, ScanCondition::Text("Hunter".to_string())),
+            Ok(1)
+        );
+        assert_eq!(scanner.results()[0].address, 0x80A1);
+        assert_eq!(
+            scanner.next_scan_in(&memory, ScanCondition::Unchanged),
+            Ok(1)
+        );
+        assert!(matches!(
+            scanner.first_scan_in(&memory, ScanCondition::Increased),
+            Err(ValueScanError::InvalidCondition(_))
+        ));
+        // 字符串不支持数值条件，结果不变
+        for condition in [
+            ScanCondition::Increased,
+            ScanCondition::Decreased,
+            ScanCondition::Exact(FieldValue::Int(0)),
+            ScanCondition::Range(FieldValue::Int(0), FieldValue::Int(1)),
+        ] {
+            assert!(matches!(
+                scanner.next_scan_in(&memory, condition),
+                Err(ValueScanError::InvalidCondition(_))
+            ));
+        }
+        assert_eq!(scanner.count(), 1);
+    }
+}
@@ -0,0 +1,296 @@
+use std::{
+    ops::Sub,
+    time::{Duration, Instant},
+};
+
+use super::PointerPath;
+
+type ValueAccessor<T> = Box<dyn Fn() -> Option<T> + Send + 'static>;
+type ChangeCallback<T> = Box<dyn Fn(&T, &T) + Send + 'static>;
+type ChangeFilter<T> = Box<dyn Fn(&T, &T) -> bool + Send + 'static>;
+
+/// 值监视器
+///
+/// 按固定间隔读取一个值，与上次记录的值不同时调用回调，参数为旧值与新值。
+/// 读取失败（例如指针链暂时为空）时保留上次的值，恢复后继续比较。
+///
+/// 与 [`KeyBind`](crate::keys::KeyBind) 相同，需要在插件的更新循环中调用 [`Watch::update`]。
+///
+/// ```ignore
+/// let mut watch = Watch::new(|| Quest::new_static().map(|q| q.quest_state()), Duration::from_millis(100))
+///     .on_change(|old, new| info!("quest state {} -> {}", old, new));
+/// // 在更新循环中
+/// watch.update();
+/// ```
+pub struct Watch<T> {
+    accessor: ValueAccessor<T>,
+    interval: Duration,
+    debounce: Duration,
+    filter: Option<ChangeFilter<T>>,
+    callbacks: Vec<ChangeCallback<T>>,
+    last_poll: Option<Instant>,
+    /// 上次触发回调（或首次读取）时的值
+    value: Option<T>,
+    /// 等待防抖的新值及其首次出现的时间
+    pending: Option<(T, Instant)>,
+    available: bool,
+}
+
+impl<T> Watch<T>
+where
+    T: Clone + PartialEq + Send + 'static,
+{
+    /// 由取值函数创建，返回None表示暂时无法读取
+    pub fn new<F>(accessor: F, interval: Duration) -> Self
+    where
+        F: Fn() -> Option<T> + Send + 'static,
+    {
+        Self {
+            accessor: Box::new(accessor),
+            interval,
+            debounce: Duration::ZERO,
+            filter: None,
+            callbacks: Vec::new(),
+            last_poll: None,
+            value: None,
+            pending: None,
+            available: false,
+        }
+    }
+
+    /// 监视指针路径指向的值
+    pub fn path(path: PointerPath, interval: Duration) -> Self
+    where
+        T: Copy,
+    {
+        Self::new(move || path.try_read::<T>().ok(), interval)
+    }
+
+    /// 值变化时调用，参数为旧值与新值
+    pub fn on_change<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&T, &T) + Send + 'static,
+    {
+        self.callbacks.push(Box::new(f));
+        self
+    }
+
+    /// 新值需保持不变一段时间后才视为变化
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// 仅当 `filter(旧值, 新值)` 为true时视为变化
+    pub fn with_filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&T, &T) -> bool + Send + 'static,
+    {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// 仅当新值与上次触发时的值相差不小于 `threshold` 时视为变化
+    pub fn with_threshold(self, threshold: T) -> Self
+    where
+        T: PartialOrd + Sub<Output = T>,
+    {
+        self.with_filter(move |old, new| {
+            let diff = if old > new {
+                old.clone() - new.clone()
+            } else {
+                new.clone() - old.clone()
+            };
+            diff >= threshold
+        })
+    }
+
+    /// 上次触发回调时的值
+    pub fn value(&self) -> Option<&T> {
+        self.value.as_ref()
+    }
+
+    /// 最近一次读取是否成功
+    pub fn is_available(&self) -> bool {
+        self.available
+    }
+
+    /// 距上次读取超过间隔时读取并比较
+    pub fn update(&mut self) {
+        self.update_at(Instant::now());
+    }
+
+    /// 立即读取并比较，忽略间隔
+    pub fn poll(&mut self) {
+        self.poll_at(Instant::now());
+    }
+
+    fn update_at(&mut self, now: Instant) {
+        if let Some(last_poll) = self.last_poll {
+            if now.duration_since(last_poll) < self.interval {
+                return;
+            }
+        }
+        self.poll_at(now);
+    }
+
+    fn poll_at(&mut self, now: Instant) {
+        self.last_poll = Some(now);
+        let Some(current) = (self.accessor)() else {
+            // 暂时无法读取，保留上次的值
+            self.available = false;
+            self.pending = None;
+            return;
+        };
+        self.available = true;
+
+        let Some(old) = self.value.as_ref() else {
+            self.value = Some(current);
+            return;
+        };
+        let changed = old != &current && self.filter.as_ref().is_none_or(|f| f(old, &current));
+        if !changed {
+            self.pending = None;
+            return;
+        }
+
+        if !self.debounce.is_zero() {
+            match &self.pending {
+                Some((value, since)) if value == &current => {
+                    if now.duration_since(*since) < self.debounce {
+                        return;
+                    }
+                }
+                _ => {
+                    self.pending = Some((current, now));
+                    return;
+                }
+            }
+        }
+
+        self.pending = None;
+        let old = self.value.replace(current.clone()).unwrap();
+        for callback in self.callbacks.iter() {
+            callback(&old, &current);
+        }
+    }
+}
+
+trait Watcher: Send {
+    fn update_at(&mut self, now: Instant);
+}
+
+impl<T> Watcher for Watch<T>
+where
+    T: Clone + PartialEq + Send + 'static,
+{
+    fn update_at(&mut self, now: Instant) {
+        Watch::update_at(self, now);
+    }
+}
+
+/// [`WatchSet::add`] 返回的标识，用于移除监视器
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WatchId(usize);
+
+/// 一组不同类型的值监视器，统一更新
+#[derive(Default)]
+pub struct WatchSet {
+    watchers: Vec<(WatchId, Box<dyn Watcher>)>,
+    next_id: usize,
+}
+
+impl WatchSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add<T>(&mut self, watch: Watch<T>) -> WatchId
+    where
+        T: Clone + PartialEq + Send + 'static,
+    {
+        let id = WatchId(self.next_id);
+        self.next_id += 1;
+        self.watchers.push((id, Box::new(watch)));
+        id
+    }
+
+    /// 移除监视器，成功返回true
+    pub fn remove(&mut self, id: WatchId) -> bool {
+        let len = self.watchers.len();
+        self.watchers.retain(|(watch_id, _)| *watch_id != id);
+        self.watchers.len() != len
+    }
+
+    pub fn len(&self) -> usize {
+        self.watchers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.watchers.is_empty()
+    }
+
+    /// 更新所有到达间隔的监视器
+    pub fn update(&mut self) {
+        let now = Instant::now();
+        for (_, watcher) in self.watchers.iter_mut() {
+            watcher.update_at(now);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    #[test]
+    fn test_watch() {
+        let source = Arc::new(Mutex::new(Some(100.0_f32)));
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let (source_clone, events_clone) = (source.clone(), events.clone());
+        let mut watch = Watch::new(move || *source_clone.lock().unwrap(), Duration::ZERO)
+            .with_threshold(5.0)
+            .with_debounce(Duration::from_millis(100))
+            .on_change(move |old, new| events_clone.lock().unwrap().push((*old, *new)));
+        let set = |value: Option<f32>| *source.lock().unwrap() = value;
+
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        watch.update_at(at(0));
+        assert_eq!(watch.value(), Some(&100.0));
+
+        // 小于阈值
+        set(Some(97.0));
+        watch.update_at(at(10));
+        // 指针暂时为空时保留旧值
+        set(None);
+        watch.update_at(at(20));
+        assert!(!watch.is_available());
+        assert_eq!(watch.value(), Some(&100.0));
+
+        // 防抖期间不触发
+        set(Some(80.0));
+        watch.update_at(at(30));
+        watch.update_at(at(100));
+        assert!(events.lock().unwrap().is_empty());
+        watch.update_at(at(130));
+        assert_eq!(*events.lock().unwrap(), vec![(100.0, 80.0)]);
+
+        // 防抖期间值又变回来
+        set(Some(60.0));
+        watch.update_at(at(140));
+        set(Some(80.0));
+        watch.update_at(at(300));
+        assert_eq!(events.lock().unwrap().len(), 1);
+
+        let mut set = WatchSet::new();
+        let id = set.add(watch);
+        set.add(Watch::new(|| Some(1_i32), Duration::from_millis(50)));
+        set.update();
+        assert!(set.remove(id));
+        assert!(!set.remove(id));
+        assert_eq!(set.len(), 1);
+    }
+}
@@ -0,0 +1,561 @@
+use thiserror::Error;
+
+use super::with_memory_source;
+
+/// x86-64指令的最大长度
+pub const MAX_INSTRUCTION_LEN: usize = 15;
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    #[error("instruction is truncated")]
+    Truncated,
+    #[error("unsupported instruction: {0}")]
+    Unsupported(String),
+    #[error("failed to read instruction at 0x{0:X}")]
+    Unreadable(usize),
+}
+
+/// 相对寻址操作数的类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RipOperandKind {
+    /// `call rel32`
+    Call,
+    /// `jmp rel32` / `jmp rel8`
+    Jmp,
+    /// 条件跳转
+    Jcc,
+    /// `loop` / `jrcxz` 等只有rel8形式的跳转
+    Loop,
+    /// `[rip+disp32]` 内存操作数，例如 `mov`、`lea`、`cmp`，目标为内存地址
+    Memory,
+    /// `call [rip+disp32]`，目标为函数指针所在地址
+    IndirectCall,
+    /// `jmp [rip+disp32]`，目标为函数指针所在地址
+    IndirectJmp,
+}
+
+impl RipOperandKind {
+    /// 是否为相对跳转或调用
+    pub fn is_relative_branch(&self) -> bool {
+        matches!(
+            self,
+            RipOperandKind::Call | RipOperandKind::Jmp | RipOperandKind::Jcc | RipOperandKind::Loop
+        )
+    }
+}
+
+/// 解码得到的相对寻址操作数
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RipOperand {
+    pub kind: RipOperandKind,
+    /// 指令地址
+    pub address: usize,
+    /// 指令长度
+    pub len: usize,
+    /// 偏移量在指令中的位置
+    pub disp_offset: usize,
+    /// 偏移量
+    pub disp: i32,
+    /// 绝对目标地址
+    pub target: usize,
+}
+
+/// 解码得到的一条指令
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Instruction {
+    /// 指令地址
+    pub address: usize,
+    /// 指令长度
+    pub len: usize,
+    /// 相对寻址操作数，包括相对跳转与 `[rip+disp32]`
+    pub rip_operand: Option<RipOperand>,
+}
+
+impl Instruction {
+    /// 是否为相对跳转或调用，这类指令搬移后需要修正目标
+    pub fn is_relative_branch(&self) -> bool {
+        self.rip_operand
+            .is_some_and(|operand| operand.kind.is_relative_branch())
+    }
+
+    /// 是否包含 `[rip+disp32]` 内存操作数
+    pub fn is_rip_relative(&self) -> bool {
+        self.rip_operand
+            .is_some_and(|operand| !operand.kind.is_relative_branch())
+    }
+}
+
+/// 连续的若干条完整指令
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CodeSpan {
+    pub instructions: Vec<Instruction>,
+    /// 总长度
+    pub len: usize,
+}
+
+impl CodeSpan {
+    /// 每条指令的长度
+    pub fn lens(&self) -> Vec<usize> {
+        self.instructions.iter().map(|i| i.len).collect()
+    }
+
+    pub fn has_relative_branch(&self) -> bool {
+        self.instructions.iter().any(|i| i.is_relative_branch())
+    }
+
+    pub fn has_rip_relative(&self) -> bool {
+        self.instructions.iter().any(|i| i.is_rip_relative())
+    }
+
+    fn push(&mut self, instruction: Instruction) {
+        self.len += instruction.len;
+        self.instructions.push(instruction);
+    }
+}
+
+/// 解码 `ip` 处的一条指令，得到其长度与相对寻址操作数
+///
+/// 支持通用指令、SSE/AVX（VEX与EVEX编码）与x87指令，仅计算长度，不解析操作语义。
+pub fn decode_instruction(code: &[u8], ip: usize) -> Result<Instruction, DecodeError> {
+    let byte = |i: usize| code.get(i).copied().ok_or(DecodeError::Truncated);
+
+    let mut pos = 0;
+    let mut operand_size_override = false;
+    let mut address_size_override = false;
+    loop {
+        match byte(pos)? {
+            0x66 => operand_size_override = true,
+            0x67 => address_size_override = true,
+            0xF0 | 0xF2 | 0xF3 | 0x2E | 0x36 | 0x3E | 0x26 | 0x64 | 0x65 => {}
+            _ => break,
+        }
+        pos += 1;
+        if pos >= MAX_INSTRUCTION_LEN {
+            return Err(unsupported(code, 0, pos));
+        }
+    }
+    let mut rex_w = false;
+    if (0x40..=0x4F).contains(&byte(pos)?) {
+        rex_w = byte(pos)? & 0x08 != 0;
+        pos += 1;
+    }
+
+    let imm_z = if operand_size_override { 2 } else { 4 };
+    let opcode_pos = pos;
+    let opcode = byte(pos)?;
+    pos += 1;
+
+    // (是否有ModRM, 立即数长度, 相对跳转类型)
+    let (has_modrm, mut imm_len, branch) = match opcode {
+        0x0F => {
+            let opcode2 = byte(pos)?;
+            pos += 1;
+            match opcode2 {
+                0x38 => {
+                    pos += 1;
+                    (true, 0, None)
+                }
+                0x3A => {
+                    pos += 1;
+                    (true, 1, None)
+                }
+                0x80..=0x8F => (false, 4, Some(RipOperandKind::Jcc)),
+                _ if two_byte_has_modrm(opcode2) => (true, two_byte_imm_len(opcode2), None),
+                _ => (false, 0, None),
+            }
+        }
+        // VEX / EVEX
+        0xC4 | 0xC5 | 0x62 => {
+            let map = match opcode {
+                0xC5 => {
+                    pos += 1;
+                    1
+                }
+                0xC4 => {
+                    let map = byte(pos)? & 0x1F;
+                    pos += 2;
+                    map
+                }
+                _ => {
+                    let map = byte(pos)? & 0x07;
+                    pos += 3;
+                    map
+                }
+            };
+            let opcode2 = byte(pos)?;
+            pos += 1;
+            let imm_len = match map {
+                1 => two_byte_imm_len(opcode2),
+                2 => 0,
+                3 => 1,
+                _ => return Err(unsupported(code, opcode_pos, pos - opcode_pos)),
+            };
+            (true, imm_len, None)
+        }
+        0x70..=0x7F => (false, 1, Some(RipOperandKind::Jcc)),
+        0xE0..=0xE3 => (false, 1, Some(RipOperandKind::Loop)),
+        0xE8 => (false, 4, Some(RipOperandKind::Call)),
+        0xE9 => (false, 4, Some(RipOperandKind::Jmp)),
+        0xEB => (false, 1, Some(RipOperandKind::Jmp)),
+        0xB8..=0xBF if rex_w => (false, 8, None),
+        0xA0..=0xA3 => (false, if address_size_override { 4 } else { 8 }, None),
+        _ => match one_byte_form(opcode, imm_z) {
+            Some((has_modrm, imm_len)) => (has_modrm, imm_len, None),
+            None => return Err(unsupported(code, opcode_pos, 1)),
+        },
+    };
+
+    let mut rip_disp_offset = None;
+    let mut reg = 0;
+    if has_modrm {
+        let modrm = byte(pos)?;
+        let (mode, rm) = (modrm >> 6, modrm & 0b111);
+        reg = (modrm >> 3) & 0b111;
+        pos += 1;
+        if mode != 3 && rm == 4 {
+            let sib = byte(pos)?;
+            pos += 1;
+            if mode == 0 && sib & 0b111 == 5 {
+                pos += 4;
+            }
+        }
+        match mode {
+            0 if rm == 5 => {
+                rip_disp_offset = Some(pos);
+                pos += 4;
+            }
+            1 => pos += 1,
+            2 => pos += 4,
+            _ => {}
+        }
+        // test r/m, imm
+        if (opcode == 0xF6 || opcode == 0xF7) && reg < 2 {
+            imm_len = if opcode == 0xF6 { 1 } else { imm_z };
+        }
+    }
+
+    let disp_pos = pos;
+    let len = pos + imm_len;
+    if len > MAX_INSTRUCTION_LEN {
+        return Err(unsupported(code, 0, code.len().min(MAX_INSTRUCTION_LEN)));
+    }
+    if code.len() < len {
+        return Err(DecodeError::Truncated);
+    }
+
+    let operand = |kind, disp_offset: usize, disp: i32| RipOperand {
+        kind,
+        address: ip,
+        len,
+        disp_offset,
+        disp,
+        target: ip.wrapping_add(len).wrapping_add_signed(disp as isize),
+    };
+    let read_disp32 = |i: usize| i32::from_le_bytes(code[i..i + 4].try_into().unwrap());
+    let rip_operand = match (branch, rip_disp_offset) {
+        (Some(kind), _) => Some(if imm_len == 1 {
+            operand(kind, disp_pos, code[disp_pos] as i8 as i32)
+        } else {
+            operand(kind, disp_pos, read_disp32(disp_pos))
+        }),
+        (None, Some(disp_offset)) => {
+            let kind = match (opcode, reg) {
+                (0xFF, 2) => RipOperandKind::IndirectCall,
+                (0xFF, 4) => RipOperandKind::IndirectJmp,
+                _ => RipOperandKind::Memory,
+            };
+            Some(operand(kind, disp_offset, read_disp32(disp_offset)))
+        }
+        (None, None) => None,
+    };
+
+    Ok(Instruction {
+        address: ip,
+        len,
+        rip_operand,
+    })
+}
+
+/// 解码 `ip` 处的一条指令中的相对寻址操作数
+///
+/// 支持 `call/jmp rel32`、`jmp/jcc rel8`、`jcc rel32`，以及所有使用 `[rip+disp32]` 的指令
+/// （包括操作数之后带有立即数的情况，如 `mov byte ptr [rip+x], 1`）。
+pub fn decode_rip_operand(code: &[u8], ip: usize) -> Result<RipOperand, DecodeError> {
+    let instruction = decode_instruction(code, ip)?;
+    instruction.rip_operand.ok_or_else(|| {
+        DecodeError::Unsupported(format!(
+            "{} has no RIP-relative operand",
+            super::bytes_to_space_hex(&code[..instruction.len])
+        ))
+    })
+}
+
+/// 解码前 `count` 条指令
+pub fn decode_span(code: &[u8], ip: usize, count: usize) -> Result<CodeSpan, DecodeError> {
+    let mut span = CodeSpan::default();
+    for _ in 0..count {
+        span.push(decode_instruction(&code[span.len..], ip + span.len)?);
+    }
+    Ok(span)
+}
+
+/// 解码覆盖至少 `min_len` 字节所需的最少完整指令
+///
+/// 常用于确定写入跳转时需要覆盖的指令，例如 `decode_span_covering(code, ip, 5)`。
+pub fn decode_span_covering(
+    code: &[u8],
+    ip: usize,
+    min_len: usize,
+) -> Result<CodeSpan, DecodeError> {
+    let mut span = CodeSpan::default();
+    while span.len < min_len {
+        span.push(decode_instruction(&code[span.len..], ip + span.len)?);
+    }
+    Ok(span)
+}
+
+/// `code` 的前 `len` 字节是否恰好为若干条完整指令
+///
+/// 指令超出 `code` 末尾时必然越过 `len`，此时返回false。
+pub fn is_instruction_boundary(code: &[u8], ip: usize, len: usize) -> Result<bool, DecodeError> {
+    match decode_span_covering(code, ip, len) {
+        Ok(span) => Ok(span.len == len),
+        Err(DecodeError::Truncated) => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// 从内存数据源读取 `addr` 处最多 `max_len` 字节的代码
+///
+/// 代码可能位于可读区域的末尾，先按可读范围缩短读取长度再读取。
+pub fn read_code(addr: usize, max_len: usize) -> Result<Vec<u8>, DecodeError> {
+    let mut code = vec![0_u8; max_len];
+    let len = with_memory_source(|source| {
+        (1..=max_len)
+            .rev()
+            .find(|&len| source.is_readable(addr, len))
+            .filter(|&len| source.read_bytes(addr, &mut code[..len]))
+    })
+    .ok_or(DecodeError::Unreadable(addr))?;
+    code.truncate(len);
+    Ok(code)
+}
+
+/// 从内存数据源读取并解码 `addr` 处的指令
+pub fn read_rip_operand(addr: usize) -> Result<RipOperand, DecodeError> {
+    decode_rip_operand(&read_code(addr, MAX_INSTRUCTION_LEN)?, addr)
+}
+
+/// 从内存数据源读取并解码覆盖至少 `min_len` 字节的完整指令
+pub fn read_span_covering(addr: usize, min_len: usize) -> Result<CodeSpan, DecodeError> {
+    let code = read_code(addr, min_len + MAX_INSTRUCTION_LEN)?;
+    decode_span_covering(&code, addr, min_len)
+}
+
+/// 从内存数据源读取并解码 `addr` 处的前 `count` 条指令
+pub fn read_span(addr: usize, count: usize) -> Result<CodeSpan, DecodeError> {
+    let code = read_code(addr, count * MAX_INSTRUCTION_LEN)?;
+    decode_span(&code, addr, count)
+}
+
+fn unsupported(code: &[u8], pos: usize, len: usize) -> DecodeError {
+    let end = (pos + len).min(code.len());
+    DecodeError::Unsupported(super::bytes_to_space_hex(&code[pos..end]))
+}
+
+/// 单字节操作码的格式：(是否有ModRM, 立即数长度)，64位模式下无效的操作码返回None
+fn one_byte_form(opcode: u8, imm_z: usize) -> Option<(bool, usize)> {
+    Some(match opcode {
+        0x00..=0x3F => match opcode & 0b111 {
+            0..=3 => (true, 0),
+            4 => (false, 1),
+            5 => (false, imm_z),
+            _ => return None,
+        },
+        0x50..=0x5F => (false, 0),
+        0x63 => (true, 0),
+        0x68 => (false, imm_z),
+        0x69 => (true, imm_z),
+        0x6A => (false, 1),
+        0x6B => (true, 1),
+        0x6C..=0x6F => (false, 0),
+        0x80 | 0x83 => (true, 1),
+        0x81 => (true, imm_z),
+        0x84..=0x8F => (true, 0),
+        0x90..=0x99 | 0x9B..=0x9F => (false, 0),
+        0xA4..=0xA7 | 0xAA..=0xAF => (false, 0),
+        0xA8 => (false, 1),
+        0xA9 => (false, imm_z),
+        0xB0..=0xB7 => (false, 1),
+        0xB8..=0xBF => (false, imm_z),
+        0xC0 | 0xC1 | 0xC6 => (true, 1),
+        0xC7 => (true, imm_z),
+        0xC2 | 0xCA => (false, 2),
+        0xC3 | 0xC9 | 0xCB | 0xCC | 0xCF => (false, 0),
+        0xC8 => (false, 3),
+        0xCD => (false, 1),
+        0xD0..=0xD3 | 0xD8..=0xDF => (true, 0),
+        0xD7 => (false, 0),
+        0xE4..=0xE7 => (false, 1),
+        0xEC..=0xEF | 0xF1 | 0xF4 | 0xF5 | 0xF8..=0xFD => (false, 0),
+        0xF6 | 0xF7 | 0xFE | 0xFF => (true, 0),
+        _ => return None,
+    })
+}
+
+fn two_byte_has_modrm(opcode: u8) -> bool {
+    !matches!(
+        opcode,
+        0x04..=0x09 | 0x0B | 0x0E | 0x30..=0x37 | 0x77 | 0xA0..=0xA2 | 0xA8..=0xAA | 0xC8..=0xCF
+    )
+}
+
+fn two_byte_imm_len(opcode: u8) -> usize {
+    match opcode {
+        0x0F | 0x70..=0x73 | 0xA4 | 0xAC | 0xBA | 0xC2 | 0xC4..=0xC6 => 1,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use address_scanner::hex_str_to_bytes;
+
+    use super::*;
+
+    #[test]
+    fn test_decode_rip_operand() {
+        let cases: &[(&[u8], RipOperandKind, usize, usize)] = &[
+            // mov rcx, [rip+0x10]
+            (
+                &hex_str_to_bytes!("48 8B 0D 10 00 00 00"),
+                RipOperandKind::Memory,
+                7,
+                0x1017,
+            ),
+            // lea rax, [rip-0x10]
+            (
+                &hex_str_to_bytes!("48 8D 05 F0 FF FF FF"),
+                RipOperandKind::Memory,
+                7,
+                0xFF7,
+            ),
+            // mov byte ptr [rip+0x20], 1
+            (
+                &hex_str_to_bytes!("C6 05 20 00 00 00 01"),
+                RipOperandKind::Memory,
+                7,
+                0x1027,
+            ),
+            // cmp dword ptr [rip+0x20], 0x100
+            (
+                &hex_str_to_bytes!("81 3D 20 00 00 00 00 01 00 00"),
+                RipOperandKind::Memory,
+                10,
+                0x102A,
+            ),
+            // movss xmm0, [rip+0x8]
+            (
+                &hex_str_to_bytes!("F3 0F 10 05 08 00 00 00"),
+                RipOperandKind::Memory,
+                8,
+                0x1010,
+            ),
+            (
+                &hex_str_to_bytes!("E8 00 01 00 00"),
+                RipOperandKind::Call,
+                5,
+                0x1105,
+            ),
+            (
+                &hex_str_to_bytes!("E9 00 F0 FF FF"),
+                RipOperandKind::Jmp,
+                5,
+                0x5,
+            ),
+            (&hex_str_to_bytes!("EB FE"), RipOperandKind::Jmp, 2, 0x1000),
+            (
+                &hex_str_to_bytes!("0F 84 10 00 00 00"),
+                RipOperandKind::Jcc,
+                6,
+                0x1016,
+            ),
+            (
+                &hex_str_to_bytes!("FF 15 10 00 00 00"),
+                RipOperandKind::IndirectCall,
+                6,
+                0x1016,
+            ),
+            (
+                &hex_str_to_bytes!("FF 25 10 00 00 00"),
+                RipOperandKind::IndirectJmp,
+                6,
+                0x1016,
+            ),
+        ];
+        for &(code, kind, len, target) in cases {
+            let operand = decode_rip_operand(code, 0x1000).unwrap();
+            assert_eq!(
+                (operand.kind, operand.len, operand.target),
+                (kind, len, target)
+            );
+        }
+
+        assert!(matches!(
+            decode_rip_operand(&[0xC3], 0x1000),
+            Err(DecodeError::Unsupported(_))
+        ));
+        // mov rcx, [rax+0x10]
+        assert!(matches!(
+            decode_rip_operand(&[0x48, 0x8B, 0x48, 0x10], 0x1000),
+            Err(DecodeError::Unsupported(_))
+        ));
+        assert_eq!(
+            decode_rip_operand(&[0x48, 0x8B, 0x0D, 0x10], 0x1000),
+            Err(DecodeError::Truncated)
+        );
+    }
+
+    #[test]
+    fn test_instruction_len() {
+        let cases: &[(&[u8], usize)] = &[
+            (&hex_str_to_bytes!("C3"), 1),
+            (&hex_str_to_bytes!("48 89 5C 24 08"), 5),
+            (&hex_str_to_bytes!("48 81 EC 00 01 00 00"), 7),
+            (&hex_str_to_bytes!("66 81 7C 24 10 34 12"), 7),
+            (&hex_str_to_bytes!("48 B8 88 77 66 55 44 33 22 11"), 10),
+            (&hex_str_to_bytes!("F6 41 0C 0E"), 4),
+            (&hex_str_to_bytes!("F7 D8"), 2),
+            (&hex_str_to_bytes!("0F B6 8C 02 77 30 00 00"), 8),
+            (&hex_str_to_bytes!("66 0F 3A 0F C1 08"), 6),
+            (&hex_str_to_bytes!("C5 FA 10 05 10 00 00 00"), 8),
+            (&hex_str_to_bytes!("C4 E3 79 04 C0 01"), 6),
+            (&hex_str_to_bytes!("0F 1F 44 00 00"), 5),
+            (&hex_str_to_bytes!("E3 10"), 2),
+        ];
+        for &(code, len) in cases {
+            assert_eq!(
+                decode_instruction(code, 0x1000).unwrap().len,
+                len,
+                "{:02X?}",
+                code
+            );
+        }
+        assert!(
+            decode_instruction(&hex_str_to_bytes!("C5 FA 10 05 10 00 00 00"), 0x1000)
+                .unwrap()
+                .is_rip_relative()
+        );
+
+        // sub rsp, 0x28; test rcx, rcx; jz +0x10; mov rbx, rcx
+        let code = hex_str_to_bytes!("48 83 EC 28 48 85 C9 74 10 48 8B D9");
+        let span = decode_span_covering(&code, 0x1000, 5).unwrap();
+        assert_eq!(span.lens(), [4, 3]);
+        assert!(!span.has_relative_branch());
+        let span = decode_span(&code, 0x1000, 3).unwrap();
+        assert_eq!(span.len, 9);
+        assert!(span.has_relative_branch());
+        assert!(is_instruction_boundary(&code, 0x1000, 7).unwrap());
+        assert!(!is_instruction_boundary(&code, 0x1000, 5).unwrap());
+    }
+}